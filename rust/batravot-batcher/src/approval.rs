@@ -0,0 +1,304 @@
+use std::collections::HashSet;
+use std::str::Split;
+use ark_ff::Zero;
+use serde_json::{json, Value};
+use web3::types::Address;
+use batravot_lib::{ApprovalSpecifiers, G1, verifier};
+use batravot_lib::ballot::parse_options;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::{BatcherConfig, InvalidBallotPolicy};
+use crate::ballots::{apply_policy, check_record_election_id, CheckedBallots, json_options, OptionsAggregator, read_options_ballots, RejectedBallot, REPEATED_BALLOT};
+use crate::input::file::BallotRecord;
+use crate::report::write_json;
+
+/// This struct represents a ballot of an approval election, which approves a subset of the options
+/// public_key - The public key of the voter
+/// selection - The approved options, in increasing order
+/// vote_proof - The proof of the vote, made with the sum of the specifiers of the approved options
+/// Approval ballots can not be signed yet, so they are only accepted with `--allow-unsigned`
+#[derive(Clone)]
+pub struct ApprovalBallot {
+    pub voter_public_key: G1,
+    pub selection: Vec<usize>,
+    pub vote_proof: G1,
+    pub eth_address: Address,
+}
+
+impl ApprovalBallot {
+    /// This function parses an approval ballot from a JSON object
+    /// The object has the fields `publicKey`, `selection`, `voteProof` and `address`
+    /// The selection is either a list of options, e.g. `[0, 2]`, or a string, e.g. `"0,2"` as in CSV
+    pub(crate) fn from_json(json: &Value) -> Result<ApprovalBallot, String> {
+        let field = |name: &str| json.get(name).and_then(|value| value.as_str())
+            .ok_or(format!("Error reading the `{}` field", name));
+
+        let public_key = G1::from_str_c(field("publicKey")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let selection = json_options(json, "selection")?;
+        let vote_proof = G1::from_str_c(field("voteProof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(field("address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        Ok(ApprovalBallot {
+            voter_public_key: public_key,
+            selection,
+            vote_proof,
+            eth_address: address,
+        })
+    }
+
+    /// This function parses an approval ballot from a set of strings
+    /// public_key - The public key of the voter, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
+    /// selection - The approved options, in format `0,2` or `[0,2]`
+    /// vote_proof - The proof of the vote, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
+    /// address - The Ethereum address of the voter
+    pub(crate) fn from_iter(mut iter: Split<&str>) -> Result<ApprovalBallot, String> {
+        let public_key = G1::from_str_c(iter.next().ok_or("Error reading the public key")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let selection = parse_options(iter.next().ok_or("Error reading the selection")?)?;
+        let vote_proof = G1::from_str_c(iter.next().ok_or("Error reading the vote proof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(iter.next().ok_or("Error reading the address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        // Check that there are no more elements
+        if iter.next().is_some() {
+            return Err("There are more elements than expected".to_string());
+        }
+
+        Ok(ApprovalBallot {
+            voter_public_key: public_key,
+            selection,
+            vote_proof,
+            eth_address: address,
+        })
+    }
+}
+
+impl BallotRecord for ApprovalBallot {
+    const CSV_COLUMNS: &'static [&'static str] = &["version", "publicKey", "selection", "voteProof", "address"];
+
+    fn from_line(line: &str) -> Result<ApprovalBallot, String> {
+        ApprovalBallot::from_iter(line.split("|"))
+    }
+
+    fn from_record(record: &Value, election_id: u64) -> Result<ApprovalBallot, String> {
+        check_record_election_id(record, election_id)?;
+        ApprovalBallot::from_json(record)
+    }
+}
+
+/// This function checks the vote proofs of the approval ballots and decides what to do with the invalid ones
+/// A ballot whose selection is not valid, e.g. it approves an option that does not exist, is invalid
+/// It returns the ballots to include in the batch, each with whether it is valid, and the list of invalid ballots
+/// If the policy is to abort, an error is returned on the first invalid ballot
+pub(crate) fn proof_check_approval_ballots(specifiers: &ApprovalSpecifiers, ballots: Vec<(usize, ApprovalBallot)>,
+                                           policy: InvalidBallotPolicy) -> Result<CheckedBallots<ApprovalBallot>, String> {
+    let mut checked_ballots = Vec::new();
+    let mut rejected_ballots = Vec::new();
+
+    // The proofs are all checked first, so that they can be checked in parallel
+    let votes: Vec<(&G1, &[usize], &G1)> = ballots.iter()
+        .map(|(_, ballot)| (&ballot.voter_public_key, ballot.selection.as_slice(), &ballot.vote_proof))
+        .collect();
+    let valid_proofs = verifier::validate_approval_vote_proofs(&votes, specifiers);
+
+    for ((position, mut ballot), valid_proof) in ballots.into_iter().zip(valid_proofs) {
+        let reason = match specifiers.check_selection(&ballot.selection) {
+            Err(reason) => reason,
+            Ok(_) if !valid_proof => "The Vote Proof is not valid for the selection".to_string(),
+            Ok(selection) => {
+                // The ballot is valid, so we keep it, with the options in increasing order
+                ballot.selection = selection;
+                checked_ballots.push((position, ballot, true));
+                continue;
+            }
+        };
+
+        let keep = apply_policy(policy, position, &ballot.eth_address, &reason)?;
+        rejected_ballots.push(RejectedBallot {
+            position,
+            eth_address: Some(ballot.eth_address),
+            reason,
+            kept: keep,
+        });
+        // A kept ballot is only counted for the options that exist, its vote proof makes the batch invalid anyway
+        if keep {
+            ballot.selection.retain(|option| *option < specifiers.options.len());
+            checked_ballots.push((position, ballot, false));
+        }
+    }
+
+    Ok((checked_ballots, rejected_ballots))
+}
+
+/// The aggregate of the approval ballots of a batch that is read from a file
+/// Only the election proof, the voters of each option and the set of voters are kept, so that the memory does not grow with the size of the ballots
+/// The first ballot of a voter is kept and the later ones are rejected, so that a voter approves each option at most once
+pub(crate) struct ApprovalAggregator {
+    election_proof: G1,
+    voters_by_option: Vec<Vec<Address>>,
+    voters: HashSet<Address>,
+}
+
+impl ApprovalAggregator {
+    pub(crate) fn new(options: usize) -> ApprovalAggregator {
+        ApprovalAggregator {
+            election_proof: G1::zero(),
+            voters_by_option: vec![Vec::new(); options],
+            voters: HashSet::new(),
+        }
+    }
+
+    /// Returns the batched election proof of all the ballots in the batch
+    pub(crate) fn election_proof(&self) -> G1 {
+        self.election_proof
+    }
+
+    /// Returns the voters who approved each option, in the order of the options
+    pub(crate) fn voters_by_option(&self) -> &[Vec<Address>] {
+        &self.voters_by_option
+    }
+}
+
+impl OptionsAggregator for ApprovalAggregator {
+    type Ballot = ApprovalBallot;
+    type Specifiers = ApprovalSpecifiers;
+    const KIND: &'static str = "approval";
+
+    fn proof_check(specifiers: &ApprovalSpecifiers, ballots: Vec<(usize, ApprovalBallot)>, policy: InvalidBallotPolicy)
+                   -> Result<CheckedBallots<ApprovalBallot>, String> {
+        proof_check_approval_ballots(specifiers, ballots, policy)
+    }
+
+    /// This function adds the checked ballots to the batch, the voter is added to the list of each option they approve
+    /// If the voter already has a ballot in the batch, the ballot is rejected, so that a voter approves each option at most once
+    /// A kept invalid ballot is added as well, its vote proof makes the batch invalid anyway
+    fn add(&mut self, ballots: Vec<(usize, ApprovalBallot, bool)>) -> Vec<RejectedBallot> {
+        let mut repeated_ballots = Vec::new();
+        for (position, ballot, _) in ballots {
+            if !self.voters.insert(ballot.eth_address) {
+                repeated_ballots.push(RejectedBallot { position, eth_address: Some(ballot.eth_address), reason: REPEATED_BALLOT.to_string(), kept: false });
+                continue;
+            }
+            self.election_proof += &ballot.vote_proof;
+            for option in ballot.selection.iter() {
+                self.voters_by_option[*option].push(ballot.eth_address);
+            }
+        }
+        repeated_ballots
+    }
+
+    fn len(&self) -> usize {
+        self.voters.len()
+    }
+}
+
+/// This function batches the ballots of an approval election, read from the file of the configuration
+/// The batch has the list of the voters who approved each option, and the election proof of all the ballots
+pub(crate) fn run_approval(election_id: u64, options: usize, config: &BatcherConfig) -> Result<(), String> {
+    let specifiers = ApprovalSpecifiers::from(election_id, options)?;
+    let mut aggregator = ApprovalAggregator::new(options);
+    let report = read_options_ballots(election_id, &specifiers, &mut aggregator, config)?;
+
+    // Print the results
+    let election_proof = aggregator.election_proof();
+    println!("\n");
+    println!("----------------------------------------");
+    println!("Please submit the following data to the election verifier:");
+    println!("Election Proof:    {}", election_proof.solidity_repr());
+    for (option, voters) in aggregator.voters_by_option().iter().enumerate() {
+        println!("Who approved {}:    [{}]", option, voters.iter().map(|x| x.solidity_repr()).collect::<Vec<String>>().join(", "));
+    }
+    println!("----------------------------------------");
+
+    // Write the batch and the report of the invalid ballots, if requested
+    if let Some(output) = &config.output {
+        write_json(output, &approval_batch_json(election_id, &election_proof, aggregator.voters_by_option()))
+            .map_err(|err| format!("Error writing the batch to {}: {}", output, err))?;
+        println!("The batch has been written to {}", output);
+    }
+    let rejected = report.rejected();
+    report.finish(aggregator.len())?;
+    if let Some(report) = &config.report {
+        println!("The report of {} invalid ballots has been written to {}", rejected, report);
+    }
+
+    Ok(())
+}
+
+/// This function returns the batch of an approval election as JSON
+/// `votersByOption` has the list of the voters who approved each option, in the order of the options
+pub(crate) fn approval_batch_json(election_id: u64, election_proof: &G1, voters_by_option: &[Vec<Address>]) -> Value {
+    let voters_by_option: Vec<Vec<String>> = voters_by_option.iter()
+        .map(|voters| voters.iter().map(|voter| voter.solidity_repr()).collect())
+        .collect();
+    json!({
+        "electionId": election_id,
+        "votersByOption": voters_by_option,
+        "electionProof": election_proof.solidity_repr(),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use batravot_lib::{ScalarField, voter};
+    use super::*;
+
+    #[test]
+    fn batches_the_voters_of_each_option() {
+        let specifiers = ApprovalSpecifiers::from(1, 3).unwrap();
+        let ballot = |key: u64, selection: &[usize]| {
+            let prk = ScalarField::from(key);
+            let line = format!("{}|{:?}|{}|{}", voter::generate_public_key(&prk).solidity_repr(), selection,
+                               voter::generate_approval_vote_proof(&prk, &specifiers, &[0]).unwrap().solidity_repr(),
+                               Address::from_low_u64_be(key).solidity_repr());
+            ApprovalBallot::from_line(&line).unwrap()
+        };
+        let valid = |key: u64, selection: &[usize]| {
+            let prk = ScalarField::from(key);
+            ApprovalBallot {
+                vote_proof: voter::generate_approval_vote_proof(&prk, &specifiers, selection).unwrap(),
+                ..ballot(key, selection)
+            }
+        };
+
+        // The third ballot has the proof of another selection, the fourth approves an option that does not exist
+        let ballots = vec![(1, valid(1, &[2, 0])), (2, valid(2, &[1])), (3, ballot(3, &[1])), (4, ballot(4, &[0, 3]))];
+        let (checked_ballots, rejected_ballots) = proof_check_approval_ballots(&specifiers, ballots, InvalidBallotPolicy::Drop).unwrap();
+        assert_eq!(rejected_ballots.iter().map(|ballot| ballot.position).collect::<Vec<usize>>(), vec![3, 4]);
+        assert_eq!(checked_ballots[0].1.selection, vec![0, 2]);
+
+        let mut aggregator = ApprovalAggregator::new(3);
+        assert!(aggregator.add(checked_ballots).is_empty());
+        let address = Address::from_low_u64_be;
+        assert_eq!(aggregator.voters_by_option(), &[vec![address(1)], vec![address(2)], vec![address(1)]]);
+
+        let public_keys: Vec<G1> = [1u64, 2].into_iter().map(|key| voter::generate_public_key(&ScalarField::from(key))).collect();
+        let option_keys = vec![vec![&public_keys[0]], vec![&public_keys[1]], vec![&public_keys[0]]];
+        assert!(verifier::validate_approval_election_proof(&option_keys, &aggregator.election_proof(), &specifiers));
+
+        // A later ballot of the voter 1 is rejected, the first one stays in the batch
+        let repeated_ballots = aggregator.add(vec![(3, valid(1, &[1]), true)]);
+        assert_eq!(repeated_ballots.len(), 1);
+        assert_eq!(repeated_ballots[0].position, 3);
+        assert_eq!(repeated_ballots[0].reason, REPEATED_BALLOT);
+        assert!(!repeated_ballots[0].kept);
+        assert_eq!(aggregator.len(), 2);
+        assert_eq!(aggregator.voters_by_option(), &[vec![address(1)], vec![address(2)], vec![address(1)]]);
+        let option_keys = vec![vec![&public_keys[0]], vec![&public_keys[1]], vec![&public_keys[0]]];
+        assert!(verifier::validate_approval_election_proof(&option_keys, &aggregator.election_proof(), &specifiers));
+    }
+
+    #[test]
+    fn parses_the_selection() {
+        let record = json!({ "version": 1, "publicKey": "", "selection": [0, -1], "voteProof": "", "address": "" });
+        assert_eq!(json_options(&record, "selection").err(), Some("Invalid option `-1`".to_string()));
+        assert_eq!(json_options(&json!({ "selection": "[0, 2]" }), "selection"), Ok(vec![0, 2]));
+
+        let record = json!({ "version": 1, "electionId": 2, "publicKey": "", "selection": [0], "voteProof": "", "address": "" });
+        assert_eq!(ApprovalBallot::from_record(&record, 1).err(), Some("The ballot is for the election 2, not 1".to_string()));
+    }
+}
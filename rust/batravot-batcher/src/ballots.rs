@@ -1,111 +1,563 @@
-use std::str::{FromStr, Split};
-use web3::types::Address;
-use batravot_lib::{ElectionSpecifiers, G1, verifier, Vote};
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
+use serde_json::{json, Value};
+use web3::types::{Address, H256};
+use batravot_lib::{DleqProof, ElectionSpecifiers, ElectionVerifier, G1, verifier, Vote};
+use batravot_lib::ballot::{ballot_hash, check_weight, BallotLine, BALLOT_FORMAT_VERSION};
+use batravot_lib::ethereum::{BallotDomain, EthSignature, TypedBallot};
 use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
 use colored::Colorize;
+use batravot_lib::ballot::parse_options;
+use crate::{BatcherConfig, InputMode};
+use crate::input::file::{BallotReader, BallotRecord, CHUNK_SIZE, ParsedBallot};
+use crate::report::RejectionReport;
+
+/// This function checks the signatures and the vote proofs of the ballots and decides what to do with the invalid ones
+/// ballots - The ballots with their position in the input, starting from 1
+/// policy - What to do with a ballot whose signature or vote proof is not valid
+/// require_signatures - Whether the unsigned ballots are invalid, the signature of a signed ballot is always checked
+/// domain - The chain and the contract of the election the ballots are signed for, a signed ballot is invalid without it
+/// It returns the ballots to include in the batch, and the list of invalid ballots
+/// If the policy is to abort, an error is returned on the first invalid ballot
+pub(crate) fn proof_check_ballots(election_id: u64, election_specifiers: &ElectionSpecifiers, ballots: Vec<(usize, Ballot)>,
+                                  policy: InvalidBallotPolicy, require_signatures: bool, domain: Option<&BallotDomain>) -> Result<(Vec<Ballot>, Vec<RejectedBallot>), String> {
+    let mut checked_ballots = Vec::new();
+    let mut rejected_ballots = Vec::new();
 
-pub(crate) fn proof_check_ballots(election_id: u64, ballots: Vec<Ballot>) -> Vec<Ballot> {
-// Check if the proofs of the ballots are valid
-    // First, we generate the election specifiers from the election id
-    let election_specifiers = ElectionSpecifiers::from(election_id);
     // Then, we validate each vote proof in order to be sure that the vote is valid
-    // If the vote proof is not valid, we ask the user what to do with the ballot
-    let checked_ballots : Vec<Ballot> = ballots.into_iter().enumerate().filter(|(i, ballot)| {
-        // Check if the vote proof is valid
-        let proof_is_correct = match ballot.vote {
-            Vote::For => verifier::validate_election_proof(&vec!(&ballot.voter_public_key), &Vec::new(), &ballot.vote_proof, &election_specifiers),
-            Vote::Against => verifier::validate_election_proof(&Vec::new(), &vec!(&ballot.voter_public_key), &ballot.vote_proof, &election_specifiers),
-        };
+    // The proofs are all checked first, so that they can be checked in parallel
+    // The ballots with a DLEQ proof are checked with it, which is cheaper than the pairing check of the other ballots
+    // The proofs of the weighted ballots are checked against the public keys scaled by the weights
+    let proof_keys: Vec<G1> = ballots.iter().map(|(_, ballot)| ballot.proof_key()).collect();
+    let dleq_votes: Vec<(&G1, Vote, &G1, &DleqProof)> = ballots.iter().zip(proof_keys.iter())
+        .filter_map(|((_, ballot), proof_key)| ballot.dleq_proof.as_ref().map(|dleq_proof| (proof_key, ballot.vote, &ballot.vote_proof, dleq_proof)))
+        .collect();
+    let votes: Vec<(&G1, Vote, &G1)> = ballots.iter().zip(proof_keys.iter())
+        .filter(|((_, ballot), _)| ballot.dleq_proof.is_none())
+        .map(|((_, ballot), proof_key)| (proof_key, ballot.vote, &ballot.vote_proof))
+        .collect();
+    let mut valid_dleq_proofs = verifier::validate_vote_dleq_proofs(&dleq_votes, election_specifiers).into_iter();
+    let mut valid_pairing_proofs = verifier::validate_vote_proofs(&votes, election_specifiers).into_iter();
+    let valid_proofs: Vec<bool> = ballots.iter()
+        .map(|(_, ballot)| match ballot.dleq_proof {
+            Some(_) => valid_dleq_proofs.next(),
+            None => valid_pairing_proofs.next(),
+        }.unwrap_or(false))
+        .collect();
 
-        // In case the vote proof is not valid, ask the user what to do with the ballot
-        let keep = if !proof_is_correct {
-            eprintln!("{}", format!("\nThe Vote Proof of the #{} ballot with Address {} is not valid", i + 1, ballot.eth_address.solidity_repr()).red());
-            // Ask whether the user wants to continue, remove the ballot, or exit the program
-            println!("What do you want to do? (k)eep the ballot, (r)emove the ballot, (e)xit");
-            let mut choice = String::new();
-            std::io::stdin().read_line(&mut choice)
-                .unwrap_or_else( |_| {eprintln!("{}", "Failed to read selected choice. Will remove by default.".red()); 0});
-            if choice.to_lowercase().contains("k") {
-                // Keep
-                println!("The ballot will be included");
-                true // The ballot will be included
-
-            } else if choice.to_lowercase().contains("e") {
-                // Exit the program
-                println!("The program will exit");
-                std::process::exit(0);
-                // We don't need to return anything here, because the program will exit
-            } else {
-                // Remove the ballot
-                println!("The ballot will be removed");
-                false // The ballot will be removed
+    // If the signature or the vote proof is not valid, we act according to the policy
+    for ((position, ballot), valid_proof) in ballots.into_iter().zip(valid_proofs) {
+        // Check if the signature and the vote proof are valid
+        let reason = match ballot.check_required_signature(election_id, domain, require_signatures) {
+            Err(reason) => reason,
+            Ok(()) if !valid_proof => ballot.invalid_proof_reason().to_string(),
+            Ok(()) => {
+                // The ballot is valid, so we keep it
+                checked_ballots.push(ballot);
+                continue;
             }
-        } else {
-            // The vote proof is valid, so we keep the ballot
-            true
         };
 
-        keep
-    }).map(|(_, ballot)| ballot).collect();
+        let keep = apply_policy(policy, position, &ballot.eth_address, &reason)?;
+
+        rejected_ballots.push(RejectedBallot {
+            position,
+            eth_address: Some(ballot.eth_address),
+            reason,
+            kept: keep,
+        });
+        if keep {
+            checked_ballots.push(ballot);
+        }
+    }
+
+    Ok((checked_ballots, rejected_ballots))
+}
+
+/// This function decides what to do with an invalid ballot, based on the policy
+/// position - The position of the ballot in the input
+/// address - The Ethereum address of the ballot
+/// reason - Why the ballot is not valid
+/// It returns whether the ballot should be kept, or an error if the policy is to abort
+pub(crate) fn apply_policy(policy: InvalidBallotPolicy, position: usize, address: &Address, reason: &str) -> Result<bool, String> {
+    match policy {
+        InvalidBallotPolicy::Ask => Ok(ask_keep_ballot(position, address, reason)),
+        InvalidBallotPolicy::Keep => {
+            eprintln!("{}", format!("{} for the ballot #{} with Address {}. The ballot will be included", reason, position, address.solidity_repr()).red());
+            Ok(true)
+        }
+        InvalidBallotPolicy::Drop => {
+            eprintln!("{}", format!("{} for the ballot #{} with Address {}. The ballot will be removed", reason, position, address.solidity_repr()).red());
+            Ok(false)
+        }
+        InvalidBallotPolicy::Abort => {
+            Err(format!("{} for the ballot #{} with Address {}", reason, position, address.solidity_repr()))
+        }
+    }
+}
+
+/// In case the signature or the vote proof is not valid, ask the user what to do with the ballot
+/// Returns whether the ballot should be kept, exits the program if the user asks to
+fn ask_keep_ballot(position: usize, address: &Address, reason: &str) -> bool {
+    eprintln!("{}", format!("\n{} for the #{} ballot with Address {}", reason, position, address.solidity_repr()).red());
+    // Ask whether the user wants to continue, remove the ballot, or exit the program
+    println!("What do you want to do? (k)eep the ballot, (r)emove the ballot, (e)xit");
+    let mut choice = String::new();
+    std::io::stdin().read_line(&mut choice)
+        .unwrap_or_else( |_| {eprintln!("{}", "Failed to read selected choice. Will remove by default.".red()); 0});
+    if choice.to_lowercase().contains("k") {
+        // Keep
+        println!("The ballot will be included");
+        true // The ballot will be included
+
+    } else if choice.to_lowercase().contains("e") {
+        // Exit the program
+        println!("The program will exit");
+        std::process::exit(0);
+        // We don't need to return anything here, because the program will exit
+    } else {
+        // Remove the ballot
+        println!("The ballot will be removed");
+        false // The ballot will be removed
+    }
+}
+
+/// This function checks the version of a ballot record or document, the version is required
+/// It is set in the `version` field of each record, or of the whole document in JSON
+pub(crate) fn check_format_version(version: Option<&Value>) -> Result<(), String> {
+    match version.and_then(json_u64) {
+        Some(BALLOT_FORMAT_VERSION) => Ok(()),
+        Some(version) => Err(format!("The ballot format version {} is not supported, the supported version is {}", version, BALLOT_FORMAT_VERSION)),
+        None => Err("The ballot format version is missing, the `version` field must be set".to_string()),
+    }
+}
+
+/// Returns the number in the value, which can be either a JSON number or a string, as in CSV
+pub(crate) fn json_u64(value: &Value) -> Option<u64> {
+    value.as_u64().or_else(|| value.as_str().and_then(|value| value.trim().parse().ok()))
+}
+
+/// This function checks the optional `electionId` field of a ballot record, if it is set it must be the id of the election being batched
+pub(crate) fn check_record_election_id(record: &Value, election_id: u64) -> Result<(), String> {
+    // An empty CSV column is the same as a missing field
+    if let Some(record_election_id) = record.get("electionId").filter(|value| !value.is_null() && value.as_str() != Some("")) {
+        let record_election_id = json_u64(record_election_id)
+            .ok_or(format!("Error parsing the election id: {}", record_election_id))?;
+        if record_election_id != election_id {
+            return Err(format!("The ballot is for the election {}, not {}", record_election_id, election_id));
+        }
+    }
+    Ok(())
+}
+
+/// This function reads the options of an approval or a ranked ballot from a field of a JSON object, either a list `[0, 2]` or a string `"0,2"`, as in CSV
+pub(crate) fn json_options(json: &Value, field: &str) -> Result<Vec<usize>, String> {
+    match json.get(field) {
+        Some(Value::Array(options)) => options.iter()
+            .map(|option| option.as_u64().and_then(|option| usize::try_from(option).ok()).ok_or(format!("Invalid option `{}`", option)))
+            .collect(),
+        Some(Value::String(options)) => parse_options(options),
+        _ => Err(format!("Error reading the `{}` field", field)),
+    }
+}
+
+/// The ballots to include in the batch, each with its position and whether it is valid, and the list of invalid ballots
+pub(crate) type CheckedBallots<B> = (Vec<(usize, B, bool)>, Vec<RejectedBallot>);
+
+/// Why a later ballot of a voter is rejected, the approval and ranked ballots can not be signed, so nothing proves that its sender controls the address
+pub(crate) const REPEATED_BALLOT: &str = "The voter already has a ballot in the batch, an unsigned ballot can not replace it";
+
+/// The aggregate of the ballots of an election with options, an approval or a ranked election, which are read from a file
+/// Ballot - The kind of ballot of the election
+/// Specifiers - The specifiers of the options of the election, the vote proofs are checked with them
+/// KIND - The name of the kind of election, for the messages
+pub(crate) trait OptionsAggregator {
+    type Ballot: BallotRecord;
+    type Specifiers;
+    const KIND: &'static str;
+
+    /// This function checks the vote proofs of the ballots and decides what to do with the invalid ones
+    /// It returns the ballots to include in the batch, each with its position and whether it is valid, and the list of invalid ballots
+    /// If the policy is to abort, an error is returned on the first invalid ballot
+    fn proof_check(specifiers: &Self::Specifiers, ballots: Vec<(usize, Self::Ballot)>, policy: InvalidBallotPolicy)
+                   -> Result<CheckedBallots<Self::Ballot>, String>;
 
+    /// This function adds the checked ballots to the batch, an invalid ballot is only there if the policy kept it
+    /// The first ballot of a voter stays in the batch, the later ones are returned as rejected, see `REPEATED_BALLOT`
+    fn add(&mut self, ballots: Vec<(usize, Self::Ballot, bool)>) -> Vec<RejectedBallot>;
 
-    checked_ballots
+    /// Returns the number of ballots in the batch, one for each voter
+    fn len(&self) -> usize;
+}
+
+/// This function reads the ballots of an approval or a ranked election from the file of the configuration, checks them and adds them to the aggregator
+/// It returns the report of the invalid ballots, it is finished once the batch is written
+pub(crate) fn read_options_ballots<A: OptionsAggregator>(election_id: u64, specifiers: &A::Specifiers, aggregator: &mut A,
+                                                         config: &BatcherConfig) -> Result<RejectionReport, String> {
+    let file_path = match &config.input_mode {
+        InputMode::File(file_path) => file_path,
+        InputMode::Stdin => return Err(format!("The {} ballots can only be read from a file, `--file` must be set", A::KIND)),
+    };
+
+    // Read the ballots in chunks, check them and add the valid ones to the batch
+    let mut report = RejectionReport::create(config.report.as_deref(), election_id)?;
+    let mut reader: BallotReader<A::Ballot> = BallotReader::open(file_path, election_id, CHUNK_SIZE)?;
+    println!("Reading the {} ballots in the {} format", A::KIND, reader.format);
+    let show_progress = io::stderr().is_terminal();
+    while let Some(ballots) = reader.next_chunk()? {
+        check_options_ballots(specifiers, ballots, config.on_invalid, aggregator, &mut report)?;
+        if show_progress {
+            eprint!("\rRead {}% of the file, {} ballots accepted, {} rejected", reader.progress(), aggregator.len(), report.rejected());
+        }
+    }
+    if show_progress {
+        eprintln!();
+    }
+    // The invalid ballots that were kept are both accepted and rejected
+    println!("{}", format!("\nFinished reading {} ballots from the file", aggregator.len() + report.rejected() - report.kept()).green());
+
+    Ok(report)
+}
+
+/// This function checks a chunk of approval or ranked ballots and adds the ones to include to the batch
+/// The ballots that could not be parsed are rejected, unless the policy is to ask or to abort, then they stop the batcher
+fn check_options_ballots<A: OptionsAggregator>(specifiers: &A::Specifiers, ballots: Vec<ParsedBallot<A::Ballot>>, policy: InvalidBallotPolicy,
+                                               aggregator: &mut A, report: &mut RejectionReport) -> Result<(), String> {
+    let mut rejected_ballots = Vec::new();
+    let mut parsed_ballots = Vec::new();
+    for (position, ballot) in ballots {
+        match ballot {
+            Ok(ballot) => parsed_ballots.push((position, ballot)),
+            Err(err) if policy == InvalidBallotPolicy::Ask || policy == InvalidBallotPolicy::Abort => {
+                return Err(err);
+            }
+            Err(err) => {
+                eprintln!("{}", format!("{}. The ballot will be removed", err).red());
+                rejected_ballots.push(RejectedBallot { position, eth_address: None, reason: err, kept: false });
+            }
+        }
+    }
+
+    let (checked_ballots, mut invalid_ballots) = A::proof_check(specifiers, parsed_ballots, policy)?;
+    let mut repeated_ballots = aggregator.add(checked_ballots);
+    for ballot in repeated_ballots.iter() {
+        eprintln!("{}", format!("{}. The ballot #{} will be removed", ballot.reason, ballot.position).red());
+    }
+    rejected_ballots.append(&mut invalid_ballots);
+    rejected_ballots.append(&mut repeated_ballots);
+    report.add(rejected_ballots)
+}
+
+/// What to do with a ballot that is not valid
+/// Ask - Ask the user on the standard input, this is the default
+/// Drop - Remove the ballot from the batch
+/// Keep - Include the ballot in the batch anyway, a ballot that could not be parsed is still removed
+/// Abort - Stop the batcher with an error
+#[derive(Clone, Copy, PartialEq)]
+pub enum InvalidBallotPolicy {
+    Ask,
+    Drop,
+    Keep,
+    Abort,
+}
+
+impl FromStr for InvalidBallotPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ask" => Ok(InvalidBallotPolicy::Ask),
+            "drop" => Ok(InvalidBallotPolicy::Drop),
+            "keep" => Ok(InvalidBallotPolicy::Keep),
+            "abort" => Ok(InvalidBallotPolicy::Abort),
+            _ => Err(format!("Invalid policy for invalid ballots: {}, expected `drop`, `keep` or `abort`", s)),
+        }
+    }
+}
+
+/// This struct represents a ballot that was found to be invalid
+/// position - The position of the ballot in the input, for files it is the line number
+/// eth_address - The address of the voter, if the ballot could be parsed
+/// reason - Why the ballot is invalid
+/// kept - Whether the ballot was included in the batch anyway
+pub(crate) struct RejectedBallot {
+    pub position: usize,
+    pub eth_address: Option<Address>,
+    pub reason: String,
+    pub kept: bool,
 }
 
 /// This struct represents a ballot
 /// public_key - The public key of the voter
 /// vote - The vote of the voter
 /// vote_proof - The proof of the vote
+/// signature - The EIP-712 signature of the ballot by the Ethereum account of the voter, if the ballot is signed
+/// dleq_proof - The proof that the vote proof is the specifier of the vote raised to the private key of the public key, if the voter provides it
+/// weight - The declared weight of the vote, if the ballot is weighted, the vote proof is then scaled by the weight
+#[derive(Clone)]
 pub struct Ballot {
     pub voter_public_key: G1,
     pub vote: Vote,
     pub vote_proof: G1,
-    pub eth_address: Address
+    pub eth_address: Address,
+    pub signature: Option<BallotSignature>,
+    pub dleq_proof: Option<DleqProof>,
+    pub weight: Option<u64>,
+}
+
+/// The signature of a ballot, it proves that the voter controls the address of the ballot
+/// sequence - The number of the ballot among the ballots of the voter, a later ballot must have a higher sequence
+/// signature - The signature of the `TypedBallot` with the election id, the vote, the vote proof and the sequence
+#[derive(Clone, Copy)]
+pub struct BallotSignature {
+    pub sequence: u64,
+    pub signature: EthSignature,
+}
+
+impl BallotSignature {
+    /// This function parses the signature of a ballot, from its sequence and its signature in hex format
+    /// The ballot is not signed if both are missing, a sequence without a signature or the other way around is an error
+    pub(crate) fn parse(sequence: Option<u64>, signature: Option<&str>) -> Result<Option<BallotSignature>, String> {
+        match (sequence, signature) {
+            (None, None) => Ok(None),
+            (Some(sequence), Some(signature)) => Ok(Some(BallotSignature {
+                sequence,
+                signature: EthSignature::from_str_c(signature)
+                    .map_err(|err| format!("Error parsing the signature: {}", err))?,
+            })),
+            (Some(_), None) => Err("The ballot has a sequence but no signature".to_string()),
+            (None, Some(_)) => Err("The ballot has a signature but no sequence".to_string()),
+        }
+    }
 }
 
 impl Ballot {
-    /// This function parses a ballot from a set of strings
-    /// public_key - The public key of the voter, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
-    /// vote - The vote of the voter, is either 0 (for `against`) or 1 (for `For`)
-    /// vote_proof - The proof of the vote, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
-    /// It returns an error if the strings are not valid
-    /// Otherwise, it returns the ballot
-    pub(crate) fn from_iter(mut iter: Split<&str>) -> Result<Ballot, String> {
+    /// This function checks that the vote proof is valid for the vote and the public key of the ballot
+    /// If the ballot has a DLEQ proof, only the DLEQ proof is checked, which does not need a pairing
+    /// The pairing check uses the verifier of the election, so that the specifiers are not prepared again for each ballot
+    pub(crate) fn verify(&self, specifiers: &ElectionSpecifiers, election_verifier: &ElectionVerifier) -> bool {
+        let proof_key = self.proof_key();
+        match &self.dleq_proof {
+            Some(dleq_proof) => verifier::validate_vote_dleq_proofs(&[(&proof_key, self.vote, &self.vote_proof, dleq_proof)], specifiers)[0],
+            None => election_verifier.validate_vote_proof(&proof_key, self.vote, &self.vote_proof),
+        }
+    }
 
-        // Parse the public key
-        let public_key_str = iter.next()
-            .ok_or("Error reading the public key")?;
-        let public_key = G1::from_str_c(public_key_str)
-            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+    /// Returns the key the vote proof is checked against, the public key scaled by the weight if the ballot is weighted
+    pub(crate) fn proof_key(&self) -> G1 {
+        match self.weight {
+            Some(weight) => verifier::weighted_public_key(&self.voter_public_key, weight),
+            None => self.voter_public_key,
+        }
+    }
 
-        // Parse the vote
-        let vote_str = iter.next()
-            .ok_or("Error reading the vote")?;
-        let vote = Vote::from_str(vote_str)
-            .map_err(|err| format!("Error parsing the vote: {}", err))?;
+    /// Returns why the ballot is rejected if `verify` fails
+    pub(crate) fn invalid_proof_reason(&self) -> &'static str {
+        match (&self.dleq_proof, self.weight) {
+            (Some(_), None) => "The DLEQ proof of the Vote Proof is not valid",
+            (Some(_), Some(_)) => "The DLEQ proof of the Vote Proof is not valid for the declared weight",
+            (None, None) => "The Vote Proof is not valid",
+            (None, Some(_)) => "The Vote Proof is not valid for the declared weight",
+        }
+    }
 
-        // Parse the vote proof
-        let vote_proof_str = iter.next()
-            .ok_or("Error reading the vote proof")?;
-        let vote_proof = G1::from_str_c(vote_proof_str)
-            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+    /// This function returns the hash of the ballot, it identifies the ballot in the receipts given to the voters
+    pub(crate) fn hash(&self) -> H256 {
+        ballot_hash(&self.voter_public_key, self.vote, &self.vote_proof, &self.eth_address)
+    }
 
-        // Parse the address
-        let address_str = iter.next()
-            .ok_or("Error reading the address")?;
-        let address = Address::from_str_c(address_str)
-            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+    /// This function checks that the ballot is signed by the Ethereum account of its address
+    /// The signature is for the election being batched, so that a ballot of another election is rejected
+    /// The domain of the signature has the chain and the contract of the election, without them the signature can not be checked
+    pub(crate) fn check_signature(&self, election_id: u64, domain: Option<&BallotDomain>) -> Result<(), String> {
+        let signature = self.signature.as_ref()
+            .ok_or("The signature is missing")?;
+        let domain = domain
+            .ok_or("The signature can not be checked, the chain and the contract of the election are not set")?;
+        let typed_ballot = TypedBallot {
+            election_id,
+            vote: self.vote,
+            vote_proof: self.vote_proof,
+            sequence: signature.sequence,
+        };
+        match typed_ballot.recover_signer(domain, &signature.signature) {
+            Ok(signer) if signer == self.eth_address => Ok(()),
+            _ => Err("The signature is not from the address of the ballot".to_string()),
+        }
+    }
 
-        // Check that there are no more elements
-        if iter.next().is_some() {
-            return Err("There are more elements than expected".to_string());
+    /// This function checks the signature of the ballot if it is signed, or if it must be
+    /// A weighted ballot must always be signed: anyone can scale its vote proof and its weight together, only the signature of the vote proof binds the weight to the voter
+    /// require_signatures - Whether all the ballots must be signed
+    pub(crate) fn check_required_signature(&self, election_id: u64, domain: Option<&BallotDomain>, require_signatures: bool) -> Result<(), String> {
+        if self.weight.is_some() && self.signature.is_none() {
+            return Err("A weighted ballot must be signed, otherwise its weight is not bound to the voter".to_string());
+        }
+        if require_signatures || self.signature.is_some() {
+            self.check_signature(election_id, domain)?;
         }
+        Ok(())
+    }
+
+    /// This function parses a ballot from a JSON object
+    /// The object has the fields `publicKey`, `vote`, `voteProof` and `address`, in the same formats as in the ballot files
+    /// The `sequence` and `signature` fields are set if the ballot is signed, the sequence is either a number or a string
+    /// The `dleqProof` field is optional, in the format `[c,s]`
+    /// The `weight` field is set if the ballot is weighted, either as a number or a string
+    pub(crate) fn from_json(json: &Value) -> Result<Ballot, String> {
+        let field = |name: &str| json.get(name).and_then(|value| value.as_str())
+            .ok_or(format!("Error reading the `{}` field", name));
+
+        let public_key = G1::from_str_c(field("publicKey")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let vote = Vote::from_str(field("vote")?)
+            .map_err(|err| format!("Error parsing the vote: {}", err))?;
+        let vote_proof = G1::from_str_c(field("voteProof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(field("address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        // An empty CSV column is the same as a missing field
+        let sequence = json.get("sequence").filter(|value| !value.is_null() && value.as_str() != Some(""))
+            .map(|value| json_u64(value).ok_or(format!("Error parsing the sequence: {}", value)))
+            .transpose()?;
+        let signature = json.get("signature").and_then(|value| value.as_str()).filter(|value| !value.is_empty());
+        let signature = BallotSignature::parse(sequence, signature)?;
+        let dleq_proof = json.get("dleqProof").and_then(|value| value.as_str()).filter(|value| !value.is_empty())
+            .map(|value| DleqProof::from_str_c(value).map_err(|err| format!("Error parsing the DLEQ proof: {}", err)))
+            .transpose()?;
+        let weight = json.get("weight").filter(|value| !value.is_null() && value.as_str() != Some(""))
+            .map(|value| json_u64(value).ok_or(format!("Error parsing the weight: {}", value)).and_then(check_weight))
+            .transpose()?;
 
         Ok(Ballot {
             voter_public_key: public_key,
             vote,
             vote_proof,
-            eth_address: address
+            eth_address: address,
+            signature,
+            dleq_proof,
+            weight,
+        })
+    }
+
+    /// This function parses a ballot record of a ballot file, in the JSON, JSON Lines or CSV format
+    /// The record is a JSON object with the same fields as for `from_json`, in CSV each column is a field
+    /// The `electionId` field is optional, but if it is set, it must be the id of the election being batched
+    /// Any other field, e.g. `metadata`, is ignored
+    pub(crate) fn from_record(record: &Value, election_id: u64) -> Result<Ballot, String> {
+        check_record_election_id(record, election_id)?;
+        Ballot::from_json(record)
+    }
+
+    /// This function returns the ballot as a JSON object, in the format read by `from_json`
+    pub(crate) fn to_json(&self) -> Value {
+        let mut json = json!({
+            "publicKey": self.voter_public_key.solidity_repr(),
+            "vote": format!("{:?}", self.vote),
+            "voteProof": self.vote_proof.solidity_repr(),
+            "address": self.eth_address.solidity_repr(),
+        });
+        if let Some(signature) = &self.signature {
+            json["sequence"] = json!(signature.sequence);
+            json["signature"] = json!(signature.signature.solidity_repr());
+        }
+        if let Some(dleq_proof) = &self.dleq_proof {
+            json["dleqProof"] = json!(dleq_proof.solidity_repr());
+        }
+        if let Some(weight) = self.weight {
+            json["weight"] = json!(weight);
+        }
+        json
+    }
+
+    /// This function parses a ballot from a line of a ballot file, see `BallotLine` for the format
+    /// It returns an error if the line is not valid
+    /// Otherwise, it returns the ballot
+    pub(crate) fn from_line(line: &str) -> Result<Ballot, String> {
+        let line = BallotLine::parse(line)?;
+        Ok(Ballot {
+            voter_public_key: line.public_key,
+            vote: line.vote,
+            vote_proof: line.vote_proof,
+            eth_address: line.address,
+            signature: line.signature.map(|(sequence, signature)| BallotSignature { sequence, signature }),
+            dleq_proof: line.dleq_proof,
+            weight: line.weight,
         })
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use batravot_lib::{ScalarField, voter};
+    use super::*;
+
+    /// Returns an unsigned ballot of the voter with the private key, the vote proof is valid for the election 1 only if `valid` is set
+    fn ballot(prk: u64, valid: bool) -> Ballot {
+        let prk = ScalarField::from(prk);
+        let specifiers = ElectionSpecifiers::from(1);
+        let specifier = if valid { &specifiers.forr.0 } else { &specifiers.against.0 };
+        Ballot {
+            voter_public_key: voter::generate_public_key(&prk),
+            vote: Vote::For,
+            vote_proof: voter::generate_vote_proof(&prk, specifier),
+            eth_address: Address::from_low_u64_be(1),
+            signature: None,
+            dleq_proof: None,
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn applies_the_policy() {
+        let address = Address::from_low_u64_be(1);
+        assert_eq!(apply_policy(InvalidBallotPolicy::Keep, 3, &address, "The Vote Proof is not valid"), Ok(true));
+        assert_eq!(apply_policy(InvalidBallotPolicy::Drop, 3, &address, "The Vote Proof is not valid"), Ok(false));
+        assert_eq!(apply_policy(InvalidBallotPolicy::Abort, 3, &address, "The Vote Proof is not valid"),
+            Err(format!("The Vote Proof is not valid for the ballot #3 with Address {}", address.solidity_repr())));
+    }
+
+    #[test]
+    fn checks_the_ballots_with_the_policy() {
+        let specifiers = ElectionSpecifiers::from(1);
+        let ballots = || vec![(1, ballot(3, true)), (2, ballot(4, false)), (4, ballot(5, true))];
+
+        // The invalid ballot is reported in each case, and only included with `keep`
+        for (policy, included) in [(InvalidBallotPolicy::Keep, 3), (InvalidBallotPolicy::Drop, 2)] {
+            let (checked_ballots, rejected_ballots) = proof_check_ballots(1, &specifiers, ballots(), policy, false, None).unwrap();
+            assert_eq!(checked_ballots.len(), included);
+            assert_eq!(rejected_ballots.len(), 1);
+            assert_eq!((rejected_ballots[0].position, rejected_ballots[0].kept), (2, policy == InvalidBallotPolicy::Keep));
+            assert_eq!(rejected_ballots[0].reason, "The Vote Proof is not valid");
+        }
+
+        let err = proof_check_ballots(1, &specifiers, ballots(), InvalidBallotPolicy::Abort, false, None).err().unwrap();
+        assert!(err.starts_with("The Vote Proof is not valid for the ballot #2"));
+
+        // The unsigned ballots are invalid when the signatures are required
+        let (checked_ballots, rejected_ballots) = proof_check_ballots(1, &specifiers, ballots(), InvalidBallotPolicy::Drop, true, None).unwrap();
+        assert!(checked_ballots.is_empty());
+        assert_eq!(rejected_ballots.iter().map(|ballot| ballot.reason.as_str()).collect::<Vec<_>>(),
+            vec!["The signature is missing", "The signature is missing", "The signature is missing"]);
+
+        // A weighted ballot must be signed even when the unsigned ballots are allowed
+        let prk = ScalarField::from(6u64);
+        let weighted = Ballot {
+            vote_proof: voter::generate_weighted_vote_proof(&prk, &specifiers.forr.0, 9),
+            weight: Some(9),
+            ..ballot(6, true)
+        };
+        let (checked_ballots, rejected_ballots) = proof_check_ballots(1, &specifiers, vec![(1, weighted)], InvalidBallotPolicy::Drop, false, None).unwrap();
+        assert!(checked_ballots.is_empty());
+        assert_eq!(rejected_ballots[0].reason, "A weighted ballot must be signed, otherwise its weight is not bound to the voter");
+    }
+
+    #[test]
+    fn parses_the_policy() {
+        assert!(InvalidBallotPolicy::from_str(" Keep ") == Ok(InvalidBallotPolicy::Keep));
+        assert!(InvalidBallotPolicy::from_str("abort") == Ok(InvalidBallotPolicy::Abort));
+        assert!(InvalidBallotPolicy::from_str("ignore").is_err());
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter;
+use ark_ff::Zero;
+use web3::types::{Address, H256};
+use batravot_lib::{batcher, ElectionSpecifiers, ElectionVerifier, G1, Vote};
+use batravot_lib::ethereum::BallotDomain;
+use crate::ballots::Ballot;
+
+/// The batch of the accepted ballots of a single election
+/// Ballots are added one by one, only after their vote proof has been checked
+/// If a voter sends several ballots, only the last one is kept, same as the contract only keeps the last vote
+/// A signed ballot only replaces the ballot of the voter if its sequence is higher, so that an older ballot can not be replayed
+/// An unsigned ballot never replaces the ballot of the voter, as anyone can send it for the address
+pub(crate) struct ElectionBatch {
+    pub election_id: u64,
+    specifiers: ElectionSpecifiers,
+    // The verifier of the vote proofs, with the specifiers prepared once for all the ballots
+    verifier: ElectionVerifier,
+    // Whether the unsigned ballots are rejected
+    require_signatures: bool,
+    // The chain and the contract of the election, the ballots are signed for them
+    domain: Option<BallotDomain>,
+    ballots: Vec<Ballot>,
+    // The receipt of each ballot in `ballots`
+    receipts: Vec<Receipt>,
+    // The position of the ballot of each voter in `ballots`
+    positions: HashMap<Address, usize>,
+    // The number of ballots admitted so far, including the replaced ones
+    admitted: u64,
+    // The sum of the vote proofs of `ballots`, kept up to date on each admission
+    election_proof: G1,
+    // The number of ballots that were rejected
+    pub rejected: usize,
+}
+
+/// The receipt of an admitted ballot, the voter can use it to check later that their ballot is in the batch
+/// sequence - The number of the ballot in the order of admission, starting from 1
+/// ballot_hash - The hash of the admitted ballot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Receipt {
+    pub sequence: u64,
+    pub ballot_hash: H256,
+}
+
+impl ElectionBatch {
+    pub(crate) fn new(election_id: u64, require_signatures: bool, domain: Option<BallotDomain>) -> ElectionBatch {
+        let specifiers = ElectionSpecifiers::from(election_id);
+        ElectionBatch {
+            election_id,
+            verifier: ElectionVerifier::new(&specifiers),
+            specifiers,
+            require_signatures,
+            domain,
+            ballots: Vec::new(),
+            receipts: Vec::new(),
+            positions: HashMap::new(),
+            admitted: 0,
+            election_proof: G1::zero(),
+            rejected: 0,
+        }
+    }
+
+    /// This function checks the signature, the sequence and the vote proof of the ballot, without adding it to the batch
+    /// Returns an error with the reason if the ballot is not valid
+    pub(crate) fn check(&self, ballot: &Ballot) -> Result<(), String> {
+        ballot.check_required_signature(self.election_id, self.domain.as_ref(), self.require_signatures)?;
+        // Nothing proves that the sender of an unsigned ballot controls its address, so it can not replace the ballot of the voter
+        if let Some(position) = self.positions.get(&ballot.eth_address) {
+            match (&ballot.signature, &self.ballots[*position].signature) {
+                (None, _) => return Err("The voter already has a ballot in the batch, only a signed ballot can replace it".to_string()),
+                (Some(signature), Some(current_signature)) if signature.sequence <= current_signature.sequence => {
+                    return Err(format!("The sequence {} of the ballot is not higher than the sequence {} of the ballot of the voter in the batch", signature.sequence, current_signature.sequence));
+                }
+                _ => {}
+            }
+        }
+        if !ballot.verify(&self.specifiers, &self.verifier) {
+            return Err(ballot.invalid_proof_reason().to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns the receipt the ballot gets if it is the next one added to the batch
+    pub(crate) fn next_receipt(&self, ballot: &Ballot) -> Receipt {
+        Receipt {
+            sequence: self.admitted + 1,
+            ballot_hash: ballot.hash(),
+        }
+    }
+
+    /// This function adds the ballot to the batch with its receipt, without checking the vote proof
+    /// It is used for the ballots that were already checked, e.g. when they are restored from the store
+    pub(crate) fn insert(&mut self, ballot: Ballot, receipt: Receipt) {
+        self.admitted = self.admitted.max(receipt.sequence);
+        self.election_proof += &ballot.vote_proof;
+        match self.positions.get(&ballot.eth_address) {
+            Some(position) => {
+                self.election_proof -= &self.ballots[*position].vote_proof;
+                self.ballots[*position] = ballot;
+                self.receipts[*position] = receipt;
+            }
+            None => {
+                self.positions.insert(ballot.eth_address, self.ballots.len());
+                self.ballots.push(ballot);
+                self.receipts.push(receipt);
+            }
+        }
+    }
+
+    /// Returns the receipt of the ballot of the voter currently in the batch, if there is one
+    pub(crate) fn receipt(&self, voter: &Address) -> Option<Receipt> {
+        self.positions.get(voter).map(|position| self.receipts[*position])
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.ballots.len()
+    }
+
+    /// Returns the batched election proof of all the ballots in the batch
+    pub(crate) fn election_proof(&self) -> G1 {
+        self.election_proof
+    }
+
+    /// Returns the list of who voted `For` and who voted `Against`
+    pub(crate) fn voters(&self) -> (Vec<Address>, Vec<Address>) {
+        let mut for_voters = Vec::new();
+        let mut against_voters = Vec::new();
+        for ballot in self.ballots.iter() {
+            match ballot.vote {
+                Vote::For => for_voters.push(ballot.eth_address),
+                Vote::Against => against_voters.push(ballot.eth_address),
+            }
+        }
+        (for_voters, against_voters)
+    }
+
+    /// Returns the weights of the `For` and the `Against` votes, in the order of `voters`, if any ballot is weighted
+    pub(crate) fn weights(&self) -> Option<(Vec<u64>, Vec<u64>)> {
+        vote_weights(self.ballots.iter().map(|ballot| (ballot.vote, ballot.weight)))
+    }
+}
+
+/// This function returns the weights of the `For` and the `Against` votes, in the order of the votes
+/// The weights are those the vote proofs are scaled by, so a vote that is not weighted has the weight 1
+/// If no vote is weighted, there are no weights, so that the batch is the same as without weights
+fn vote_weights(votes: impl Iterator<Item = (Vote, Option<u64>)> + Clone) -> Option<(Vec<u64>, Vec<u64>)> {
+    if votes.clone().all(|(_, weight)| weight.is_none()) {
+        return None;
+    }
+    let mut for_weights = Vec::new();
+    let mut against_weights = Vec::new();
+    for (vote, weight) in votes {
+        match vote {
+            Vote::For => for_weights.push(weight.unwrap_or(1)),
+            Vote::Against => against_weights.push(weight.unwrap_or(1)),
+        }
+    }
+    Some((for_weights, against_weights))
+}
+
+/// The size of a vote in the file of a `BatchAggregator`
+/// The address of the voter, the vote, whether the ballot is weighted and the declared weight, in big-endian
+const VOTE_RECORD_SIZE: usize = 30;
+
+/// The aggregate of the ballots of a batch that is read from a file
+/// Only the election proof and the votes of the voters are kept, so that the memory does not grow with the size of the ballots
+/// The votes are written to a temporary file as they are added and read back when the batch is written, so that the memory does not grow with the number of voters either
+/// Each vote is kept with its declared weight, if the ballot is weighted
+pub(crate) struct BatchAggregator {
+    election_proof: G1,
+    votes: BufWriter<File>,
+    len: usize,
+    // Whether any of the ballots is weighted
+    weighted: bool,
+}
+
+impl BatchAggregator {
+    pub(crate) fn new() -> Result<BatchAggregator, String> {
+        let file = tempfile::tempfile().map_err(|err| format!("Error creating the temporary file of the votes: {}", err))?;
+        Ok(BatchAggregator {
+            election_proof: G1::zero(),
+            votes: BufWriter::new(file),
+            len: 0,
+            weighted: false,
+        })
+    }
+
+    /// This function adds the ballots to the batch, their vote proofs must have been checked already
+    pub(crate) fn add(&mut self, ballots: &[Ballot]) -> Result<(), String> {
+        let vote_proofs = ballots.iter().map(|ballot| &ballot.vote_proof).collect();
+        self.election_proof += batcher::generate_batched_election_proof(&vote_proofs);
+
+        // The votes may have been read since the last ballots were added, the new ones go after all the others
+        let write_votes = |votes: &mut BufWriter<File>| -> io::Result<()> {
+            votes.seek(SeekFrom::End(0))?;
+            for ballot in ballots {
+                let mut record = [0u8; VOTE_RECORD_SIZE];
+                record[..20].copy_from_slice(ballot.eth_address.as_bytes());
+                record[20] = (ballot.vote == Vote::For) as u8;
+                if let Some(weight) = ballot.weight {
+                    record[21] = 1;
+                    record[22..].copy_from_slice(&weight.to_be_bytes());
+                }
+                votes.write_all(&record)?;
+            }
+            Ok(())
+        };
+        write_votes(&mut self.votes).map_err(|err| format!("Error writing the votes to the temporary file: {}", err))?;
+        self.len += ballots.len();
+        self.weighted |= ballots.iter().any(|ballot| ballot.weight.is_some());
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn election_proof(&self) -> G1 {
+        self.election_proof
+    }
+
+    /// Returns whether any of the ballots is weighted, the batch then has the weights of all the voters
+    pub(crate) fn weighted(&self) -> bool {
+        self.weighted
+    }
+
+    /// Returns the address, the vote and the declared weight of each voter, in the order the ballots were added
+    /// The votes are read back from the temporary file one by one
+    pub(crate) fn votes(&mut self) -> io::Result<impl Iterator<Item = io::Result<(Address, Vote, Option<u64>)>> + '_> {
+        self.votes.flush()?;
+        let mut file = self.votes.get_ref();
+        file.seek(SeekFrom::Start(0))?;
+        let mut reader = BufReader::new(file);
+        let mut remaining = self.len;
+        Ok(iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            remaining -= 1;
+            let mut record = [0u8; VOTE_RECORD_SIZE];
+            Some(reader.read_exact(&mut record).map(|_| {
+                let vote = if record[20] == 1 { Vote::For } else { Vote::Against };
+                let weight = (record[21] == 1).then(|| u64::from_be_bytes(record[22..].try_into().unwrap()));
+                (Address::from_slice(&record[..20]), vote, weight)
+            }))
+        }))
+    }
+
+    /// Returns the address and the declared weight of the voters with the given vote, in the order the ballots were added
+    pub(crate) fn voters(&mut self, vote: Vote) -> io::Result<impl Iterator<Item = io::Result<(Address, Option<u64>)>> + '_> {
+        Ok(self.votes()?.filter_map(move |voter| match voter {
+            Ok((address, voter_vote, weight)) if voter_vote == vote => Some(Ok((address, weight))),
+            Ok(_) => None,
+            Err(err) => Some(Err(err)),
+        }))
+    }
+}
@@ -1,41 +1,434 @@
+use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use colored::Colorize;
-use crate::ballots::Ballot;
+use std::marker::PhantomData;
+use std::path::Path;
+use serde_json::{Map, Value};
+use crate::ballots::{Ballot, check_format_version, json_u64};
 
 
-/// The function that reads the ballots from a file and returns a vector of ballots
-/// We assume that the data for each new ballot is separated by a new line
-/// And that the data for each ballot is separated by a | character
-pub(crate) fn read_ballots_from_file(file_path: String) -> Result<Vec<Ballot>, String> {
-    let mut ballots = Vec::new();
+/// A ballot read from the file together with its position, the line number or the number of the ballot in a JSON document
+/// The ballot is an error if it could not be parsed
+pub(crate) type ParsedBallot<B = Ballot> = (usize, Result<B, String>);
 
-    // Open the file
+/// A kind of ballot that can be read from a ballot file, in any of the formats
+/// CSV_COLUMNS - The columns that the header of a CSV file must have
+pub(crate) trait BallotRecord: Sized + Send {
+    const CSV_COLUMNS: &'static [&'static str];
+
+    /// This function parses a ballot from a line of a pipe separated file
+    fn from_line(line: &str) -> Result<Self, String>;
+
+    /// This function parses a ballot record of a JSON, JSON Lines or CSV file, the version of the record has been checked
+    fn from_record(record: &Value, election_id: u64) -> Result<Self, String>;
+}
+
+impl BallotRecord for Ballot {
+    const CSV_COLUMNS: &'static [&'static str] = &["version", "publicKey", "vote", "voteProof", "address"];
+
+    fn from_line(line: &str) -> Result<Ballot, String> {
+        Ballot::from_line(line)
+    }
+
+    fn from_record(record: &Value, election_id: u64) -> Result<Ballot, String> {
+        Ballot::from_record(record, election_id)
+    }
+}
+
+/// The number of ballots read from the file at once
+/// Only one chunk of ballots is in memory at a time, so that files of any size can be processed
+pub(crate) const CHUNK_SIZE: usize = 4096;
+
+/// The format of a ballot file
+/// Pipe - One `<public key>|<vote>|<vote proof>|<address>` per line, the original format
+/// JsonLines - One JSON ballot object per line, each with the `version` field
+/// Json - A JSON document `{"version": 1, "electionId": ..., "ballots": [...]}`, or a single ballot object
+/// Csv - A CSV file with a header, the `version`, `publicKey`, `vote`, `voteProof` and `address` columns are required
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum BallotFormat {
+    Pipe,
+    JsonLines,
+    Json,
+    Csv,
+}
+
+impl fmt::Display for BallotFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str = match self {
+            BallotFormat::Pipe => "pipe separated",
+            BallotFormat::JsonLines => "JSON Lines",
+            BallotFormat::Json => "JSON",
+            BallotFormat::Csv => "CSV",
+        };
+        write!(f, "{}", str)
+    }
+}
+
+impl BallotFormat {
+    /// This function detects the format of the ballot file
+    /// The extension of the file is used if it is known, otherwise the first line of the file decides
+    pub(crate) fn detect(file_path: &str) -> Result<BallotFormat, String> {
+        match Path::new(file_path).extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") | Some("ndjson") => return Ok(BallotFormat::JsonLines),
+            Some("json") => return Ok(BallotFormat::Json),
+            Some("csv") => return Ok(BallotFormat::Csv),
+            _ => {}
+        }
+
+        let file = File::open(file_path)
+            .map_err(|err| format!("Error opening the file: {}", err))?;
+        let mut first_line = String::new();
+        for line in BufReader::new(file).lines() {
+            first_line = line.map_err(|err| format!("Error reading the line: {}", err))?;
+            if first_line.trim() != "" {
+                break;
+            }
+        }
+        let first_line = first_line.trim();
+
+        Ok(if first_line.starts_with('{') {
+            // A line that is a whole ballot object is JSON Lines, otherwise the line starts a JSON document
+            match serde_json::from_str::<Value>(first_line) {
+                Ok(record) if record.get("ballots").is_none() => BallotFormat::JsonLines,
+                _ => BallotFormat::Json,
+            }
+        } else if first_line.contains('|') {
+            BallotFormat::Pipe
+        } else {
+            BallotFormat::Csv
+        })
+    }
+}
+
+/// The reader of the ballots from a file, it reads the file in chunks of ballots
+/// Errors in reading the file are returned as an error, while errors in parsing a ballot are returned for each ballot
+/// So that the caller can decide what to do with the ballots that could not be parsed
+/// The kind of ballot is `Ballot` by default, see `BallotRecord`
+pub(crate) struct BallotReader<B: BallotRecord = Ballot> {
+    pub format: BallotFormat,
+    source: Source,
+    election_id: u64,
+    chunk_size: usize,
+    file_size: u64,
+    ballot: PhantomData<fn() -> B>,
+}
+
+/// Where the ballots are read from, depending on the format
+/// Lines - The pipe separated and the JSON Lines files, read line by line
+/// Csv - The CSV files, read record by record
+/// Json - The JSON documents, they are parsed at once, the records are then read one by one
+enum Source {
+    Lines { reader: BufReader<File>, line_number: usize, bytes_read: u64 },
+    Csv { reader: csv::Reader<File>, headers: csv::StringRecord },
+    Json { records: std::vec::IntoIter<Value>, read: usize, total: usize },
+}
+
+/// A ballot that has been read, but not parsed yet
+enum RawBallot {
+    Line(String),
+    Record(Value),
+}
+
+impl<B: BallotRecord> BallotReader<B> {
+    /// This function opens the ballot file and detects its format
+    /// election_id - The id of the election being batched, the ballots for other elections are rejected
+    pub(crate) fn open(file_path: &str, election_id: u64, chunk_size: usize) -> Result<BallotReader<B>, String> {
+        let format = BallotFormat::detect(file_path)?;
+        let file = File::open(file_path)
+            .map_err(|err| format!("Error opening the file: {}", err))?;
+        let file_size = file.metadata()
+            .map_err(|err| format!("Error reading the file: {}", err))?
+            .len();
+
+        let source = match format {
+            BallotFormat::Pipe | BallotFormat::JsonLines => Source::Lines { reader: BufReader::new(file), line_number: 0, bytes_read: 0 },
+            BallotFormat::Csv => {
+                let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_reader(file);
+                let headers = reader.headers()
+                    .map_err(|err| format!("Error reading the CSV header: {}", err))?
+                    .clone();
+                for &column in B::CSV_COLUMNS {
+                    if !headers.iter().any(|header| header == column) {
+                        return Err(format!("The CSV header has no `{}` column", column));
+                    }
+                }
+                Source::Csv { reader, headers }
+            }
+            BallotFormat::Json => {
+                let records = read_json_document(file_path, election_id)?;
+                Source::Json { total: records.len(), records: records.into_iter(), read: 0 }
+            }
+        };
+
+        Ok(BallotReader {
+            format,
+            source,
+            election_id,
+            chunk_size,
+            file_size,
+            ballot: PhantomData,
+        })
+    }
+
+    /// This function reads and parses the next chunk of ballots, empty lines are skipped
+    /// It returns `None` once the whole file has been read
+    pub(crate) fn next_chunk(&mut self) -> Result<Option<Vec<ParsedBallot<B>>>, String> {
+        let mut raw_ballots = Vec::with_capacity(self.chunk_size);
+        while raw_ballots.len() < self.chunk_size {
+            match self.next_raw_ballot()? {
+                Some(raw_ballot) => raw_ballots.push(raw_ballot),
+                None => break,
+            }
+        }
+
+        if raw_ballots.is_empty() {
+            return Ok(None);
+        }
+
+        // Parse the ballots, with the `parallel` feature the ballots are parsed on all the threads
+        let parse = |(position, raw_ballot): &(usize, RawBallot)| (*position, self.parse(raw_ballot)
+            .map_err(|err| format!("Error parsing the ballot at {} {}: {}", self.position_name(), position, err)));
+        #[cfg(feature = "parallel")]
+        let ballots = {
+            use rayon::prelude::*;
+            raw_ballots.par_iter().map(parse).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let ballots = raw_ballots.iter().map(parse).collect();
+
+        Ok(Some(ballots))
+    }
+
+    /// Returns the share of the file that has been read, in percent
+    pub(crate) fn progress(&self) -> u64 {
+        let (read, total) = match &self.source {
+            Source::Lines { bytes_read, .. } => (*bytes_read, self.file_size),
+            Source::Csv { reader, .. } => (reader.position().byte(), self.file_size),
+            Source::Json { read, total, .. } => (*read as u64, *total as u64),
+        };
+        (read * 100).checked_div(total).unwrap_or(100)
+    }
+
+    /// Returns the next ballot of the file with its position, or `None` at the end of the file
+    fn next_raw_ballot(&mut self) -> Result<Option<(usize, RawBallot)>, String> {
+        match &mut self.source {
+            Source::Lines { reader, line_number, bytes_read } => loop {
+                let mut line = String::new();
+                let read = reader.read_line(&mut line)
+                    .map_err(|err| format!("Error reading the line: {}", err))?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                *line_number += 1;
+                *bytes_read += read as u64;
+
+                // If the line is empty, skip it
+                if line.trim() != "" {
+                    return Ok(Some((*line_number, RawBallot::Line(line.trim().to_string()))));
+                }
+            },
+            Source::Csv { reader, headers } => {
+                let mut record = csv::StringRecord::new();
+                let read = reader.read_record(&mut record)
+                    .map_err(|err| format!("Error reading the CSV record: {}", err))?;
+                if !read {
+                    return Ok(None);
+                }
+                let line_number = record.position().map(|position| position.line() as usize).unwrap_or_default();
+                let fields: Map<String, Value> = headers.iter().zip(record.iter())
+                    .map(|(header, value)| (header.to_string(), Value::String(value.to_string())))
+                    .collect();
+                Ok(Some((line_number, RawBallot::Record(Value::Object(fields)))))
+            }
+            Source::Json { records, read, .. } => Ok(records.next().map(|record| {
+                *read += 1;
+                (*read, RawBallot::Record(record))
+            })),
+        }
+    }
+
+    /// This function parses a ballot in the format of the file
+    fn parse(&self, raw_ballot: &RawBallot) -> Result<B, String> {
+        match raw_ballot {
+            RawBallot::Line(line) if self.format == BallotFormat::Pipe => B::from_line(line),
+            RawBallot::Line(line) => {
+                let record: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+                check_format_version(record.get("version"))?;
+                B::from_record(&record, self.election_id)
+            }
+            RawBallot::Record(record) => {
+                check_format_version(record.get("version"))?;
+                B::from_record(record, self.election_id)
+            }
+        }
+    }
+
+    fn position_name(&self) -> &'static str {
+        match self.format {
+            BallotFormat::Json => "ballot",
+            _ => "line",
+        }
+    }
+}
+
+/// This function reads the ballot records of a JSON document
+/// The document is either a single ballot object, or an object with the list of `ballots`
+/// The version and the election id of the document apply to all its ballots
+fn read_json_document(file_path: &str, election_id: u64) -> Result<Vec<Value>, String> {
     let file = File::open(file_path)
-        .map_err(|err| format!("Error opening the file: {}", err))?;
-    let reader = BufReader::new(file);
+        .map_err(|err| format!("Error reading the file: {}", err))?;
+    // The document is parsed as the file is read, with `serde_json::Deserializer::from_reader`, so its text is never held in memory
+    let mut document: Value = serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| format!("Error parsing the JSON document: {}", err))?;
 
-    // Read the ballots from the file
-    for (i, line) in reader.lines().enumerate() {
-        let line = line
-            .map_err(|err| format!("Error reading the line: {}", err))?;
+    let ballots = match document.as_object_mut().and_then(|fields| fields.remove("ballots")) {
+        None => return Ok(vec![document]),
+        Some(Value::Array(ballots)) => ballots,
+        Some(_) => return Err("The `ballots` field of the JSON document must be a list".to_string()),
+    };
+    check_format_version(document.get("version"))?;
+    // A document for another election is rejected as a whole
+    if let Some(document_election_id) = document.get("electionId") {
+        if json_u64(document_election_id) != Some(election_id) {
+            return Err(format!("The ballots are for the election {}, not {}", document_election_id, election_id));
+        }
+    }
 
-        // If the line is empty, skip it
-        if line.trim() == "" {
-            continue;
+    let mut records = ballots;
+    for record in records.iter_mut() {
+        if let Value::Object(fields) = record {
+            fields.entry("version").or_insert(document["version"].clone());
+            if let Some(document_election_id) = document.get("electionId") {
+                fields.entry("electionId").or_insert(document_election_id.clone());
+            }
         }
+    }
+    Ok(records)
+}
 
-        // Split the line by the | character
-        let line_split = line.split("|");
+#[cfg(test)]
+mod tests {
+    use batravot_lib::{ElectionSpecifiers, ethereum, G1, ScalarField, verifier, Vote, voter};
+    use batravot_lib::ethereum::{BallotDomain, TypedBallot};
+    use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+    use std::fs;
+    use web3::types::Address;
+    use super::*;
 
-        // Parse the elements of the line and create a ballot
-        let ballot = Ballot::from_iter(line_split)
-            .map_err(|err| format!("Error parsing the ballot at line {}: {}", i, err))?;
+    /// Returns the public key, the vote proof and the address of a valid `For` ballot of the election 1
+    fn ballot_fields() -> (String, String, String) {
+        let prk = ScalarField::from(9u64);
+        (voter::generate_public_key(&prk).solidity_repr(),
+         voter::generate_vote_proof(&prk, &ElectionSpecifiers::from(1).forr.0).solidity_repr(),
+         Address::from_low_u64_be(1).solidity_repr())
+    }
+
+    /// Writes the file and returns whether each ballot of each chunk of two ballots could be parsed
+    fn read_chunks(name: &str, content: &str) -> (BallotFormat, Vec<Vec<(usize, bool)>>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+
+        let mut reader: BallotReader = BallotReader::open(&path.to_string_lossy(), 1, 2).unwrap();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = reader.next_chunk().unwrap() {
+            chunks.push(chunk.iter().map(|(position, ballot)| (*position, ballot.is_ok())).collect());
+        }
+        assert_eq!(reader.progress(), 100);
+        (reader.format, chunks)
+    }
+
+    #[test]
+    fn reads_the_file_in_chunks() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        let line = format!("{}|for|{}|{}\n", public_key, vote_proof, address);
+        let content = format!("{}\n{}invalid\n{}{}", line, line, line, line);
+
+        assert_eq!(read_chunks("chunks.txt", &content), (BallotFormat::Pipe, vec![
+            vec![(1, true), (3, true)],
+            vec![(4, false), (5, true)],
+            vec![(6, true)],
+        ]));
+    }
+
+    #[test]
+    fn reads_the_json_lines_and_csv_formats() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        let record = |version: u64, election_id: u64| serde_json::json!({
+            "version": version, "electionId": election_id, "publicKey": public_key, "vote": "for",
+            "voteProof": vote_proof, "address": address, "metadata": {"source": "test"},
+        }).to_string();
+        let content = format!("{}\n{}\n{}\n", record(1, 1), record(2, 1), record(1, 2));
+        assert_eq!(read_chunks("ballots", &content), (BallotFormat::JsonLines, vec![
+            vec![(1, true), (2, false)],
+            vec![(3, false)],
+        ]));
 
-        // Add the ballot to the vector
-        ballots.push(ballot);
+        let content = format!("version,electionId,publicKey,vote,voteProof,address,comment\n1,1,\"{}\",for,\"{}\",{},first\n1,,\"{}\",for,\"{}\",{},\n",
+            public_key, vote_proof, address, public_key, vote_proof, address);
+        assert_eq!(read_chunks("ballots.csv", &content), (BallotFormat::Csv, vec![
+            vec![(2, true), (3, true)],
+        ]));
     }
 
-    println!("{}", format!("\nFinished reading ballots from the file").green());
-    Ok(ballots)
+    #[test]
+    fn reads_the_json_format() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        let ballot = serde_json::json!({ "publicKey": public_key, "vote": "for", "voteProof": vote_proof, "address": address });
+        let document = serde_json::json!({ "version": 1, "electionId": 1, "metadata": {}, "ballots": [ballot, {"vote": "for"}] });
+        assert_eq!(read_chunks("ballots-document", &format!("{:#}", document)), (BallotFormat::Json, vec![
+            vec![(1, true), (2, false)],
+        ]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ballots-other.json");
+        fs::write(&path, serde_json::json!({ "version": 1, "electionId": 2, "ballots": [ballot] }).to_string()).unwrap();
+        assert!(BallotReader::<Ballot>::open(&path.to_string_lossy(), 1, 2).is_err());
+    }
+
+    #[test]
+    fn reads_signed_ballots() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        let sign_key = ethereum::parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let typed_ballot = TypedBallot { election_id: 1, vote: Vote::For, vote_proof: G1::from_str_c(&vote_proof).unwrap(), sequence: 7 };
+        let domain = BallotDomain { chain_id: 1, verifying_contract: Address::from_low_u64_be(0xc0) };
+        let signature = typed_ballot.sign(&domain, &sign_key).solidity_repr();
+
+        // The signature is only checked with the vote proof, a sequence without a signature can not be parsed
+        let content = format!("{pk}|for|{vp}|{address}|7|{sig}\n{pk}|for|{vp}|{address}|7\n{pk}|for|{vp}|{address}|7|0x01\n",
+            pk = public_key, vp = vote_proof, address = address, sig = signature);
+        assert_eq!(read_chunks("signed.txt", &content), (BallotFormat::Pipe, vec![
+            vec![(1, true), (2, false)],
+            vec![(3, false)],
+        ]));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signed.jsonl");
+        let record = serde_json::json!({ "version": 1, "publicKey": public_key, "vote": "for", "voteProof": vote_proof, "address": address, "sequence": "7", "signature": signature });
+        fs::write(&path, format!("{}\n", record)).unwrap();
+        let (_, ballot) = BallotReader::<Ballot>::open(&path.to_string_lossy(), 1, 2).unwrap().next_chunk().unwrap().unwrap().remove(0);
+        let ballot = ballot.unwrap();
+        assert_eq!(ballot.signature.map(|signature| signature.sequence), Some(7));
+        // The address of the ballot is not the address of the signing key
+        assert_eq!(ballot.check_signature(1, Some(&domain)), Err("The signature is not from the address of the ballot".to_string()));
+        assert!(ballot.check_signature(1, None).is_err());
+    }
+
+    #[test]
+    fn reads_weighted_ballots() {
+        let (public_key, _, address) = ballot_fields();
+        let vote_proof = voter::generate_weighted_vote_proof(&ScalarField::from(9u64), &ElectionSpecifiers::from(1).forr.0, 40).solidity_repr();
+
+        // The empty optional elements are missing, a zero weight can not be parsed
+        let content = format!("{pk}|for|{vp}|{address}||||40\n{pk}|for|{vp}|{address}||||0\n",
+            pk = public_key, vp = vote_proof, address = address);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("weighted.txt");
+        fs::write(&path, content).unwrap();
+        let mut ballots = BallotReader::<Ballot>::open(&path.to_string_lossy(), 1, 2).unwrap().next_chunk().unwrap().unwrap();
+        assert!(ballots.remove(1).1.is_err());
+        let ballot = ballots.remove(0).1.unwrap();
+        assert_eq!((ballot.weight, ballot.signature.is_none(), ballot.dleq_proof.is_none()), (Some(40), true, true));
+        assert_eq!(ballot.proof_key(), verifier::weighted_public_key(&ballot.voter_public_key, 40));
+    }
 }
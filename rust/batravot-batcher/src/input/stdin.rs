@@ -4,7 +4,7 @@ use colored::Colorize;
 use web3::types::Address;
 use batravot_lib::{G1, Vote};
 use batravot_lib::representation::FromStrCustom;
-use crate::ballots::Ballot;
+use crate::ballots::{Ballot, BallotSignature};
 
 /// The function that reads the ballots from the standard input
 /// It prompts the user to enter the ballot information one by one
@@ -31,11 +31,17 @@ pub(crate) fn read_ballots_from_stdin() -> Result<Vec<Ballot>, String> {
         let eth_address = read_eth_address()
             .map_err(|err| format!("Error reading address: {}", err))?;
 
+        let signature = read_signature()
+            .map_err(|err| format!("Error reading signature: {}", err))?;
+
         let ballot = Ballot {
             voter_public_key: public_key,
             vote,
             vote_proof,
-            eth_address
+            eth_address,
+            signature,
+            dleq_proof: None,
+            weight: None,
         };
 
         // Add the ballot to the vector
@@ -78,6 +84,31 @@ fn read_eth_address() -> Result<Address, String> {
     }
 }
 
+/// This function reads the sequence and the signature of the ballot from the standard input
+/// They are entered as `<sequence>|<signature>`, an empty line means that the ballot is not signed
+/// If there is an error at parsing the signature, it will ask the user to try again
+fn read_signature() -> Result<Option<BallotSignature>, String> {
+    println!("\nEnter the sequence and the signature of the ballot as <sequence>|<signature>, or leave it empty if the ballot is not signed:");
+    loop {
+        let mut signature_str = String::new();
+        io::stdin().read_line(&mut signature_str)
+            .map_err(|err| format!("Error reading the signature: {}", err))?;
+        let signature_str = signature_str.trim();
+        if signature_str.is_empty() {
+            return Ok(None);
+        }
+
+        let (sequence, signature) = signature_str.split_once('|').unwrap_or((signature_str, ""));
+        let signature = sequence.trim().parse::<u64>()
+            .map_err(|err| format!("Error parsing the sequence: {}", err))
+            .and_then(|sequence| BallotSignature::parse(Some(sequence), Some(signature)));
+        match signature {
+            Ok(signature) => return Ok(signature),
+            Err(err) => eprintln!("{}", format!("There was an error parsing the signature: {}.\nPlease try again.", err).red()),
+        }
+    }
+}
+
 /// The function that reads a public key from the standard input
 /// If there is an error at parsing the public key, it will ask the user to try again
 fn read_public_key() -> Result<G1, String>{
@@ -1,10 +1,28 @@
+mod approval;
 mod ballots;
+mod batch;
+mod ranked;
+mod report;
+mod server;
+mod store;
+mod tally;
 
-use batravot_lib::{batcher, Vote};
+use std::io::{self, IsTerminal, Write};
+use colored::Colorize;
+use batravot_lib::{ElectionSpecifiers, Vote};
+use batravot_lib::ethereum::BallotDomain;
 use batravot_lib::representation::{SolidityRepresentable};
-use crate::ballots::proof_check_ballots;
+use crate::ballots::{proof_check_ballots, RejectedBallot};
+use crate::batch::BatchAggregator;
+use crate::report::{RejectionReport, write_batch};
+use batravot_lib::balances::BalanceSnapshot;
+use crate::tally::{print_tally, tally, voters_above_balance, voters_without_balance};
 
-use crate::input::file::read_ballots_from_file;
+pub use crate::ballots::InvalidBallotPolicy;
+pub use crate::server::{serve, ServerConfig};
+pub use crate::tally::TallyRules;
+
+use crate::input::file::{BallotReader, CHUNK_SIZE, ParsedBallot};
 use crate::input::stdin::{read_ballots_from_stdin, get_election_id};
 
 mod input {
@@ -13,50 +31,179 @@ mod input {
 }
 
 /// The main logic function, it selects the correct function based on the command line arguments
-pub fn run(input_mode: InputMode) -> Result<(), String> {
-
-    // Clear the screen
-    print!("{}[2J", 27 as char);
+pub fn run(config: BatcherConfig) -> Result<(), String> {
 
-    // Ask the user to enter the election id
-    let election_id = get_election_id()?;
+    // Approval and ranked ballots can not be signed, and the tally preview only counts `For` and `Against` votes
+    match config.options {
+        Some(_) if !config.allow_unsigned => {
+            return Err("The approval and ranked ballots can not be signed, `--allow-unsigned` must be set to batch them".to_string());
+        }
+        Some(_) if config.tally.is_some() => {
+            return Err("The outcome of an approval or ranked election can not be previewed, `--balances` can not be set with `--options`".to_string());
+        }
+        Some(options) if config.ranked && config.seats.is_some_and(|seats| seats > options) => {
+            return Err(format!("The election has {} options, it can not fill {} seats", options, config.seats.unwrap_or(1)));
+        }
+        _ if config.seats.is_some() && !config.ranked => {
+            return Err("Only a ranked election has seats, `--seats` can only be set with `--ranked`".to_string());
+        }
+        None if config.ranked => {
+            return Err("The number of options of a ranked election must be set with `--options`".to_string());
+        }
+        None if config.domain.is_none() && !config.allow_unsigned => {
+            return Err("The signatures of the ballots are checked for the chain and the contract of the election, `--chain-id` and `--contract` must be set".to_string());
+        }
+        _ => {}
+    }
 
-    // Read the ballots from the input sources
-    let ballots = match input_mode {
-        InputMode::Stdin => read_ballots_from_stdin()?,
-        InputMode::File(file_path) => read_ballots_from_file(file_path)?,
+    // Read the balance snapshot first, so that a wrong file is reported before any input is requested
+    let snapshot = match &config.tally {
+        Some(tally_config) => Some(BalanceSnapshot::from_file(&tally_config.balances_file)?),
+        None => None,
     };
 
-    // Check if the ballots are valid, if not, ask the user to decide what to do
-    let proof_checked_ballots = proof_check_ballots(election_id, ballots);
+    // Clear the screen, unless the batcher runs unattended
+    if config.election_id.is_none() {
+        print!("{}[2J", 27 as char);
+    }
 
-    // Generate the batched election proof
-    let vote_proofs = proof_checked_ballots.iter().map(|ballot| &ballot.vote_proof).collect();
-    let batched_election_proof = batcher::generate_batched_election_proof(&vote_proofs);
+    // Ask the user to enter the election id, unless it is provided
+    let election_id = match config.election_id {
+        Some(election_id) => election_id,
+        None => get_election_id()?,
+    };
 
-    // Generate the list of who voted `For` and `against` the election
-    let mut for_voters = Vec::new();
-    let mut against_voters = Vec::new();
-    for ballot in proof_checked_ballots {
-        match ballot.vote {
-            Vote::For => for_voters.push(ballot.eth_address),
-            Vote::Against => against_voters.push(ballot.eth_address),
-        }
+    // The ballots of an approval election are batched by option, those of a ranked election by rank and option
+    match config.options {
+        Some(options) if config.ranked => return ranked::run_ranked(election_id, options, config.seats.unwrap_or(1), &config),
+        Some(options) => return approval::run_approval(election_id, options, &config),
+        None => {}
     }
 
-    // Print the results
+    // Read the ballots from the input sources, check them and add the valid ones to the batch
+    // Ballots that could not be parsed are either rejected or stop the batcher, depending on the policy
+    // A file is processed in chunks, so that only a chunk of ballots is in memory at a time
+    // The votes and the rejected ballots are written to files as they are checked, so that the memory does not grow with their number
+    let specifiers = ElectionSpecifiers::from(election_id);
+    let mut aggregator = BatchAggregator::new()?;
+    let mut report = RejectionReport::create(config.report.as_deref(), election_id)?;
+    match &config.input_mode {
+        InputMode::Stdin => {
+            let ballots = read_ballots_from_stdin()?.into_iter()
+                .enumerate()
+                .map(|(i, ballot)| (i + 1, Ok(ballot)))
+                .collect();
+            check_ballots(election_id, &specifiers, ballots, &config, &mut aggregator, &mut report)?;
+        }
+        InputMode::File(file_path) => {
+            let mut reader = BallotReader::open(file_path, election_id, CHUNK_SIZE)?;
+            println!("Reading the ballots in the {} format", reader.format);
+            let show_progress = io::stderr().is_terminal();
+            while let Some(ballots) = reader.next_chunk()? {
+                check_ballots(election_id, &specifiers, ballots, &config, &mut aggregator, &mut report)?;
+                if show_progress {
+                    eprint!("\rRead {}% of the file, {} ballots accepted, {} rejected", reader.progress(), aggregator.len(), report.rejected());
+                }
+            }
+            if show_progress {
+                eprintln!();
+            }
+            // The invalid ballots that were kept are both accepted and rejected
+            println!("{}", format!("\nFinished reading {} ballots from the file", aggregator.len() + report.rejected() - report.kept()).green());
+        }
+    };
+
+    // The batched election proof is the sum of the vote proofs of the accepted ballots
+    let batched_election_proof = aggregator.election_proof();
+
+    // Print the results, the lists of who voted `For` and `Against` the election, with their weights if the ballots are weighted
+    // The voters are read back from the aggregator one by one, rather than collected
     println!("\n");
     println!("----------------------------------------");
     println!("Please submit the following data to the election verifier:");
     println!("Election Proof:    {}", batched_election_proof.solidity_repr());
-    println!("Who voted for:     [{}]", for_voters.iter().map(|x| x.solidity_repr()).collect::<Vec<String>>().join(", "));
-    println!("Who voted against: [{}]", against_voters.iter().map(|x| x.solidity_repr()).collect::<Vec<String>>().join(", "));
+    let print_lists = |aggregator: &mut BatchAggregator| -> io::Result<()> {
+        print_list("Who voted for:     ", aggregator.voters(Vote::For)?.map(|voter| voter.map(|(address, _)| address.solidity_repr())))?;
+        print_list("Who voted against: ", aggregator.voters(Vote::Against)?.map(|voter| voter.map(|(address, _)| address.solidity_repr())))?;
+        if aggregator.weighted() {
+            print_list("Weights for:       ", aggregator.voters(Vote::For)?.map(|voter| voter.map(|(_, weight)| weight.unwrap_or(1).to_string())))?;
+            print_list("Weights against:   ", aggregator.voters(Vote::Against)?.map(|voter| voter.map(|(_, weight)| weight.unwrap_or(1).to_string())))?;
+        }
+        Ok(())
+    };
+    print_lists(&mut aggregator).map_err(|err| format!("Error reading the votes from the temporary file: {}", err))?;
     println!("----------------------------------------");
 
+    // Write the batch and the report of the invalid ballots, if requested
+    if let Some(output) = &config.output {
+        write_batch(output, election_id, &mut aggregator)?;
+        println!("The batch has been written to {}", output);
+    }
+    let rejected = report.rejected();
+    report.finish(aggregator.len())?;
+    if let Some(report) = &config.report {
+        println!("The report of {} invalid ballots has been written to {}", rejected, report);
+    }
+
+    // Preview the outcome of the election, if the balance snapshot was provided
+    // The votes are collected for the tally, which keeps one entry per voter, same as the balance snapshot
+    if let (Some(tally_config), Some(snapshot)) = (&config.tally, snapshot) {
+        let votes = aggregator.votes().and_then(|votes| votes.collect::<io::Result<Vec<_>>>())
+            .map_err(|err| format!("Error reading the votes from the temporary file: {}", err))?;
+        let missing = voters_without_balance(votes.iter().map(|(address, _, _)| address), &snapshot);
+        if !missing.is_empty() {
+            eprintln!("{}", format!("The following voters are not in the balance snapshot, their votes have no weight: [{}]", missing.join(", ")).red());
+        }
+        let above_balance = voters_above_balance(&votes, &snapshot);
+        if !above_balance.is_empty() {
+            eprintln!("{}", format!("The following voters declared a weight higher than their balance in the snapshot, their votes count with their balance: [{}]", above_balance.join(", ")).red());
+        }
+
+        let outcome = tally(votes, &snapshot, &tally_config.rules);
+        print_tally(&outcome, &tally_config.rules);
+        println!("----------------------------------------");
+    }
+
     Ok(())
 }
 
 
+/// This function checks a chunk of ballots and adds the valid ones to the batch
+/// The ballots that could not be parsed are rejected, unless the policy is to ask or to abort, then they stop the batcher
+fn check_ballots(election_id: u64, specifiers: &ElectionSpecifiers, ballots: Vec<ParsedBallot>, config: &BatcherConfig,
+                 aggregator: &mut BatchAggregator, report: &mut RejectionReport) -> Result<(), String> {
+    let policy = config.on_invalid;
+    let mut rejected_ballots = Vec::new();
+    let mut parsed_ballots = Vec::new();
+    for (position, ballot) in ballots {
+        match ballot {
+            Ok(ballot) => parsed_ballots.push((position, ballot)),
+            Err(err) if policy == InvalidBallotPolicy::Ask || policy == InvalidBallotPolicy::Abort => {
+                return Err(err);
+            }
+            Err(err) => {
+                eprintln!("{}", format!("{}. The ballot will be removed", err).red());
+                rejected_ballots.push(RejectedBallot { position, eth_address: None, reason: err, kept: false });
+            }
+        }
+    }
+
+    // Check if the ballots are valid, if not, decide what to do based on the policy
+    let (proof_checked_ballots, mut invalid_ballots) = proof_check_ballots(election_id, specifiers, parsed_ballots, policy, !config.allow_unsigned, config.domain.as_ref())?;
+    aggregator.add(&proof_checked_ballots)?;
+    rejected_ballots.append(&mut invalid_ballots);
+    report.add(rejected_ballots)
+}
+
+/// This function prints a list of the batch on a single line, the values are written one by one
+fn print_list(label: &str, values: impl Iterator<Item = io::Result<String>>) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{}[", label)?;
+    for (i, value) in values.enumerate() {
+        write!(stdout, "{}{}", if i == 0 { "" } else { ", " }, value?)?;
+    }
+    writeln!(stdout, "]")
+}
 
 /// How the ballots will be provided to the batcher application
 /// File - The ballots will be provided in a file
@@ -64,4 +211,38 @@ pub fn run(input_mode: InputMode) -> Result<(), String> {
 pub enum InputMode {
     File(String),
     Stdin,
+}
+
+/// The options of the batcher application, set with the command line flags
+/// election_id - The id of the election, asked for on the standard input if not provided
+/// input_mode - Where the ballots are read from
+/// output - The file to write the batch to, in JSON
+/// report - The file to write the report of the invalid ballots to, in JSON
+/// on_invalid - What to do with the invalid ballots
+/// tally - How to preview the outcome of the election, if at all
+/// allow_unsigned - Whether the ballots without a signature are accepted, by default they are invalid
+/// domain - The chain and the contract of the election, the ballots are signed for them, required unless `allow_unsigned` is set
+/// options - The number of options of an approval or ranked election, the ballots are then approval ballots, unless `ranked` is set
+/// ranked - Whether the ballots are ranked ballots, they are then tallied with the single transferable vote
+/// seats - The number of options elected by the single transferable vote tally of a ranked election, one if it is not set, it can only be set for a ranked election
+pub struct BatcherConfig {
+    pub election_id: Option<u64>,
+    pub input_mode: InputMode,
+    pub output: Option<String>,
+    pub report: Option<String>,
+    pub on_invalid: InvalidBallotPolicy,
+    pub tally: Option<TallyConfig>,
+    pub allow_unsigned: bool,
+    pub domain: Option<BallotDomain>,
+    pub options: Option<usize>,
+    pub ranked: bool,
+    pub seats: Option<usize>,
+}
+
+/// How the outcome of the election should be previewed
+/// balances_file - The file with the token balances of the voters, in CSV or JSON
+/// rules - The quorum and threshold of the election
+pub struct TallyConfig {
+    pub balances_file: String,
+    pub rules: TallyRules,
 }
\ No newline at end of file
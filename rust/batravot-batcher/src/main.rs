@@ -1,29 +1,192 @@
-use std::process;
-use batravot_batcher::{InputMode, run};
+use std::{fs, process};
+use std::str::FromStr;
+use batravot_lib::ethereum::{self, BallotDomain};
+use batravot_lib::representation::FromStrCustom;
+use batravot_batcher::{BatcherConfig, InputMode, InvalidBallotPolicy, run, serve, ServerConfig, TallyConfig, TallyRules};
 
 /// This is the main function that initiates the Batcher application
 /// If there are any errors in the execution and prints them in error stream
 fn main() {
 
+    let args: Vec<String> = std::env::args().collect();
+
+    // In the server mode, the ballots are received over HTTP instead of being read from the input
+    if args.get(1).map(|mode| mode.as_str()) == Some("serve") {
+        run_server(&args);
+        return;
+    }
 
     // Check if there is a `-f` or `--file` argument set, if so, the input mode will be file
     // And there should be a file path as the next argument
     // If not, the input mode will be stdin
-    let mut iter = std::env::args().skip_while(|x| x != "-f" && x != "--file");
-    let input_mode = if iter.next().is_some() {
-        if let Some(file_path) = iter.next() {
-            InputMode::File(file_path)
-        } else {
-            eprintln!("Error: No file path provided after `-f` or `--file` flag");
+    let input_mode = match flag_value(&args, &["-f", "--file"]) {
+        Some(file_path) => InputMode::File(file_path),
+        None => InputMode::Stdin,
+    };
+
+    // Check if there is a `--balances` argument set, if so, the outcome of the election will be previewed
+    // The quorum and threshold are optional and are set with the `--quorum` and `--threshold` arguments
+    let tally_config = flag_value(&args, &["--balances"]).map(|balances_file| {
+        let mut rules = TallyRules::default();
+        if let Some(quorum) = flag_value(&args, &["--quorum"]) {
+            rules.quorum = parse_percentage(&quorum, "--quorum");
+        }
+        if let Some(threshold) = flag_value(&args, &["--threshold"]) {
+            rules.threshold = parse_percentage(&threshold, "--threshold");
+        }
+        TallyConfig { balances_file, rules }
+    });
+
+    // The election id can be provided with the `--election-id` argument, otherwise it is asked for
+    let election_id = flag_value(&args, &["--election-id"]).map(|election_id| {
+        election_id.trim().parse::<u64>().unwrap_or_else(|err| {
+            eprintln!("Error: There was an error parsing the Election Id: {}", err);
             process::exit(1);
+        })
+    });
+
+    // What to do with the invalid ballots, by default the user is asked for each of them
+    let on_invalid = match flag_value(&args, &["--on-invalid"]) {
+        Some(policy) => InvalidBallotPolicy::from_str(&policy).unwrap_or_else(|err| {
+            eprintln!("Error: {}", err);
+            process::exit(1);
+        }),
+        None => InvalidBallotPolicy::Ask,
+    };
+
+    // The number of options of an approval election, the ballots are then approval ballots, or ranked ballots with `--ranked`
+    let options = flag_value(&args, &["--options"]).map(|options| {
+        match options.trim().parse::<usize>() {
+            Ok(options) if options > 0 => options,
+            _ => {
+                eprintln!("Error: `--options` must be a positive number of options, got {}", options);
+                process::exit(1);
+            }
+        }
+    });
+
+    // The number of seats of a ranked election, one if it is not set
+    let seats = flag_value(&args, &["--seats"]).map(|seats| {
+        match seats.trim().parse::<usize>() {
+            Ok(seats) if seats > 0 => seats,
+            _ => {
+                eprintln!("Error: `--seats` must be a positive number of seats, got {}", seats);
+                process::exit(1);
+            }
         }
-    } else {
-        InputMode::Stdin
+    });
+
+    let config = BatcherConfig {
+        election_id,
+        input_mode,
+        output: flag_value(&args, &["-o", "--output"]),
+        report: flag_value(&args, &["--report"]),
+        on_invalid,
+        tally: tally_config,
+        allow_unsigned: args.iter().any(|arg| arg == "--allow-unsigned"),
+        domain: ballot_domain(&args),
+        options,
+        ranked: args.iter().any(|arg| arg == "--ranked"),
+        seats,
     };
 
-    run(input_mode).unwrap_or_else(|err| {
+    run(config).unwrap_or_else(|err| {
         eprintln!("An error occurred: {}", err);
         process::exit(1);
     });
 }
 
+/// This function starts the batcher in the server mode
+/// The server listens on `--listen`, `127.0.0.1:8080` by default, and accepts the ballots for the `--elections`,
+/// provided as a comma separated list of election ids
+/// The ballots are written to the `--store` file, if provided, so that they survive a restart
+/// The ballots must be signed, unless `--allow-unsigned` is set
+/// The receipts are signed with the Ethereum private key of `--receipt-key` or of the `--receipt-key-file` file, one of them is required
+fn run_server(args: &[String]) {
+    let listen = flag_value(args, &["--listen"]).unwrap_or("127.0.0.1:8080".to_string());
+    let elections = flag_value(args, &["--elections"]).unwrap_or_default()
+        .split(',')
+        .filter(|election_id| election_id.trim() != "")
+        .map(|election_id| election_id.trim().parse::<u64>().unwrap_or_else(|err| {
+            eprintln!("Error: There was an error parsing the Election Id {}: {}", election_id, err);
+            process::exit(1);
+        }))
+        .collect();
+
+    let store = flag_value(args, &["--store"]);
+
+    let allow_unsigned = args.iter().any(|arg| arg == "--allow-unsigned");
+
+    let receipt_key = match (flag_value(args, &["--receipt-key"]), flag_value(args, &["--receipt-key-file"])) {
+        (Some(receipt_key), _) => Ok(receipt_key),
+        (None, Some(receipt_key_file)) => fs::read_to_string(&receipt_key_file)
+            .map_err(|err| format!("Error reading the receipt key file: {}", err)),
+        (None, None) => Err("The server signs the receipts of the ballots, set its Ethereum private key with `--receipt-key` or `--receipt-key-file`".to_string()),
+    }.and_then(|receipt_key| ethereum::parse_secret_key(&receipt_key)).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    });
+
+    let domain = ballot_domain(args);
+
+    serve(ServerConfig { listen, elections, store, allow_unsigned, domain, receipt_key }).unwrap_or_else(|err| {
+        eprintln!("An error occurred: {}", err);
+        process::exit(1);
+    });
+}
+
+/// This function returns the value of any of the provided flags
+/// The value either follows the flag as the next argument, or is set with `--flag=value`
+/// If the flag is set, but there is no value after it, the application exits with an error
+fn flag_value(args: &[String], flags: &[&str]) -> Option<String> {
+    let inline_value = args.iter().find_map(|arg| {
+        let (flag, value) = arg.split_once('=')?;
+        flags.contains(&flag).then(|| value.to_string())
+    });
+    if inline_value.is_some() {
+        return inline_value;
+    }
+
+    let mut iter = args.iter().skip_while(|x| !flags.contains(&x.as_str()));
+    let flag = iter.next()?;
+    match iter.next() {
+        Some(value) => Some(value.clone()),
+        None => {
+            eprintln!("Error: No value provided after `{}` flag", flag);
+            process::exit(1);
+        }
+    }
+}
+
+/// This function returns the chain and the contract of the election, set with `--chain-id` and `--contract`
+/// The signatures of the ballots are checked for them, so both are set or none of them
+/// If any of them is invalid, the application exits with an error
+fn ballot_domain(args: &[String]) -> Option<BallotDomain> {
+    let domain = match (flag_value(args, &["--chain-id"]), flag_value(args, &["--contract"])) {
+        (None, None) => return None,
+        (Some(chain_id), Some(contract)) => chain_id.trim().parse::<u64>()
+            .map_err(|err| format!("There was an error parsing the chain id: {}", err))
+            .and_then(|chain_id| Ok(BallotDomain {
+                chain_id,
+                verifying_contract: web3::types::Address::from_str_c(contract.trim())
+                    .map_err(|err| format!("There was an error parsing the contract address: {}", err))?,
+            })),
+        _ => Err("The ballots are signed for both the chain and the contract of the election, `--chain-id` and `--contract` must be set together".to_string()),
+    };
+    Some(domain.unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }))
+}
+
+/// This function parses a percentage between 0 and 100
+/// If the percentage is invalid, the application exits with an error
+fn parse_percentage(value: &str, flag: &str) -> u32 {
+    match value.trim().trim_end_matches('%').parse::<u32>() {
+        Ok(percentage) if percentage <= 100 => percentage,
+        _ => {
+            eprintln!("Error: `{}` must be a percentage between 0 and 100, got {}", flag, value);
+            process::exit(1);
+        }
+    }
+}
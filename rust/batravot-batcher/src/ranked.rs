@@ -0,0 +1,321 @@
+use std::collections::HashSet;
+use std::str::Split;
+use ark_ff::Zero;
+use serde_json::{json, Value};
+use web3::types::Address;
+use batravot_lib::{G1, RankedSpecifiers, verifier};
+use batravot_lib::ballot::parse_options;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::{BatcherConfig, InvalidBallotPolicy};
+use crate::ballots::{apply_policy, check_record_election_id, CheckedBallots, json_options, OptionsAggregator, read_options_ballots, RejectedBallot, REPEATED_BALLOT};
+use crate::input::file::BallotRecord;
+use crate::report::write_json;
+use crate::tally::{instant_runoff, print_runoff};
+
+/// This struct represents a ballot of a ranked election, which ranks the options in the order of preference
+/// public_key - The public key of the voter
+/// ranking - The ranked options, the most preferred first, the options that are not ranked are the least preferred
+/// vote_proof - The proof of the vote, made with the sum of the specifiers of the ranked options at their rank
+/// Ranked ballots can not be signed yet, so they are only accepted with `--allow-unsigned`
+#[derive(Clone)]
+pub struct RankedBallot {
+    pub voter_public_key: G1,
+    pub ranking: Vec<usize>,
+    pub vote_proof: G1,
+    pub eth_address: Address,
+}
+
+impl RankedBallot {
+    /// This function parses a ranked ballot from a JSON object
+    /// The object has the fields `publicKey`, `ranking`, `voteProof` and `address`
+    /// The ranking is either a list of options, e.g. `[2, 0, 1]`, or a string, e.g. `"2,0,1"` as in CSV
+    pub(crate) fn from_json(json: &Value) -> Result<RankedBallot, String> {
+        let field = |name: &str| json.get(name).and_then(|value| value.as_str())
+            .ok_or(format!("Error reading the `{}` field", name));
+
+        let public_key = G1::from_str_c(field("publicKey")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let ranking = json_options(json, "ranking")?;
+        let vote_proof = G1::from_str_c(field("voteProof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(field("address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        Ok(RankedBallot {
+            voter_public_key: public_key,
+            ranking,
+            vote_proof,
+            eth_address: address,
+        })
+    }
+
+    /// This function parses a ranked ballot from a set of strings
+    /// public_key - The public key of the voter, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
+    /// ranking - The ranked options, the most preferred first, in format `2,0,1` or `[2,0,1]`
+    /// vote_proof - The proof of the vote, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
+    /// address - The Ethereum address of the voter
+    pub(crate) fn from_iter(mut iter: Split<&str>) -> Result<RankedBallot, String> {
+        let public_key = G1::from_str_c(iter.next().ok_or("Error reading the public key")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let ranking = parse_options(iter.next().ok_or("Error reading the ranking")?)?;
+        let vote_proof = G1::from_str_c(iter.next().ok_or("Error reading the vote proof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(iter.next().ok_or("Error reading the address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        // Check that there are no more elements
+        if iter.next().is_some() {
+            return Err("There are more elements than expected".to_string());
+        }
+
+        Ok(RankedBallot {
+            voter_public_key: public_key,
+            ranking,
+            vote_proof,
+            eth_address: address,
+        })
+    }
+}
+
+impl BallotRecord for RankedBallot {
+    const CSV_COLUMNS: &'static [&'static str] = &["version", "publicKey", "ranking", "voteProof", "address"];
+
+    fn from_line(line: &str) -> Result<RankedBallot, String> {
+        RankedBallot::from_iter(line.split("|"))
+    }
+
+    fn from_record(record: &Value, election_id: u64) -> Result<RankedBallot, String> {
+        check_record_election_id(record, election_id)?;
+        RankedBallot::from_json(record)
+    }
+}
+
+/// This function checks the vote proofs of the ranked ballots and decides what to do with the invalid ones
+/// A ballot whose ranking is not valid, e.g. it ranks an option twice, is invalid
+/// It returns the ballots to include in the batch, each with whether it is valid, and the list of invalid ballots
+/// If the policy is to abort, an error is returned on the first invalid ballot
+pub(crate) fn proof_check_ranked_ballots(specifiers: &RankedSpecifiers, ballots: Vec<(usize, RankedBallot)>,
+                                         policy: InvalidBallotPolicy) -> Result<CheckedBallots<RankedBallot>, String> {
+    let mut checked_ballots = Vec::new();
+    let mut rejected_ballots = Vec::new();
+
+    // The proofs are all checked first, so that they can be checked in parallel
+    let votes: Vec<(&G1, &[usize], &G1)> = ballots.iter()
+        .map(|(_, ballot)| (&ballot.voter_public_key, ballot.ranking.as_slice(), &ballot.vote_proof))
+        .collect();
+    let valid_proofs = verifier::validate_ranked_vote_proofs(&votes, specifiers);
+
+    for ((position, mut ballot), valid_proof) in ballots.into_iter().zip(valid_proofs) {
+        let reason = match specifiers.check_ranking(&ballot.ranking) {
+            Err(reason) => reason,
+            Ok(()) if !valid_proof => "The Vote Proof is not valid for the ranking".to_string(),
+            Ok(()) => {
+                // The ballot is valid, so we keep it
+                checked_ballots.push((position, ballot, true));
+                continue;
+            }
+        };
+
+        let keep = apply_policy(policy, position, &ballot.eth_address, &reason)?;
+        rejected_ballots.push(RejectedBallot {
+            position,
+            eth_address: Some(ballot.eth_address),
+            reason,
+            kept: keep,
+        });
+        // A kept ballot is only counted up to the first option that does not exist or is ranked again, its vote proof makes the batch invalid anyway
+        if keep {
+            let valid_ranks = (0..ballot.ranking.len())
+                .take_while(|rank| ballot.ranking[*rank] < specifiers.ranks.len() && !ballot.ranking[..*rank].contains(&ballot.ranking[*rank]))
+                .count();
+            ballot.ranking.truncate(valid_ranks);
+            checked_ballots.push((position, ballot, false));
+        }
+    }
+
+    Ok((checked_ballots, rejected_ballots))
+}
+
+/// The aggregate of the ranked ballots of a batch that is read from a file
+/// The voters of each option at each rank are kept for the batch, and the rankings of the valid ballots for the single transferable vote tally
+/// The first ballot of a voter is kept and the later ones are rejected, so that the batch and the tally count the same ballot of each voter
+pub(crate) struct RankedAggregator {
+    election_proof: G1,
+    voters_by_rank: Vec<Vec<Vec<Address>>>,
+    rankings: Vec<(Address, Vec<usize>)>,
+    voters: HashSet<Address>,
+}
+
+impl RankedAggregator {
+    pub(crate) fn new(options: usize) -> RankedAggregator {
+        RankedAggregator {
+            election_proof: G1::zero(),
+            voters_by_rank: vec![vec![Vec::new(); options]; options],
+            rankings: Vec::new(),
+            voters: HashSet::new(),
+        }
+    }
+
+    /// Returns the batched election proof of all the ballots in the batch
+    pub(crate) fn election_proof(&self) -> G1 {
+        self.election_proof
+    }
+
+    /// Returns the voters who ranked each option at each rank, `voters_by_rank()[rank][option]`
+    pub(crate) fn voters_by_rank(&self) -> &[Vec<Vec<Address>>] {
+        &self.voters_by_rank
+    }
+
+    /// Returns the address and the ranking of each valid ballot, in the order of the ballots
+    /// The invalid ballots kept in the batch are not there, so that they are not tallied
+    pub(crate) fn rankings(&self) -> &[(Address, Vec<usize>)] {
+        &self.rankings
+    }
+}
+
+impl OptionsAggregator for RankedAggregator {
+    type Ballot = RankedBallot;
+    type Specifiers = RankedSpecifiers;
+    const KIND: &'static str = "ranked";
+
+    fn proof_check(specifiers: &RankedSpecifiers, ballots: Vec<(usize, RankedBallot)>, policy: InvalidBallotPolicy)
+                   -> Result<CheckedBallots<RankedBallot>, String> {
+        proof_check_ranked_ballots(specifiers, ballots, policy)
+    }
+
+    /// This function adds the checked ballots to the batch, the voter is added to the list of each option they rank, at its rank
+    /// If the voter already has a ballot in the batch, the ballot is rejected, so that a voter ranks each option at most once
+    /// Only the rankings of the valid ballots are kept for the tally, a kept invalid ballot is only in the batch
+    fn add(&mut self, ballots: Vec<(usize, RankedBallot, bool)>) -> Vec<RejectedBallot> {
+        let mut repeated_ballots = Vec::new();
+        for (position, ballot, valid) in ballots {
+            if !self.voters.insert(ballot.eth_address) {
+                repeated_ballots.push(RejectedBallot { position, eth_address: Some(ballot.eth_address), reason: REPEATED_BALLOT.to_string(), kept: false });
+                continue;
+            }
+            self.election_proof += &ballot.vote_proof;
+            for (rank, option) in ballot.ranking.iter().enumerate() {
+                self.voters_by_rank[rank][*option].push(ballot.eth_address);
+            }
+            if valid {
+                self.rankings.push((ballot.eth_address, ballot.ranking));
+            }
+        }
+        repeated_ballots
+    }
+
+    fn len(&self) -> usize {
+        self.voters.len()
+    }
+}
+
+/// This function batches the ballots of a ranked election, read from the file of the configuration, and tallies them with the single transferable vote
+/// The batch has the list of the voters who ranked each option at each rank, and the election proof of all the ballots
+pub(crate) fn run_ranked(election_id: u64, options: usize, seats: usize, config: &BatcherConfig) -> Result<(), String> {
+    let specifiers = RankedSpecifiers::from(election_id, options)?;
+    let mut aggregator = RankedAggregator::new(options);
+    let report = read_options_ballots(election_id, &specifiers, &mut aggregator, config)?;
+
+    // Print the results, only the ranks that have voters are printed
+    let election_proof = aggregator.election_proof();
+    println!("\n");
+    println!("----------------------------------------");
+    println!("Please submit the following data to the election verifier:");
+    println!("Election Proof:    {}", election_proof.solidity_repr());
+    for (rank, voters_by_option) in aggregator.voters_by_rank().iter().enumerate() {
+        for (option, voters) in voters_by_option.iter().enumerate().filter(|(_, voters)| !voters.is_empty()) {
+            println!("Who ranked {} at {}: [{}]", option, rank + 1, voters.iter().map(|x| x.solidity_repr()).collect::<Vec<String>>().join(", "));
+        }
+    }
+    println!("----------------------------------------");
+
+    // Write the batch and the report of the invalid ballots, if requested
+    if let Some(output) = &config.output {
+        write_json(output, &ranked_batch_json(election_id, &election_proof, aggregator.voters_by_rank()))
+            .map_err(|err| format!("Error writing the batch to {}: {}", output, err))?;
+        println!("The batch has been written to {}", output);
+    }
+    let rejected = report.rejected();
+    report.finish(aggregator.len())?;
+    if let Some(report) = &config.report {
+        println!("The report of {} invalid ballots has been written to {}", rejected, report);
+    }
+
+    // Tally the valid ballots, the invalid ballots that were kept are only in the batch
+    let outcome = instant_runoff(aggregator.rankings().iter().cloned(), options, seats);
+    print_runoff(&outcome);
+    println!("----------------------------------------");
+
+    Ok(())
+}
+
+/// This function returns the batch of a ranked election as JSON
+/// `votersByRank` has, for each rank, the list of the voters who ranked each option at that rank, in the order of the options
+pub(crate) fn ranked_batch_json(election_id: u64, election_proof: &G1, voters_by_rank: &[Vec<Vec<Address>>]) -> Value {
+    let voters_by_rank: Vec<Vec<Vec<String>>> = voters_by_rank.iter()
+        .map(|voters_by_option| voters_by_option.iter()
+            .map(|voters| voters.iter().map(|voter| voter.solidity_repr()).collect())
+            .collect())
+        .collect();
+    json!({
+        "electionId": election_id,
+        "votersByRank": voters_by_rank,
+        "electionProof": election_proof.solidity_repr(),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use batravot_lib::{ScalarField, voter};
+    use super::*;
+
+    #[test]
+    fn batches_the_voters_of_each_rank() {
+        let specifiers = RankedSpecifiers::from(1, 3).unwrap();
+        let ballot = |key: u64, ranking: &[usize], proof_ranking: &[usize]| {
+            let prk = ScalarField::from(key);
+            let line = format!("{}|{:?}|{}|{}", voter::generate_public_key(&prk).solidity_repr(), ranking,
+                               voter::generate_ranked_vote_proof(&prk, &specifiers, proof_ranking).unwrap().solidity_repr(),
+                               Address::from_low_u64_be(key).solidity_repr());
+            RankedBallot::from_line(&line).unwrap()
+        };
+
+        // The third ballot has the proof of another order of the same options, the fourth ranks an option twice
+        let ballots = vec![(1, ballot(1, &[2, 0], &[2, 0])), (2, ballot(2, &[1], &[1])), (3, ballot(3, &[1, 2], &[2, 1])), (4, ballot(4, &[0, 0], &[0]))];
+        let (checked_ballots, rejected_ballots) = proof_check_ranked_ballots(&specifiers, ballots, InvalidBallotPolicy::Keep).unwrap();
+        assert_eq!(rejected_ballots.iter().map(|ballot| ballot.position).collect::<Vec<usize>>(), vec![3, 4]);
+        // The kept ballot is only counted up to the option ranked again
+        assert_eq!(checked_ballots[3].1.ranking, vec![0]);
+        assert_eq!(checked_ballots.iter().map(|(_, _, valid)| *valid).collect::<Vec<bool>>(), vec![true, true, false, false]);
+
+        let mut aggregator = RankedAggregator::new(3);
+        assert!(aggregator.add(checked_ballots[..2].to_vec()).is_empty());
+        let address = Address::from_low_u64_be;
+        assert_eq!(aggregator.voters_by_rank()[0], vec![vec![], vec![address(2)], vec![address(1)]]);
+        assert_eq!(aggregator.voters_by_rank()[1], vec![vec![address(1)], vec![], vec![]]);
+
+        let public_keys: Vec<G1> = [1u64, 2].into_iter().map(|key| voter::generate_public_key(&ScalarField::from(key))).collect();
+        let rank_keys = vec![
+            vec![vec![], vec![&public_keys[1]], vec![&public_keys[0]]],
+            vec![vec![&public_keys[0]], vec![], vec![]],
+            vec![vec![], vec![], vec![]],
+        ];
+        assert!(verifier::validate_ranked_election_proof(&rank_keys, &aggregator.election_proof(), &specifiers));
+
+        // The kept invalid ballots are in the batch, but their rankings are not tallied
+        assert!(aggregator.add(checked_ballots[2..].to_vec()).is_empty());
+        assert_eq!(aggregator.len(), 4);
+        assert_eq!(aggregator.voters_by_rank()[0][0], vec![address(4)]);
+        assert_eq!(aggregator.rankings(), &[(address(1), vec![2, 0]), (address(2), vec![1])]);
+
+        // A later ballot of the voter 1 is rejected, neither the batch nor the tally count it
+        let election_proof = aggregator.election_proof();
+        let repeated_ballots = aggregator.add(vec![(5, ballot(1, &[1, 2], &[1, 2]), true)]);
+        assert_eq!(repeated_ballots.iter().map(|ballot| (ballot.position, ballot.reason.as_str(), ballot.kept)).collect::<Vec<_>>(), vec![(5, REPEATED_BALLOT, false)]);
+        assert_eq!(aggregator.len(), 4);
+        assert_eq!(aggregator.voters_by_rank()[0], vec![vec![address(4)], vec![address(2), address(3)], vec![address(1)]]);
+        assert_eq!(aggregator.rankings(), &[(address(1), vec![2, 0]), (address(2), vec![1])]);
+        assert_eq!(aggregator.election_proof(), election_proof);
+    }
+}
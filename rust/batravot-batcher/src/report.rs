@@ -0,0 +1,231 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use serde_json::{json, Value};
+use tempfile::NamedTempFile;
+use web3::types::Address;
+use batravot_lib::{G1, Vote};
+use batravot_lib::representation::SolidityRepresentable;
+use crate::ballots::RejectedBallot;
+use crate::batch::BatchAggregator;
+
+/// This function writes the batch that should be submitted to the election verifier as a JSON file
+/// The fields are named after the arguments of the `submitVotesWithProof` function of the contract
+/// The voters are read back from the aggregator and written one by one, the file has the same content as `batch_json`
+pub(crate) fn write_batch(file_path: &str, election_id: u64, aggregator: &mut BatchAggregator) -> Result<(), String> {
+    let write = |aggregator: &mut BatchAggregator| -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file_path)?);
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"electionId\": {},", election_id)?;
+        write_array(&mut writer, "votersFor", aggregator.voters(Vote::For)?.map(|voter| voter.map(|(address, _)| json!(address.solidity_repr()))))?;
+        writeln!(writer, ",")?;
+        write_array(&mut writer, "votersAgainst", aggregator.voters(Vote::Against)?.map(|voter| voter.map(|(address, _)| json!(address.solidity_repr()))))?;
+        writeln!(writer, ",")?;
+        write!(writer, "  \"electionProof\": {}", json!(aggregator.election_proof().solidity_repr()))?;
+        if aggregator.weighted() {
+            writeln!(writer, ",")?;
+            write_array(&mut writer, "weightsFor", aggregator.voters(Vote::For)?.map(|voter| voter.map(|(_, weight)| json!(weight.unwrap_or(1)))))?;
+            writeln!(writer, ",")?;
+            write_array(&mut writer, "weightsAgainst", aggregator.voters(Vote::Against)?.map(|voter| voter.map(|(_, weight)| json!(weight.unwrap_or(1)))))?;
+        }
+        writeln!(writer, "\n}}")?;
+        writer.flush()
+    };
+    write(aggregator).map_err(|err| format!("Error writing the batch to {}: {}", file_path, err))
+}
+
+/// This function writes a field of a batch that is a list, in the same layout as `serde_json::to_string_pretty`
+fn write_array(writer: &mut impl Write, name: &str, values: impl Iterator<Item = io::Result<Value>>) -> io::Result<()> {
+    write!(writer, "  \"{}\": [", name)?;
+    let mut empty = true;
+    for value in values {
+        write!(writer, "{}\n    {}", if empty { "" } else { "," }, value?)?;
+        empty = false;
+    }
+    if !empty {
+        write!(writer, "\n  ")?;
+    }
+    write!(writer, "]")
+}
+
+/// This function returns the batch that should be submitted to the election verifier as JSON
+/// If the batch has weighted ballots, the weights of the voters are in the `weightsFor` and `weightsAgainst` fields, in the order of the voters
+pub(crate) fn batch_json(election_id: u64, election_proof: &G1, for_voters: &[Address], against_voters: &[Address],
+                         weights: Option<&(Vec<u64>, Vec<u64>)>) -> Value {
+    let mut batch = json!({
+        "electionId": election_id,
+        "votersFor": addresses_json(for_voters),
+        "votersAgainst": addresses_json(against_voters),
+        "electionProof": election_proof.solidity_repr(),
+    });
+    if let Some((for_weights, against_weights)) = weights {
+        batch["weightsFor"] = json!(for_weights);
+        batch["weightsAgainst"] = json!(against_weights);
+    }
+    batch
+}
+
+/// The report of the invalid ballots, written to a JSON file as the ballots are checked
+/// The rejected ballots are written one per line, so that they are never all in memory, only their number is kept
+/// The report is written to a temporary file next to the report file, which replaces the report file once the batch is done
+/// so that a batcher that stops on an invalid ballot does not leave an incomplete report
+pub(crate) struct RejectionReport {
+    // The report file and the temporary file it is written to, if a report was requested
+    writer: Option<(String, BufWriter<NamedTempFile>)>,
+    rejected: usize,
+    kept: usize,
+}
+
+impl RejectionReport {
+    /// This function starts the report of an election, nothing is written if `file_path` is `None`, only the ballots are counted
+    pub(crate) fn create(file_path: Option<&str>, election_id: u64) -> Result<RejectionReport, String> {
+        let writer = match file_path {
+            Some(file_path) => {
+                let create = || -> io::Result<BufWriter<NamedTempFile>> {
+                    let directory = Path::new(file_path).parent().filter(|directory| !directory.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                    let mut writer = BufWriter::new(NamedTempFile::new_in(directory)?);
+                    write!(writer, "{{\n  \"electionId\": {},\n  \"rejected\": [", election_id)?;
+                    Ok(writer)
+                };
+                let writer = create().map_err(|err| format!("Error writing the report to {}: {}", file_path, err))?;
+                Some((file_path.to_string(), writer))
+            }
+            None => None,
+        };
+        Ok(RejectionReport { writer, rejected: 0, kept: 0 })
+    }
+
+    /// This function adds the rejected ballots of a chunk to the report, in the order of their positions
+    pub(crate) fn add(&mut self, mut ballots: Vec<RejectedBallot>) -> Result<(), String> {
+        ballots.sort_by_key(|ballot| ballot.position);
+        if let Some((file_path, writer)) = &mut self.writer {
+            for ballot in &ballots {
+                let entry = json!({
+                    "position": ballot.position,
+                    "address": ballot.eth_address.map(|address| address.solidity_repr()),
+                    "reason": ballot.reason,
+                    "action": if ballot.kept { "kept" } else { "dropped" },
+                });
+                write!(writer, "{}\n    {}", if self.rejected == 0 { "" } else { "," }, entry)
+                    .map_err(|err| format!("Error writing the report to {}: {}", file_path, err))?;
+                self.rejected += 1;
+            }
+        } else {
+            self.rejected += ballots.len();
+        }
+        self.kept += ballots.iter().filter(|ballot| ballot.kept).count();
+        Ok(())
+    }
+
+    /// Returns the number of the rejected ballots, including the invalid ballots that were kept
+    pub(crate) fn rejected(&self) -> usize {
+        self.rejected
+    }
+
+    /// Returns the number of the invalid ballots that were kept in the batch
+    pub(crate) fn kept(&self) -> usize {
+        self.kept
+    }
+
+    /// This function ends the report and moves it to the report file
+    /// accepted - The number of ballots included in the batch, including the invalid ballots that were kept
+    pub(crate) fn finish(self, accepted: usize) -> Result<(), String> {
+        let Some((file_path, mut writer)) = self.writer else {
+            return Ok(());
+        };
+        let finish = || -> io::Result<()> {
+            write!(writer, "{}],\n  \"accepted\": {}\n}}\n", if self.rejected == 0 { "" } else { "\n  " }, accepted)?;
+            let file = writer.into_inner().map_err(|err| err.into_error())?;
+            file.persist(&file_path)?;
+            Ok(())
+        };
+        finish().map_err(|err| format!("Error writing the report to {}: {}", file_path, err))
+    }
+}
+
+fn addresses_json(addresses: &[Address]) -> Vec<String> {
+    addresses.iter().map(|address| address.solidity_repr()).collect()
+}
+
+pub(crate) fn write_json(file_path: &str, value: &Value) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(value).map_err(|err| err.to_string())?;
+    fs::write(file_path, content + "\n").map_err(|err| err.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use batravot_lib::{ScalarField, voter};
+    use crate::ballots::Ballot;
+    use super::*;
+
+    fn ballot(key: u64, vote: Vote, weight: Option<u64>) -> Ballot {
+        let prk = ScalarField::from(key);
+        Ballot {
+            voter_public_key: voter::generate_public_key(&prk),
+            vote,
+            vote_proof: voter::generate_public_key(&prk),
+            eth_address: Address::from_low_u64_be(key),
+            signature: None,
+            dleq_proof: None,
+            weight,
+        }
+    }
+
+    #[test]
+    fn writes_the_batch_like_batch_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("batch.json");
+        let file_path = file_path.to_str().unwrap();
+        let address = Address::from_low_u64_be;
+
+        // The votes are read back from the temporary file, also after more ballots were added
+        let mut aggregator = BatchAggregator::new().unwrap();
+        aggregator.add(&[ballot(1, Vote::For, None), ballot(2, Vote::Against, None)]).unwrap();
+        write_batch(file_path, 4, &mut aggregator).unwrap();
+        let expected = batch_json(4, &aggregator.election_proof(), &[address(1)], &[address(2)], None);
+        assert_eq!(fs::read_to_string(file_path).unwrap(), serde_json::to_string_pretty(&expected).unwrap() + "\n");
+
+        aggregator.add(&[ballot(3, Vote::For, Some(5))]).unwrap();
+        write_batch(file_path, 4, &mut aggregator).unwrap();
+        let weights = (vec![1, 5], vec![1]);
+        let expected = batch_json(4, &aggregator.election_proof(), &[address(1), address(3)], &[address(2)], Some(&weights));
+        assert_eq!(fs::read_to_string(file_path).unwrap(), serde_json::to_string_pretty(&expected).unwrap() + "\n");
+
+        let mut empty = BatchAggregator::new().unwrap();
+        write_batch(file_path, 4, &mut empty).unwrap();
+        let expected = batch_json(4, &empty.election_proof(), &[], &[], None);
+        assert_eq!(fs::read_to_string(file_path).unwrap(), serde_json::to_string_pretty(&expected).unwrap() + "\n");
+    }
+
+    #[test]
+    fn writes_the_rejection_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("report.json");
+        let file_path = file_path.to_str().unwrap();
+        let rejected = |position: usize, kept: bool| RejectedBallot { position, eth_address: None, reason: "Invalid".to_string(), kept };
+
+        let mut report = RejectionReport::create(Some(file_path), 3).unwrap();
+        report.add(vec![rejected(4, true), rejected(2, false)]).unwrap();
+        report.add(vec![]).unwrap();
+        report.add(vec![rejected(7, false)]).unwrap();
+        assert_eq!((report.rejected(), report.kept()), (3, 1));
+        // The report file is only written once the report is finished
+        assert!(!dir.path().join("report.json").exists());
+        report.finish(10).unwrap();
+
+        let report: Value = serde_json::from_str(&fs::read_to_string(file_path).unwrap()).unwrap();
+        assert_eq!(report["electionId"], 3);
+        assert_eq!(report["accepted"], 10);
+        let entries = report["rejected"].as_array().unwrap();
+        assert_eq!(entries.iter().map(|entry| entry["position"].as_u64().unwrap()).collect::<Vec<_>>(), vec![2, 4, 7]);
+        assert_eq!(entries[1]["action"], "kept");
+        assert_eq!(entries[0]["address"], Value::Null);
+
+        let report = RejectionReport::create(Some(file_path), 3).unwrap();
+        report.finish(0).unwrap();
+        let report: Value = serde_json::from_str(&fs::read_to_string(file_path).unwrap()).unwrap();
+        assert_eq!(report["rejected"], json!([]));
+    }
+}
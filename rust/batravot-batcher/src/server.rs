@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use colored::Colorize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+use batravot_lib::representation::SolidityRepresentable;
+use crate::ballots::Ballot;
+use web3::types::Address;
+use batravot_lib::G1;
+use batravot_lib::ballot::BallotReceipt;
+use batravot_lib::ethereum::{self, BallotDomain, SecretKey};
+use batravot_lib::representation::FromStrCustom;
+use batravot_lib::submission::{ElectionVotes, MultiElectionSubmission};
+use crate::batch::{ElectionBatch, Receipt};
+use crate::report::batch_json;
+use crate::store::{BallotStore, StoreRecord};
+
+/// The maximum size of a request body, a ballot is well below it
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// The options of the batcher server
+/// listen - The address to listen on, e.g. `127.0.0.1:8080`
+/// elections - The ids of the elections the server accepts ballots for
+/// store - The file of the ballot store, the ballots are only kept in memory if it is not provided
+/// allow_unsigned - Whether the ballots without a signature are accepted, by default they are rejected
+/// domain - The chain and the contract of the elections, the ballots are signed for them, required unless `allow_unsigned` is set
+/// receipt_key - The key of the Ethereum account of the batcher, it signs the receipts of the admitted ballots
+pub struct ServerConfig {
+    pub listen: String,
+    pub elections: Vec<u64>,
+    pub store: Option<String>,
+    pub allow_unsigned: bool,
+    pub domain: Option<BallotDomain>,
+    pub receipt_key: SecretKey,
+}
+
+/// This function starts the HTTP server that accepts the ballots of the voters
+/// The server runs until the process is stopped, the requests are handled one by one
+/// If the store is provided, every ballot is written to it before it is acknowledged, and the batches are restored from it on start
+/// The API is the following:
+/// `POST /elections/<id>/ballots` - Submit a ballot as JSON, it is checked straight away and either accepted or rejected
+/// `GET /elections` - List the elections with the number of accepted ballots
+/// `GET /elections/<id>` - Get the current batch of the election, in the same format as the `--output` file
+/// `GET /elections/aggregate` - Get the batches of all the elections with ballots, checked together with one aggregated proof
+/// `GET /elections/<id>/ballots/<address>` - Get the receipt of the ballot of the voter currently in the batch
+pub fn serve(config: ServerConfig) -> Result<(), String> {
+    if config.elections.is_empty() {
+        return Err("The server must accept ballots for at least one election".to_string());
+    }
+    if config.domain.is_none() && !config.allow_unsigned {
+        return Err("The signatures of the ballots are checked for the chain and the contract of the elections, `--chain-id` and `--contract` must be set".to_string());
+    }
+
+    let server = Server::http(&config.listen)
+        .map_err(|err| format!("Error starting the server on {}: {}", config.listen, err))?;
+    let mut ballot_server = BallotServer::new(&config.elections, !config.allow_unsigned, config.domain, config.receipt_key);
+    if let Some(store_path) = &config.store {
+        let (store, records) = BallotStore::open(store_path)?;
+        let restored = records.len();
+        ballot_server.restore(records)?;
+        ballot_server.store = Some(store);
+        println!("Restored {} records from the store {}", restored, store_path);
+    }
+
+    println!("{}", format!("Accepting ballots on http://{} for elections {:?}", config.listen, config.elections).green());
+    println!("The receipts are signed by the batcher account {}", ethereum::address(&config.receipt_key).solidity_repr());
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, response) = match request.as_reader().take(MAX_BODY_SIZE).read_to_string(&mut body) {
+            Ok(_) => ballot_server.handle(request.method().as_str(), request.url(), &body),
+            Err(err) => (400, rejection(&format!("Error reading the request: {}", err))),
+        };
+
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .expect("The content type header is valid");
+        let response = Response::from_string(response.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(err) = request.respond(response) {
+            eprintln!("{}", format!("Error sending the response: {}", err).red());
+        }
+    }
+
+    Ok(())
+}
+
+/// The state of the server, a batch for each election it accepts ballots for, and the key that signs the receipts
+pub(crate) struct BallotServer {
+    elections: BTreeMap<u64, ElectionBatch>,
+    store: Option<BallotStore>,
+    receipt_key: SecretKey,
+}
+
+impl BallotServer {
+    pub(crate) fn new(election_ids: &[u64], require_signatures: bool, domain: Option<BallotDomain>, receipt_key: SecretKey) -> BallotServer {
+        BallotServer {
+            elections: election_ids.iter().map(|id| (*id, ElectionBatch::new(*id, require_signatures, domain))).collect(),
+            store: None,
+            receipt_key,
+        }
+    }
+
+    /// This function restores the batches from the records of the store
+    /// The ballots were checked before they were stored, so they are not checked again
+    /// Instead, the restored batches are compared with the aggregate records
+    pub(crate) fn restore(&mut self, records: Vec<StoreRecord>) -> Result<(), String> {
+        let mut ignored = 0;
+        for record in records {
+            let election_id = match &record {
+                StoreRecord::Accepted { election_id, .. } => election_id,
+                StoreRecord::Rejected { election_id, .. } => election_id,
+                StoreRecord::Aggregate { election_id, .. } => election_id,
+            };
+            let batch = match self.elections.get_mut(election_id) {
+                Some(batch) => batch,
+                None => {
+                    ignored += 1;
+                    continue;
+                }
+            };
+
+            match record {
+                StoreRecord::Accepted { ballot, receipt, .. } => batch.insert(*ballot, receipt),
+                StoreRecord::Rejected { .. } => batch.rejected += 1,
+                StoreRecord::Aggregate { election_id, ballots, election_proof } => {
+                    if batch.len() != ballots || batch.election_proof() != election_proof {
+                        return Err(format!("The store is inconsistent, the restored batch of the election {} does not match its aggregate record", election_id));
+                    }
+                }
+            }
+        }
+
+        if ignored > 0 {
+            eprintln!("{}", format!("{} records of the store are for elections that are not served, they were ignored", ignored).red());
+        }
+        Ok(())
+    }
+
+    /// This function handles a single request and returns the status code with the JSON response
+    pub(crate) fn handle(&mut self, method: &str, url: &str, body: &str) -> (u16, Value) {
+        let path: Vec<&str> = url.split('?').next().unwrap_or("")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        match (method, path.as_slice()) {
+            ("GET", ["elections"]) => (200, self.list_elections()),
+            ("GET", ["elections", "aggregate"]) => self.aggregate(),
+            ("GET", ["elections", election_id]) => match find_election(&mut self.elections, election_id) {
+                Ok(batch) => (200, election_json(batch)),
+                Err(response) => response,
+            },
+            ("POST", ["elections", election_id, "ballots"]) => self.submit_ballot(election_id, body),
+            ("GET", ["elections", election_id, "ballots", address]) => match find_election(&mut self.elections, election_id) {
+                Ok(batch) => ballot_receipt(batch, address, &self.receipt_key),
+                Err(response) => response,
+            },
+            (_, ["elections"]) | (_, ["elections", _]) | (_, ["elections", _, "ballots"]) | (_, ["elections", _, "ballots", _]) => (405, rejection("Method not allowed")),
+            _ => (404, rejection("Not found")),
+        }
+    }
+
+    fn list_elections(&self) -> Value {
+        let elections: Vec<Value> = self.elections.values()
+            .map(|batch| json!({ "electionId": batch.election_id, "ballots": batch.len(), "rejected": batch.rejected }))
+            .collect();
+        json!({ "elections": elections })
+    }
+
+    /// This function aggregates the batches of the elections with ballots into a single submission
+    /// The submission is checked off-chain, e.g. by the verifier, the contract has no function for it
+    /// The submission has no weights, so the elections with weighted ballots can not be aggregated
+    /// There is nothing to aggregate until an election has ballots, which is a conflict with the state of the server
+    fn aggregate(&self) -> (u16, Value) {
+        if let Some(batch) = self.elections.values().find(|batch| batch.weights().is_some()) {
+            return (409, rejection(&format!("The election {} has weighted ballots, which can not be aggregated", batch.election_id)));
+        }
+        let batches: Vec<(ElectionVotes, G1)> = self.elections.values()
+            .filter(|batch| batch.len() > 0)
+            .map(|batch| {
+                let (voters_for, voters_against) = batch.voters();
+                (ElectionVotes { election_id: batch.election_id, voters_for, voters_against }, batch.election_proof())
+            })
+            .collect();
+        if batches.is_empty() {
+            return (409, rejection("No election has ballots yet, there is nothing to aggregate"));
+        }
+        match MultiElectionSubmission::aggregate(batches) {
+            Ok(submission) => (200, submission.to_json()),
+            Err(err) => (409, rejection(&err)),
+        }
+    }
+
+    /// This function checks the ballot and adds it to the batch of the election if it is valid
+    /// Both the accepted and the rejected ballots are recorded in the store, if there is one
+    /// A ballot is only acknowledged once it is in the store
+    fn submit_ballot(&mut self, election_id: &str, body: &str) -> (u16, Value) {
+        let batch = match find_election(&mut self.elections, election_id) {
+            Ok(batch) => batch,
+            Err(response) => return response,
+        };
+
+        let ballot = match serde_json::from_str(body).map_err(|err| err.to_string()).and_then(|json| Ballot::from_json(&json)) {
+            Ok(ballot) => ballot,
+            Err(err) => {
+                let reason = format!("Error parsing the ballot: {}", err);
+                batch.rejected += 1;
+                persist(&mut self.store, &StoreRecord::Rejected { election_id: batch.election_id, address: None, reason: reason.clone() });
+                return (400, rejection(&reason));
+            }
+        };
+        let address = ballot.eth_address.solidity_repr();
+        let ballot_address = ballot.eth_address;
+
+        if let Err(reason) = batch.check(&ballot) {
+            eprintln!("{}", format!("Rejected the ballot of {} for the election {}: {}", address, batch.election_id, reason).red());
+            batch.rejected += 1;
+            persist(&mut self.store, &StoreRecord::Rejected { election_id: batch.election_id, address: Some(ballot.eth_address), reason: reason.clone() });
+            return (422, rejection(&reason));
+        }
+
+        // The ballot is stored before it is added to the batch, so that an accepted ballot is never lost
+        let receipt = batch.next_receipt(&ballot);
+        if let Some(store) = &mut self.store {
+            if let Err(err) = store.append(&StoreRecord::Accepted { election_id: batch.election_id, ballot: Box::new(ballot.clone()), receipt }) {
+                eprintln!("{}", err.red());
+                return (500, rejection("The ballot could not be stored, please submit it again"));
+            }
+        }
+        batch.insert(ballot, receipt);
+        persist(&mut self.store, &StoreRecord::Aggregate { election_id: batch.election_id, ballots: batch.len(), election_proof: batch.election_proof() });
+
+        println!("Accepted the ballot of {} for the election {}", address, batch.election_id);
+        (200, json!({
+            "status": "accepted",
+            "ballots": batch.len(),
+            "receipt": receipt_json(batch.election_id, &ballot_address, &receipt, &self.receipt_key),
+        }))
+    }
+}
+
+/// Returns the batch of the election, or the error response if the election is not served
+fn find_election<'a>(elections: &'a mut BTreeMap<u64, ElectionBatch>, election_id: &str) -> Result<&'a mut ElectionBatch, (u16, Value)> {
+    let election_id = election_id.parse::<u64>()
+        .map_err(|err| (400, rejection(&format!("There was an error parsing the Election Id: {}", err))))?;
+    elections.get_mut(&election_id)
+        .ok_or((404, rejection(&format!("The election {} is not served by this batcher", election_id))))
+}
+
+/// Appends the record to the store, if there is one
+/// The records other than the accepted ballots are not needed to restore the batches, so a failure is only reported
+fn persist(store: &mut Option<BallotStore>, record: &StoreRecord) {
+    if let Some(store) = store {
+        if let Err(err) = store.append(record) {
+            eprintln!("{}", err.red());
+        }
+    }
+}
+
+/// Returns the current batch of the election as JSON
+/// The election proof is `null` as long as there are no ballots
+fn election_json(batch: &ElectionBatch) -> Value {
+    let (for_voters, against_voters) = batch.voters();
+    let mut election = batch_json(batch.election_id, &batch.election_proof(), &for_voters, &against_voters, batch.weights().as_ref());
+    if batch.len() == 0 {
+        election["electionProof"] = Value::Null;
+    }
+    election["ballots"] = json!(batch.len());
+    election
+}
+
+/// Returns the receipt of the ballot of the voter, or 404 if the voter has no ballot in the batch
+fn ballot_receipt(batch: &ElectionBatch, address: &str, receipt_key: &SecretKey) -> (u16, Value) {
+    let voter = match Address::from_str_c(address) {
+        Ok(voter) => voter,
+        Err(err) => return (400, rejection(&format!("Error parsing the address: {}", err))),
+    };
+    match batch.receipt(&voter) {
+        Some(receipt) => (200, json!({
+            "status": "included",
+            "receipt": receipt_json(batch.election_id, &voter, &receipt, receipt_key),
+        })),
+        None => (404, rejection(&format!("There is no ballot of {} in the election {}", address, batch.election_id))),
+    }
+}
+
+/// Returns the receipt as JSON, with the address of the batcher and its signature of the receipt
+fn receipt_json(election_id: u64, address: &Address, receipt: &Receipt, receipt_key: &SecretKey) -> Value {
+    let receipt = BallotReceipt { election_id, address: *address, sequence: receipt.sequence, ballot_hash: receipt.ballot_hash };
+    json!({
+        "electionId": election_id,
+        "address": address.solidity_repr(),
+        "sequence": receipt.sequence,
+        "ballotHash": format!("{:?}", receipt.ballot_hash),
+        "batcherAddress": ethereum::address(receipt_key).solidity_repr(),
+        "signature": receipt.sign(receipt_key).solidity_repr(),
+    })
+}
+
+fn rejection(reason: &str) -> Value {
+    json!({ "status": "rejected", "reason": reason })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use batravot_lib::{DleqProof, ElectionSpecifiers, ScalarField, verifier, Vote, voter};
+    use batravot_lib::ethereum::{BallotDomain, EthSignature, TypedBallot};
+    use super::*;
+
+    /// The Ethereum account of the voter of all the ballots
+    fn sign_key() -> SecretKey {
+        ethereum::parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap()
+    }
+
+    /// The chain and the contract of the elections, the ballots are signed for them
+    fn domain() -> BallotDomain {
+        BallotDomain { chain_id: 11155111, verifying_contract: Address::from_low_u64_be(0xc0) }
+    }
+
+    /// The Ethereum account of the batcher, it signs the receipts
+    fn receipt_key() -> SecretKey {
+        ethereum::parse_secret_key("0x0123456789012345678901234567890123456789012345678901234567890123").unwrap()
+    }
+
+    /// Returns the ballot with the vote proof for `proof_vote`, signed for the election with the sequence
+    fn ballot_json(prk: u64, vote: &str, proof_vote: &str, election_id: u64, sequence: u64) -> String {
+        let prk = ScalarField::from(prk);
+        let specifiers = ElectionSpecifiers::from(election_id);
+        let specifier = if proof_vote == "for" { &specifiers.forr.0 } else { &specifiers.against.0 };
+        let vote_proof = voter::generate_vote_proof(&prk, specifier);
+        let typed_ballot = TypedBallot { election_id, vote: Vote::from_str(vote).unwrap(), vote_proof, sequence };
+        json!({
+            "publicKey": voter::generate_public_key(&prk).solidity_repr(),
+            "vote": vote,
+            "voteProof": vote_proof.solidity_repr(),
+            "address": ethereum::address(&sign_key()).solidity_repr(),
+            "sequence": sequence,
+            "signature": typed_ballot.sign(&domain(), &sign_key()).solidity_repr(),
+        }).to_string()
+    }
+
+    #[test]
+    fn accepts_valid_ballots_and_exposes_the_batch() {
+        let mut server = BallotServer::new(&[1, 2], true, Some(domain()), receipt_key());
+
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "for", 1, 1));
+        assert_eq!(status, 200, "{}", response);
+        assert_eq!(response["status"], "accepted");
+        assert_eq!(response["receipt"]["sequence"], 1);
+        let first_hash = response["receipt"]["ballotHash"].clone();
+
+        // The second ballot of the same voter replaces the first one
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &ballot_json(5, "against", "against", 1, 2));
+        assert_eq!(status, 200);
+        let receipt = response["receipt"].clone();
+        assert_eq!(receipt["sequence"], 2);
+        assert_ne!(receipt["ballotHash"], first_hash);
+
+        // The receipt is signed by the account of the batcher
+        let signed_receipt = BallotReceipt {
+            election_id: 1,
+            address: ethereum::address(&sign_key()),
+            sequence: 2,
+            ballot_hash: receipt["ballotHash"].as_str().unwrap().parse().unwrap(),
+        };
+        let signature = EthSignature::from_str_c(receipt["signature"].as_str().unwrap()).unwrap();
+        assert_eq!(signed_receipt.recover_signer(&signature), Ok(ethereum::address(&receipt_key())));
+        assert_eq!(receipt["batcherAddress"], ethereum::address(&receipt_key()).solidity_repr());
+
+        // Only the receipt of the last ballot is in the batch
+        let path = format!("/elections/1/ballots/{}", ethereum::address(&sign_key()).solidity_repr());
+        let (status, response) = server.handle("GET", &path, "");
+        assert_eq!(status, 200);
+        assert_eq!(response["receipt"], receipt);
+        assert_eq!(server.handle("GET", "/elections/2/ballots/0x0000000000000000000000000000000000000001", "").0, 404);
+
+        let (status, response) = server.handle("GET", "/elections/1", "");
+        assert_eq!(status, 200);
+        assert_eq!(response["ballots"], 1);
+        assert_eq!(response["votersFor"].as_array().unwrap().len(), 0);
+        assert_eq!(response["votersAgainst"].as_array().unwrap().len(), 1);
+
+        let (_, response) = server.handle("GET", "/elections/2", "");
+        assert_eq!(response["electionProof"], Value::Null);
+    }
+
+    #[test]
+    fn rejects_invalid_ballots() {
+        let mut server = BallotServer::new(&[1], true, Some(domain()), receipt_key());
+
+        // The proof is generated for another vote or another election
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "against", 1, 1)).0, 422);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "for", 2, 1)).0, 422);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", "{}").0, 400);
+        assert_eq!(server.handle("POST", "/elections/2/ballots", &ballot_json(5, "for", "for", 2, 1)).0, 404);
+        assert_eq!(server.handle("GET", "/elections/1/ballots", "").0, 405);
+
+        let (_, response) = server.handle("GET", "/elections", "");
+        assert_eq!(response["elections"][0]["ballots"], 0);
+
+        // The ballot is signed for the contract of `domain`, not for the contract of the server, or for no contract at all
+        let other_domain = BallotDomain { verifying_contract: Address::from_low_u64_be(0xc1), ..domain() };
+        for domain in [Some(other_domain), None] {
+            let mut server = BallotServer::new(&[1], false, domain, receipt_key());
+            assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "for", 1, 1)).0, 422);
+        }
+    }
+
+    #[test]
+    fn restores_the_batches_from_the_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.jsonl").to_string_lossy().to_string();
+        let start = || {
+            let mut server = BallotServer::new(&[1], true, Some(domain()), receipt_key());
+            let (store, records) = BallotStore::open(&path).unwrap();
+            server.restore(records).unwrap();
+            server.store = Some(store);
+            server
+        };
+
+        let mut server = start();
+        server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "for", 1, 1));
+        server.handle("POST", "/elections/1/ballots", &ballot_json(5, "for", "against", 1, 2));
+        server.handle("POST", "/elections/1/ballots", &ballot_json(6, "against", "against", 1, 3));
+        let (_, before) = server.handle("GET", "/elections/1", "");
+        drop(server);
+
+        let mut server = start();
+        let (_, after) = server.handle("GET", "/elections/1", "");
+        assert_eq!(before, after);
+        assert_eq!(server.handle("GET", "/elections", "").1["elections"][0]["rejected"], 1);
+
+        // The sequence of the receipts continues after the restored ballots, the sequence of the restored ballot is still enforced
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(7, "for", "for", 1, 3)).0, 422);
+        let (_, response) = server.handle("POST", "/elections/1/ballots", &ballot_json(7, "for", "for", 1, 4));
+        assert_eq!(response["receipt"]["sequence"], 3);
+    }
+
+    #[test]
+    fn rejects_unsigned_and_replayed_ballots() {
+        let mut server = BallotServer::new(&[1], true, Some(domain()), receipt_key());
+        let mut unsigned: Value = serde_json::from_str(&ballot_json(5, "for", "for", 1, 1)).unwrap();
+        unsigned.as_object_mut().unwrap().remove("signature");
+        unsigned.as_object_mut().unwrap().remove("sequence");
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &unsigned.to_string());
+        assert_eq!((status, response["reason"].as_str().unwrap()), (422, "The signature is missing"));
+
+        // The signature is not from the address of the ballot
+        let mut other_address: Value = serde_json::from_str(&ballot_json(5, "for", "for", 1, 1)).unwrap();
+        other_address["address"] = json!(Address::from_low_u64_be(1).solidity_repr());
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &other_address.to_string()).0, 422);
+
+        // An older ballot of the voter can not replace its later ballot
+        let older = ballot_json(5, "for", "for", 1, 1);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(5, "against", "against", 1, 2)).0, 200);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &older).0, 422);
+
+        // The unsigned ballots are accepted if the server allows them, but they can not replace the ballot of the voter
+        let mut server = BallotServer::new(&[1], false, Some(domain()), receipt_key());
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &unsigned.to_string()).0, 200);
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &unsigned.to_string());
+        assert_eq!((status, response["reason"].as_str().unwrap()), (422, "The voter already has a ballot in the batch, only a signed ballot can replace it"));
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &ballot_json(5, "against", "against", 1, 2)).0, 200);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &unsigned.to_string()).0, 422);
+    }
+
+    #[test]
+    fn checks_the_dleq_proof_instead_of_the_pairing() {
+        let rng = &mut ark_std::test_rng();
+        let mut server = BallotServer::new(&[1], true, Some(domain()), receipt_key());
+        let specifiers = ElectionSpecifiers::from(1);
+
+        let mut ballot: Value = serde_json::from_str(&ballot_json(5, "for", "for", 1, 1)).unwrap();
+        ballot["dleqProof"] = json!(DleqProof::generate_proof(&ScalarField::from(5), &specifiers.forr.0, rng).solidity_repr());
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &ballot.to_string());
+        assert_eq!(status, 200, "{}", response);
+
+        // A DLEQ proof of another key is rejected, even though the vote proof itself is valid
+        // The ballots are signed by the same account, so the sequence is increased
+        let mut ballot: Value = serde_json::from_str(&ballot_json(6, "for", "for", 1, 2)).unwrap();
+        ballot["dleqProof"] = json!(DleqProof::generate_proof(&ScalarField::from(7), &specifiers.forr.0, rng).solidity_repr());
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &ballot.to_string());
+        assert_eq!((status, response["reason"].as_str().unwrap()), (422, "The DLEQ proof of the Vote Proof is not valid"));
+    }
+
+    #[test]
+    fn checks_the_declared_weight_of_weighted_ballots() {
+        let mut server = BallotServer::new(&[1], false, Some(domain()), receipt_key());
+        let specifiers = ElectionSpecifiers::from(1);
+        // Each voter signs with their own account, the signature binds the weight through the vote proof
+        let weighted = |prk: u64, proof_weight: u64, weight: Value, signed: bool| {
+            let sign_key = ethereum::parse_secret_key(&format!("0x{:064x}", prk)).unwrap();
+            let vote_proof = voter::generate_weighted_vote_proof(&ScalarField::from(prk), &specifiers.forr.0, proof_weight);
+            let mut ballot = json!({
+                "publicKey": voter::generate_public_key(&ScalarField::from(prk)).solidity_repr(),
+                "vote": "for",
+                "voteProof": vote_proof.solidity_repr(),
+                "address": ethereum::address(&sign_key).solidity_repr(),
+                "weight": weight,
+            });
+            if signed {
+                let typed_ballot = TypedBallot { election_id: 1, vote: Vote::For, vote_proof, sequence: 1 };
+                ballot["sequence"] = json!(1);
+                ballot["signature"] = json!(typed_ballot.sign(&domain(), &sign_key).solidity_repr());
+            }
+            ballot.to_string()
+        };
+
+        // The proof is scaled by another weight than the declared one
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &weighted(5, 30, json!(31), true));
+        assert_eq!((status, response["reason"].as_str().unwrap()), (422, "The Vote Proof is not valid for the declared weight"));
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &weighted(5, 0, json!(0), true)).0, 400);
+        // The unsigned ballots are allowed, but not the unsigned weighted ballots
+        let (status, response) = server.handle("POST", "/elections/1/ballots", &weighted(5, 30, json!(30), false));
+        assert_eq!((status, response["reason"].as_str().unwrap()), (422, "A weighted ballot must be signed, otherwise its weight is not bound to the voter"));
+
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &weighted(5, 30, json!(30), true)).0, 200);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &weighted(6, 1, Value::Null, false)).0, 200);
+        let (_, response) = server.handle("GET", "/elections/1", "");
+        assert_eq!(response["weightsFor"], json!([30, 1]));
+        assert_eq!(response["weightsAgainst"], json!([]));
+
+        let keys = [voter::generate_public_key(&ScalarField::from(5)), voter::generate_public_key(&ScalarField::from(6))];
+        let proof = G1::from_str_c(response["electionProof"].as_str().unwrap()).unwrap();
+        assert!(verifier::validate_weighted_election_proof(&[(&keys[0], 30), (&keys[1], 1)], &[], &proof, &specifiers));
+
+        // The multi-election submission has no weights
+        assert_eq!(server.handle("GET", "/elections/aggregate", "").0, 409);
+    }
+
+    #[test]
+    fn aggregates_the_batches_of_all_the_elections() {
+        let mut server = BallotServer::new(&[1, 2, 3], false, Some(domain()), receipt_key());
+        let (status, response) = server.handle("GET", "/elections/aggregate", "");
+        assert_eq!((status, response["reason"].as_str()), (409, Some("No election has ballots yet, there is nothing to aggregate")));
+
+        // The voters are told apart by their public keys, as the ballots are unsigned
+        let unsigned = |prk: u64, vote: &str, election_id: u64| {
+            let mut ballot: Value = serde_json::from_str(&ballot_json(prk, vote, vote, election_id, 1)).unwrap();
+            ballot["address"] = json!(Address::from_low_u64_be(prk).solidity_repr());
+            ballot.as_object_mut().unwrap().remove("signature");
+            ballot.as_object_mut().unwrap().remove("sequence");
+            ballot.to_string()
+        };
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &unsigned(5, "for", 1)).0, 200);
+        assert_eq!(server.handle("POST", "/elections/1/ballots", &unsigned(6, "against", 1)).0, 200);
+        assert_eq!(server.handle("POST", "/elections/3/ballots", &unsigned(7, "for", 3)).0, 200);
+
+        // The election without ballots is left out of the submission
+        let (status, response) = server.handle("GET", "/elections/aggregate", "");
+        assert_eq!(status, 200, "{}", response);
+        let submission = MultiElectionSubmission::from_json(&response).unwrap();
+        assert_eq!(submission.elections.iter().map(|election| election.election_id).collect::<Vec<_>>(), vec![1, 3]);
+
+        let key = |prk: u64| voter::generate_public_key(&ScalarField::from(prk));
+        let specifiers = [ElectionSpecifiers::from(1), ElectionSpecifiers::from(3)];
+        let elections = [(key(5), key(6), &specifiers[0]), (key(7), G1::default(), &specifiers[1])];
+        assert!(verifier::validate_multi_election_proof(&elections, &submission.election_proof));
+    }
+}
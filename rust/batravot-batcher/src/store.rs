@@ -0,0 +1,192 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use colored::Colorize;
+use serde_json::{json, Value};
+use web3::types::{Address, H256};
+use batravot_lib::G1;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::ballots::Ballot;
+use crate::batch::Receipt;
+
+/// The append-only log of the ballots received by the batcher server
+/// Each record is a single line of JSON, written and synced to the disk before the response is sent
+/// On restart, the records are read back in order to restore the batches of the elections
+pub(crate) struct BallotStore {
+    path: String,
+    file: File,
+}
+
+/// A record of the store
+/// Accepted - A ballot that was added to the batch of the election, with its receipt
+/// Rejected - A ballot that was rejected, the address is not known if the ballot could not be parsed
+/// Aggregate - The state of the batch after an accepted ballot, used to check the restored batch
+pub(crate) enum StoreRecord {
+    Accepted { election_id: u64, ballot: Box<Ballot>, receipt: Receipt },
+    Rejected { election_id: u64, address: Option<Address>, reason: String },
+    Aggregate { election_id: u64, ballots: usize, election_proof: G1 },
+}
+
+impl BallotStore {
+    /// This function opens the store, creating the file if it does not exist, and returns its records
+    /// If the batcher stopped in the middle of a write, the last line is incomplete, it is then removed from the file
+    /// Any other line that can not be read is an error, as the store is then corrupted
+    pub(crate) fn open(path: &str) -> Result<(BallotStore, Vec<StoreRecord>), String> {
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)
+            .map_err(|err| format!("Error opening the store {}: {}", path, err))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|err| format!("Error reading the store {}: {}", path, err))?;
+
+        let mut records = Vec::new();
+        let mut valid_length = 0;
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            // Only the last line may be incomplete, as the records are written one by one
+            if !line.ends_with('\n') {
+                eprintln!("{}", format!("The last record of the store {} is incomplete, it will be removed", path).red());
+                break;
+            }
+            let record = StoreRecord::from_json_line(line)
+                .map_err(|err| format!("Error reading the record on line {} of the store {}: {}", i + 1, path, err))?;
+            records.push(record);
+            valid_length += line.len();
+        }
+
+        if valid_length < content.len() {
+            file.set_len(valid_length as u64)
+                .and_then(|_| file.seek(SeekFrom::End(0)).map(|_| ()))
+                .and_then(|_| file.sync_all())
+                .map_err(|err| format!("Error removing the incomplete record of the store {}: {}", path, err))?;
+        }
+
+        Ok((BallotStore { path: path.to_string(), file }, records))
+    }
+
+    /// This function appends the record to the store, it returns only once the record is on the disk
+    pub(crate) fn append(&mut self, record: &StoreRecord) -> Result<(), String> {
+        let line = format!("{}\n", record.to_json());
+        self.file.write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .map_err(|err| format!("Error writing to the store {}: {}", self.path, err))
+    }
+}
+
+impl StoreRecord {
+    fn to_json(&self) -> Value {
+        match self {
+            StoreRecord::Accepted { election_id, ballot, receipt } => json!({
+                "type": "accepted",
+                "electionId": election_id,
+                "ballot": ballot.to_json(),
+                "sequence": receipt.sequence,
+                "ballotHash": format!("{:?}", receipt.ballot_hash),
+            }),
+            StoreRecord::Rejected { election_id, address, reason } => json!({
+                "type": "rejected",
+                "electionId": election_id,
+                "address": address.map(|address| address.solidity_repr()),
+                "reason": reason,
+            }),
+            StoreRecord::Aggregate { election_id, ballots, election_proof } => json!({
+                "type": "aggregate",
+                "electionId": election_id,
+                "ballots": ballots,
+                "electionProof": election_proof.solidity_repr(),
+            }),
+        }
+    }
+
+    fn from_json_line(line: &str) -> Result<StoreRecord, String> {
+        let json: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+        let number = |name: &str| json.get(name).and_then(|value| value.as_u64())
+            .ok_or(format!("Error reading the `{}` field", name));
+        let string = |name: &str| json.get(name).and_then(|value| value.as_str())
+            .ok_or(format!("Error reading the `{}` field", name));
+
+        let election_id = number("electionId")?;
+        match string("type")? {
+            "accepted" => {
+                let ballot = Box::new(Ballot::from_json(json.get("ballot").unwrap_or(&Value::Null))?);
+                let ballot_hash = string("ballotHash")?.parse::<H256>()
+                    .map_err(|err| format!("Error parsing the ballot hash: {}", err))?;
+                Ok(StoreRecord::Accepted {
+                    election_id,
+                    ballot,
+                    receipt: Receipt { sequence: number("sequence")?, ballot_hash },
+                })
+            }
+            "rejected" => {
+                let address = match json.get("address").and_then(|value| value.as_str()) {
+                    Some(address) => Some(Address::from_str_c(address)?),
+                    None => None,
+                };
+                Ok(StoreRecord::Rejected { election_id, address, reason: string("reason")?.to_string() })
+            }
+            "aggregate" => {
+                let election_proof = G1::from_str_c(string("electionProof")?)
+                    .map_err(|err| format!("Error parsing the election proof: {}", err))?;
+                Ok(StoreRecord::Aggregate { election_id, ballots: number("ballots")? as usize, election_proof })
+            }
+            record_type => Err(format!("Unknown record type `{}`", record_type)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use batravot_lib::{ElectionSpecifiers, ScalarField, Vote, voter};
+    use super::*;
+
+    fn ballot(prk: u64) -> Ballot {
+        let prk = ScalarField::from(prk);
+        Ballot {
+            voter_public_key: voter::generate_public_key(&prk),
+            vote: Vote::For,
+            vote_proof: voter::generate_vote_proof(&prk, &ElectionSpecifiers::from(1).forr.0),
+            eth_address: Address::from_low_u64_be(1),
+            signature: None,
+            dleq_proof: None,
+            weight: None,
+        }
+    }
+
+    #[test]
+    fn restores_the_records_and_drops_an_incomplete_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.jsonl").to_string_lossy().to_string();
+
+        let (mut store, records) = BallotStore::open(&path).unwrap();
+        assert!(records.is_empty());
+        let ballot = ballot(3);
+        let receipt = Receipt { sequence: 1, ballot_hash: ballot.hash() };
+        let election_proof = ballot.vote_proof;
+        store.append(&StoreRecord::Accepted { election_id: 1, ballot: Box::new(ballot), receipt }).unwrap();
+        store.append(&StoreRecord::Rejected { election_id: 1, address: None, reason: "Invalid".to_string() }).unwrap();
+        store.append(&StoreRecord::Aggregate { election_id: 1, ballots: 1, election_proof }).unwrap();
+        drop(store);
+
+        // Simulate a crash in the middle of a write
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("{\"type\":\"accep");
+        fs::write(&path, content).unwrap();
+
+        let (mut store, records) = BallotStore::open(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(matches!(&records[0], StoreRecord::Accepted { receipt: restored, .. } if *restored == receipt));
+        assert!(matches!(&records[2], StoreRecord::Aggregate { election_proof: proof, .. } if *proof == election_proof));
+
+        // The next record is appended after the last complete one
+        store.append(&StoreRecord::Rejected { election_id: 1, address: None, reason: "Invalid".to_string() }).unwrap();
+        drop(store);
+        assert_eq!(BallotStore::open(&path).unwrap().1.len(), 4);
+    }
+
+    #[test]
+    fn fails_on_a_corrupted_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store.jsonl");
+        fs::write(&path, "{\"type\":\"rejected\"}\n{}\n").unwrap();
+        assert!(BallotStore::open(&path.to_string_lossy()).is_err());
+    }
+}
@@ -0,0 +1,339 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use colored::Colorize;
+use web3::types::{Address, U256};
+use batravot_lib::{ElectionResult, Vote};
+use batravot_lib::balances::BalanceSnapshot;
+use batravot_lib::representation::SolidityRepresentable;
+
+/// The rules used to decide whether an election has passed
+/// quorum - The percentage of the possible votes that must be cast for the result to be valid
+/// threshold - The percentage of the cast votes that must be `For` to pass, the `For` votes must be strictly above it
+pub struct TallyRules {
+    pub quorum: u32,
+    pub threshold: u32,
+}
+
+impl Default for TallyRules {
+    /// By default, there is no quorum and a simple majority is needed
+    fn default() -> Self {
+        TallyRules { quorum: 0, threshold: 50 }
+    }
+}
+
+/// The outcome of the tally
+/// result - The same result as the contract would compute in `closeElection`
+pub(crate) struct TallyOutcome {
+    pub result: ElectionResult,
+    pub quorum_reached: bool,
+    pub threshold_reached: bool,
+}
+
+impl TallyOutcome {
+    pub(crate) fn passed(&self) -> bool {
+        self.quorum_reached && self.threshold_reached
+    }
+}
+
+/// This function tallies the votes weighted by the token balances in the snapshot
+/// votes - The address of each voter with their vote and declared weight, as they are submitted in a single batch
+/// The vote of a weighted ballot counts with its declared weight, which is backed by its vote proof, the others with the balance of the voter
+/// The declared weight is not backed by the tokens of the voter, so it counts at most as much as the balance
+/// It computes the same values as the `closeElection` function of the contract
+pub(crate) fn tally(votes: impl IntoIterator<Item = (Address, Vote, Option<u64>)>, snapshot: &BalanceSnapshot, rules: &TallyRules) -> TallyOutcome {
+    // Same as `submitVotesWithProof`, all the `Against` votes are recorded before all the `For` votes,
+    // so an address that is in both lists of the batch counts as `For`, whatever the order of its ballots
+    let (for_votes, against_votes): (Vec<_>, Vec<_>) = votes.into_iter()
+        .partition(|(_, vote, _)| *vote == Vote::For);
+    let votes: HashMap<Address, (Vote, Option<u64>)> = against_votes.into_iter()
+        .chain(for_votes)
+        .map(|(address, vote, weight)| (address, (vote, weight)))
+        .collect();
+
+    let mut result = ElectionResult {
+        possible_votes: snapshot.total_supply(),
+        ..ElectionResult::default()
+    };
+    for (address, (vote, weight)) in votes.iter() {
+        let balance = snapshot.balance_of(address);
+        let weight = match weight {
+            Some(weight) => U256::from(*weight).min(balance),
+            None => balance,
+        };
+        result.total_voters += U256::one();
+        result.total_votes += weight;
+        if *vote == Vote::For {
+            result.yes_voters += U256::one();
+            result.yes_votes += weight;
+        }
+    }
+
+    // Both rules are checked in integers, comparing `votes * 100` with `percentage * total`
+    // We use the full multiplication, as the balances can take all the 256 bits
+    let hundred = U256::from(100);
+    let quorum_reached = result.total_votes.full_mul(hundred) >= result.possible_votes.full_mul(U256::from(rules.quorum));
+    let threshold_reached = result.yes_votes.full_mul(hundred) > result.total_votes.full_mul(U256::from(rules.threshold));
+
+    TallyOutcome {
+        result,
+        quorum_reached,
+        threshold_reached,
+    }
+}
+
+/// This function prints the outcome of the tally
+pub(crate) fn print_tally(outcome: &TallyOutcome, rules: &TallyRules) {
+    let result = &outcome.result;
+    let reached = |reached: bool| if reached { "reached".green() } else { "not reached".red() };
+
+    println!("Tally preview based on the balance snapshot:");
+    println!("Possible votes:    {}", result.possible_votes);
+    println!("Total voters:      {}", result.total_voters);
+    println!("Yes voters:        {}", result.yes_voters);
+    println!("Total votes:       {}", result.total_votes);
+    println!("Yes votes:         {}", result.yes_votes);
+    println!("Quorum ({}%):      {}", rules.quorum, reached(outcome.quorum_reached));
+    println!("Threshold ({}%):   {}", rules.threshold, reached(outcome.threshold_reached));
+    println!("Outcome:           {}", if outcome.passed() { "passed".green() } else { "rejected".red() });
+}
+
+/// This function lists the voters that are missing from the balance snapshot
+/// Their votes are counted with a zero weight, which is most likely a mistake in the snapshot
+pub(crate) fn voters_without_balance<'a>(voters: impl IntoIterator<Item = &'a Address>, snapshot: &BalanceSnapshot) -> Vec<String> {
+    voters.into_iter()
+        .filter(|address| !snapshot.has_balance(address))
+        .map(|address| address.solidity_repr())
+        .collect()
+}
+
+/// This function lists the voters whose declared weight is higher than their balance in the snapshot
+/// The weight is backed by the vote proof, but not by the balance, so the voter claims more votes than they hold, their vote counts with their balance
+pub(crate) fn voters_above_balance<'a>(votes: impl IntoIterator<Item = &'a (Address, Vote, Option<u64>)>, snapshot: &BalanceSnapshot) -> Vec<String> {
+    votes.into_iter()
+        .filter(|(address, _, weight)| weight.is_some_and(|weight| U256::from(weight) > snapshot.balance_of(address)))
+        .map(|(address, _, _)| address.solidity_repr())
+        .collect()
+}
+
+/// The votes of a ballot and the counts of a round are fixed-point numbers with five decimals, so that a surplus is transferred as a fraction of the ballots
+pub(crate) const VOTE_SCALE: u64 = 100_000;
+
+/// A round of a single transferable vote count
+/// counts - The votes of each option, `None` for the options out of the count, each ballot counts its votes for its most preferred continuing option
+/// exhausted - The votes of the ballots that rank none of the continuing options
+/// elected - The options elected in the round, several options are elected together once there are no more continuing options than seats left
+/// eliminated - The option eliminated in the round, if none was elected
+pub(crate) struct RunoffRound {
+    pub counts: Vec<Option<u64>>,
+    pub exhausted: u64,
+    pub elected: Vec<usize>,
+    pub eliminated: Option<usize>,
+}
+
+/// The outcome of a single transferable vote tally
+/// quota - The Droop quota, the votes an option needs to be elected
+/// winners - The elected options, in the order of their election
+/// rounds - The rounds of the count
+pub(crate) struct RunoffOutcome {
+    pub quota: u64,
+    pub winners: Vec<usize>,
+    pub rounds: Vec<RunoffRound>,
+}
+
+/// This function tallies ranked ballots with the single transferable vote, with one seat it is instant-runoff voting
+/// ballots - The address of each voter with their ranking, the most preferred option first, if an address voted several times, only the first ballot counts, like in the batch
+/// options - The number of options of the election
+/// seats - The number of options to elect
+/// Each ballot starts with one vote, an option is elected once its votes reach the Droop quota, `ballots / (seats + 1) + 1`.
+/// The surplus of an elected option is transferred with the Gregory method: each of its ballots keeps the fraction `surplus / votes` of its votes
+/// for its next continuing option, so that a majority that ranks the same options first only elects its share of the seats.
+/// In each round, the option with the most votes is elected if it reaches the quota, otherwise the option with the fewest votes is eliminated.
+/// Once there are no more continuing options than seats left, they are all elected. Ties are broken by the index of the option, the lowest one wins and the highest one is eliminated
+pub(crate) fn instant_runoff(ballots: impl IntoIterator<Item = (Address, Vec<usize>)>, options: usize, seats: usize) -> RunoffOutcome {
+    // The first ballot of an address counts, so that each voter counts once
+    let mut voters = HashSet::new();
+    let mut ballots: Vec<(Vec<usize>, u64)> = ballots.into_iter()
+        .filter(|(address, _)| voters.insert(*address))
+        .map(|(_, ranking)| (ranking, VOTE_SCALE))
+        .collect();
+    let seats = seats.min(options);
+    let quota = (ballots.len() as u64 / (seats as u64 + 1) + 1) * VOTE_SCALE;
+
+    let mut continuing = vec![true; options];
+    let mut winners = Vec::new();
+    let mut rounds = Vec::new();
+    while winners.len() < seats {
+        let mut counts = vec![0u64; options];
+        let mut exhausted = 0;
+        for (ranking, votes) in ballots.iter() {
+            match first_continuing(ranking, &continuing) {
+                Some(option) => counts[option] += votes,
+                None => exhausted += votes,
+            }
+        }
+        let round_counts = (0..options).map(|option| continuing[option].then_some(counts[option])).collect();
+        // The continuing options with the most votes first
+        let mut remaining: Vec<usize> = (0..options).filter(|option| continuing[*option]).collect();
+        remaining.sort_by_key(|option| (Reverse(counts[*option]), *option));
+
+        if remaining.len() <= seats - winners.len() {
+            for option in remaining.iter() {
+                continuing[*option] = false;
+            }
+            winners.extend(remaining.iter());
+            rounds.push(RunoffRound { counts: round_counts, exhausted, elected: remaining, eliminated: None });
+        } else if counts[remaining[0]] >= quota {
+            let elected = remaining[0];
+            let votes = counts[elected];
+            for (ranking, ballot_votes) in ballots.iter_mut() {
+                if first_continuing(ranking, &continuing) == Some(elected) {
+                    *ballot_votes = (*ballot_votes as u128 * (votes - quota) as u128 / votes as u128) as u64;
+                }
+            }
+            continuing[elected] = false;
+            winners.push(elected);
+            rounds.push(RunoffRound { counts: round_counts, exhausted, elected: vec![elected], eliminated: None });
+        } else {
+            let last = remaining[remaining.len() - 1];
+            continuing[last] = false;
+            rounds.push(RunoffRound { counts: round_counts, exhausted, elected: Vec::new(), eliminated: Some(last) });
+        }
+    }
+
+    RunoffOutcome { quota, winners, rounds }
+}
+
+/// Returns the most preferred option of a ranking that is still in the count
+fn first_continuing(ranking: &[usize], continuing: &[bool]) -> Option<usize> {
+    ranking.iter().copied().find(|option| continuing.get(*option) == Some(&true))
+}
+
+/// Returns the votes of a count with their five decimals
+fn format_votes(votes: u64) -> String {
+    format!("{}.{:05}", votes / VOTE_SCALE, votes % VOTE_SCALE)
+}
+
+/// This function prints the rounds and the winners of a single transferable vote tally
+pub(crate) fn print_runoff(outcome: &RunoffOutcome) {
+    println!("Single transferable vote tally of the ranked ballots, the quota is {}:", format_votes(outcome.quota));
+    for (round, runoff_round) in outcome.rounds.iter().enumerate() {
+        let counts = runoff_round.counts.iter().enumerate()
+            .filter_map(|(option, count)| count.map(|count| format!("{}: {}", option, format_votes(count))))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let result = match runoff_round.eliminated {
+            Some(option) => format!("option {} is eliminated", option).red(),
+            None => format!("{:?} elected", runoff_round.elected).green(),
+        };
+        println!("  Round {}:  [{}], {} exhausted, {}", round + 1, counts, format_votes(runoff_round.exhausted), result);
+    }
+    println!("Elected:           {:?}", outcome.winners);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(i: u64) -> Address {
+        Address::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn tallies_like_the_contract() {
+        let csv = format!("{},10\n{},20\n{},30\n{},40\n", address(1).solidity_repr(), address(2).solidity_repr(), address(3).solidity_repr(), address(4).solidity_repr());
+        let snapshot = BalanceSnapshot::from_csv(&csv).unwrap();
+
+        // The `For` vote of the first address overrides its `Against` vote
+        let votes = vec![(address(1), Vote::Against, None), (address(2), Vote::Against, None), (address(3), Vote::For, None), (address(1), Vote::For, None)];
+        let outcome = tally(votes, &snapshot, &TallyRules { quorum: 60, threshold: 50 });
+
+        assert_eq!(outcome.result, ElectionResult {
+            possible_votes: U256::from(100),
+            total_voters: U256::from(3),
+            yes_voters: U256::from(2),
+            total_votes: U256::from(60),
+            yes_votes: U256::from(40),
+        });
+        assert!(outcome.quorum_reached);
+        assert!(outcome.threshold_reached);
+        assert!(outcome.passed());
+
+        // The contract records the `For` votes last, so a later `Against` vote does not override a `For` vote of the same batch
+        let votes = vec![(address(1), Vote::For, None), (address(2), Vote::Against, None), (address(3), Vote::For, None), (address(1), Vote::Against, None)];
+        let outcome = tally(votes, &snapshot, &TallyRules { quorum: 60, threshold: 50 });
+        assert_eq!(outcome.result.yes_voters, U256::from(2));
+        assert_eq!(outcome.result.yes_votes, U256::from(40));
+
+        let outcome = tally(vec![(address(1), Vote::For, None), (address(2), Vote::Against, None)], &snapshot, &TallyRules { quorum: 60, threshold: 50 });
+        assert!(!outcome.quorum_reached);
+        assert!(!outcome.threshold_reached);
+        assert!(!outcome.passed());
+    }
+
+    #[test]
+    fn tallies_the_declared_weights() {
+        let csv = format!("{},10\n{},20\n", address(1).solidity_repr(), address(2).solidity_repr());
+        let snapshot = BalanceSnapshot::from_csv(&csv).unwrap();
+
+        // The weighted vote counts with its declared weight, the other one with the balance
+        // The address 3 has no balance, so its declared weight does not count, and the weight of the address 1 is capped by its balance
+        let votes = vec![(address(1), Vote::For, Some(8)), (address(2), Vote::Against, None), (address(3), Vote::For, Some(5))];
+        let outcome = tally(votes.clone(), &snapshot, &TallyRules::default());
+        assert_eq!((outcome.result.total_voters, outcome.result.yes_voters), (U256::from(3), U256::from(2)));
+        assert_eq!(outcome.result.total_votes, U256::from(28));
+        assert_eq!(outcome.result.yes_votes, U256::from(8));
+        assert!(!outcome.threshold_reached);
+        assert_eq!(voters_above_balance(votes.iter(), &snapshot), vec![address(3).solidity_repr()]);
+
+        let votes = vec![(address(1), Vote::For, Some(40)), (address(2), Vote::Against, None)];
+        let outcome = tally(votes.clone(), &snapshot, &TallyRules::default());
+        assert_eq!((outcome.result.total_votes, outcome.result.yes_votes), (U256::from(30), U256::from(10)));
+        assert_eq!(voters_above_balance(votes.iter(), &snapshot), vec![address(1).solidity_repr()]);
+    }
+
+    #[test]
+    fn tallies_the_ranked_ballots_with_instant_runoff() {
+        // The options 1 and 2 have the fewest first preferences, the highest one is eliminated, its ballots then go to the option 1, which wins
+        let mut ballots = Vec::new();
+        for (i, ranking) in [vec![0], vec![0, 2], vec![0], vec![1, 0], vec![1], vec![2, 1], vec![2, 1]].into_iter().enumerate() {
+            ballots.push((address(i as u64), ranking));
+        }
+        let votes = |counts: &[Option<u64>]| counts.iter().map(|count| count.map(|count| count * VOTE_SCALE)).collect::<Vec<Option<u64>>>();
+        let outcome = instant_runoff(ballots.clone(), 3, 1);
+        assert_eq!(outcome.quota, 4 * VOTE_SCALE);
+        assert_eq!(outcome.winners, vec![1]);
+        assert_eq!(outcome.rounds.len(), 2);
+        assert_eq!(outcome.rounds[0].counts, votes(&[Some(3), Some(2), Some(2)]));
+        assert_eq!(outcome.rounds[0].eliminated, Some(2));
+        assert_eq!(outcome.rounds[1].counts, votes(&[Some(3), Some(4), None]));
+        assert_eq!(outcome.rounds[1].elected, vec![1]);
+
+        // The second ballot of the address 0 does not count, the option 0 reaches the quota of 3 without surplus, so its ballots are not transferred
+        ballots.push((address(0), vec![1]));
+        let outcome = instant_runoff(ballots, 3, 2);
+        assert_eq!(outcome.winners, vec![0, 1]);
+        assert_eq!(outcome.rounds[1].counts, votes(&[None, Some(2), Some(2)]));
+        assert_eq!(outcome.rounds[1].eliminated, Some(2));
+    }
+
+    #[test]
+    fn transfers_the_surplus_of_the_elected_options() {
+        // The majority ranks the options 0 and 1 first, but it only has the votes for one seat, so the minority elects the option 2
+        let mut ballots = Vec::new();
+        for (i, ranking) in [vec![0, 1], vec![0, 1], vec![0, 1], vec![0, 1], vec![2], vec![2], vec![2]].into_iter().enumerate() {
+            ballots.push((address(i as u64), ranking));
+        }
+        let outcome = instant_runoff(ballots, 3, 2);
+        assert_eq!(outcome.quota, 3 * VOTE_SCALE);
+        assert_eq!(outcome.winners, vec![0, 2]);
+        assert_eq!(outcome.rounds[0].elected, vec![0]);
+        // Each ballot of the option 0 keeps a quarter of its vote, the surplus of 1 over the quota of 3
+        assert_eq!(outcome.rounds[1].counts, vec![None, Some(VOTE_SCALE), Some(3 * VOTE_SCALE)]);
+        assert_eq!(outcome.rounds[1].elected, vec![2]);
+
+        // Without a quota, the last continuing options fill the seats, even with no votes
+        let outcome = instant_runoff(Vec::new(), 3, 2);
+        assert_eq!(outcome.winners, vec![0, 1]);
+        assert_eq!(outcome.rounds.last().unwrap().elected, vec![0, 1]);
+    }
+}
@@ -0,0 +1,119 @@
+//! Benchmarks of the generation, the aggregation and the verification of the vote proofs
+//! Run them with and without the `parallel` feature to compare the scaling:
+//! `cargo bench -p batravot-lib` and `cargo bench -p batravot-lib --features parallel`
+//! The checks and the generation of 100 000 ballots take minutes, they only run if `BATRAVOT_BENCH_LARGE` is set:
+//! `BATRAVOT_BENCH_LARGE=1 cargo bench -p batravot-lib`
+
+use std::env;
+use ark_std::UniformRand;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use batravot_lib::{batcher, ElectionSpecifiers, ElectionTables, ElectionVerifier, G1, ScalarField, verifier, Vote, voter};
+
+/// The number of ballots to aggregate and verify the election proof of
+const ELECTION_SIZES: [usize; 3] = [1_000, 10_000, 100_000];
+/// The number of ballots to check one by one, each check takes a few pairings
+const BALLOT_CHECK_SIZES: [usize; 2] = [100, 1_000];
+/// The number of ballots to generate, each ballot takes a few scalar multiplications
+const BALLOT_GENERATION_SIZES: [usize; 2] = [100, 1_000];
+/// The number of ballots checked and generated in addition to the sizes above, if `BATRAVOT_BENCH_LARGE` is set
+const LARGE_SIZE: usize = 100_000;
+
+/// Returns the sizes, with the large size if `BATRAVOT_BENCH_LARGE` is set
+fn with_large_size(sizes: &[usize]) -> Vec<usize> {
+    let mut sizes = sizes.to_vec();
+    if env::var_os("BATRAVOT_BENCH_LARGE").is_some() {
+        sizes.push(LARGE_SIZE);
+    }
+    sizes
+}
+
+/// Generates the public keys and the `For` vote proofs of the voters with the private keys `1..=size`
+/// The keys are computed by additions, so that the setup of large elections is fast
+fn votes(size: usize, specifiers: &ElectionSpecifiers) -> (Vec<G1>, Vec<G1>) {
+    let generator = voter::generate_public_key(&ScalarField::from(1u64));
+    let mut public_keys = vec![generator];
+    let mut vote_proofs = vec![specifiers.forr.0];
+    for i in 1..size {
+        public_keys.push(public_keys[i - 1] + generator);
+        vote_proofs.push(vote_proofs[i - 1] + specifiers.forr.0);
+    }
+    (public_keys, vote_proofs)
+}
+
+fn aggregation(c: &mut Criterion) {
+    let specifiers = ElectionSpecifiers::from(1);
+    let mut group = c.benchmark_group("aggregation");
+    group.sample_size(10);
+
+    for size in ELECTION_SIZES {
+        let (public_keys, vote_proofs) = votes(size, &specifiers);
+        let public_keys: Vec<&G1> = public_keys.iter().collect();
+        let vote_proofs: Vec<&G1> = vote_proofs.iter().collect();
+        let election_proof = batcher::generate_batched_election_proof(&vote_proofs);
+
+        group.bench_with_input(BenchmarkId::new("generate_batched_election_proof", size), &vote_proofs, |b, vote_proofs| {
+            b.iter(|| batcher::generate_batched_election_proof(vote_proofs))
+        });
+        group.bench_with_input(BenchmarkId::new("validate_election_proof", size), &public_keys, |b, public_keys| {
+            b.iter(|| assert!(verifier::validate_election_proof(public_keys, &Vec::new(), &election_proof, &specifiers)))
+        });
+    }
+    group.finish();
+}
+
+fn ballot_checks(c: &mut Criterion) {
+    let specifiers = ElectionSpecifiers::from(1);
+    let mut group = c.benchmark_group("ballot_checks");
+    group.sample_size(10);
+
+    for size in with_large_size(&BALLOT_CHECK_SIZES) {
+        let (public_keys, vote_proofs) = votes(size, &specifiers);
+        let ballots: Vec<(&G1, Vote, &G1)> = public_keys.iter().zip(vote_proofs.iter())
+            .map(|(public_key, vote_proof)| (public_key, Vote::For, vote_proof))
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("validate_vote_proofs", size), &ballots, |b, ballots| {
+            b.iter(|| verifier::validate_vote_proofs(ballots, &specifiers))
+        });
+
+        // The same checks one by one, with the specifiers prepared once and with the specifiers prepared for each check
+        let election_verifier = ElectionVerifier::new(&specifiers);
+        group.bench_with_input(BenchmarkId::new("election_verifier", size), &ballots, |b, ballots| {
+            b.iter(|| ballots.iter().all(|(public_key, vote, vote_proof)| election_verifier.validate_vote_proof(public_key, *vote, vote_proof)))
+        });
+        group.bench_with_input(BenchmarkId::new("validate_election_proof", size), &ballots, |b, ballots| {
+            b.iter(|| ballots.iter().all(|(public_key, _, vote_proof)| verifier::validate_election_proof(&vec![*public_key], &Vec::new(), vote_proof, &specifiers)))
+        });
+    }
+    group.finish();
+}
+
+fn ballot_generation(c: &mut Criterion) {
+    let specifiers = ElectionSpecifiers::from(1);
+    let mut group = c.benchmark_group("ballot_generation");
+    group.sample_size(10);
+
+    for size in with_large_size(&BALLOT_GENERATION_SIZES) {
+        let rng = &mut ark_std::test_rng();
+        let private_keys: Vec<ScalarField> = (0..size).map(|_| ScalarField::rand(rng)).collect();
+
+        // The public keys and the vote proofs, with the tables built for each election and without tables
+        group.bench_with_input(BenchmarkId::new("election_tables", size), &private_keys, |b, private_keys| {
+            b.iter(|| {
+                let tables = ElectionTables::new(&specifiers, private_keys.len());
+                private_keys.iter()
+                    .map(|prk| (tables.generate_public_key(prk), tables.generate_vote_proof(prk, Vote::For)))
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("voter", size), &private_keys, |b, private_keys| {
+            b.iter(|| private_keys.iter()
+                .map(|prk| (voter::generate_public_key(prk), voter::generate_vote_proof(prk, &specifiers.forr.0)))
+                .collect::<Vec<_>>())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, aggregation, ballot_checks, ballot_generation);
+criterion_main!(benches);
@@ -0,0 +1,161 @@
+//! The token balances of the voters, they weight the votes when an election is closed
+//! The batcher previews the outcome of an election with them, and the verifier checks the declared weights of a batch against them
+
+use std::collections::HashMap;
+use std::fs;
+use serde_json::Value;
+use web3::types::{Address, U256};
+use crate::representation::FromStrCustom;
+
+/// The token balances of the voters at a given moment
+/// It is used to weight the votes in the same way as the `closeElection` function of the contract does
+/// total_supply - The total supply of the token, used as the amount of possible votes
+pub struct BalanceSnapshot {
+    balances: HashMap<Address, U256>,
+    total_supply: U256,
+}
+
+impl BalanceSnapshot {
+    /// This function reads a balance snapshot from a file
+    /// If the file content starts with `{`, it is parsed as JSON, otherwise it is parsed as CSV
+    pub fn from_file(file_path: &str) -> Result<BalanceSnapshot, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|err| format!("Error reading the balance snapshot: {}", err))?;
+
+        if content.trim_start().starts_with('{') {
+            BalanceSnapshot::from_json(&content)
+        } else {
+            BalanceSnapshot::from_csv(&content)
+        }
+    }
+
+    /// This function parses a balance snapshot from CSV
+    /// Each line is in format `<address>,<balance>`, with the balance as a decimal number
+    /// The first line is skipped if it is a header, e.g. `address,balance`
+    /// The total supply is the sum of all the balances
+    pub fn from_csv(content: &str) -> Result<BalanceSnapshot, String> {
+        let mut balances = HashMap::new();
+
+        for (i, line) in content.lines().enumerate() {
+            // If the line is empty, skip it
+            if line.trim() == "" {
+                continue;
+            }
+
+            let (address_str, balance_str) = line.split_once(',')
+                .ok_or(format!("Error parsing the balance snapshot at line {}: expected `address,balance`", i + 1))?;
+
+            // Skip the header
+            if i == 0 && address_str.trim().eq_ignore_ascii_case("address") {
+                continue;
+            }
+
+            let address = Address::from_str_c(address_str)
+                .map_err(|err| format!("Error parsing the address at line {}: {}", i + 1, err))?;
+            let balance = parse_balance(balance_str)
+                .map_err(|err| format!("Error parsing the balance at line {}: {}", i + 1, err))?;
+
+            balances.insert(address, balance);
+        }
+
+        let total_supply = sum_balances(&balances)?;
+        Ok(BalanceSnapshot { balances, total_supply })
+    }
+
+    /// This function parses a balance snapshot from JSON
+    /// Accepts either an object mapping addresses to balances, or an object in format
+    /// `{"totalSupply": <balance>, "balances": {<address>: <balance>}}`
+    /// Balances can be either numbers or decimal strings
+    /// If the total supply is not provided, it is the sum of all the balances
+    pub fn from_json(content: &str) -> Result<BalanceSnapshot, String> {
+        let json: Value = serde_json::from_str(content)
+            .map_err(|err| format!("Error parsing the balance snapshot: {}", err))?;
+
+        let balances_json = json.get("balances").unwrap_or(&json).as_object()
+            .ok_or("Error parsing the balance snapshot: expected an object of balances")?;
+
+        let mut balances = HashMap::new();
+        for (address_str, balance_json) in balances_json {
+            let address = Address::from_str_c(address_str)
+                .map_err(|err| format!("Error parsing the address {}: {}", address_str, err))?;
+            let balance = parse_json_balance(balance_json)
+                .map_err(|err| format!("Error parsing the balance of {}: {}", address_str, err))?;
+            balances.insert(address, balance);
+        }
+
+        let total_supply = match json.get("totalSupply") {
+            Some(total_supply) => parse_json_balance(total_supply)
+                .map_err(|err| format!("Error parsing the total supply: {}", err))?,
+            None => sum_balances(&balances)?,
+        };
+
+        Ok(BalanceSnapshot { balances, total_supply })
+    }
+
+    /// Returns the balance of the address, zero if it is not in the snapshot
+    pub fn balance_of(&self, address: &Address) -> U256 {
+        self.balances.get(address).cloned().unwrap_or_default()
+    }
+
+    /// Returns whether the address is in the snapshot, even with a zero balance
+    pub fn has_balance(&self, address: &Address) -> bool {
+        self.balances.contains_key(address)
+    }
+
+    /// Returns the total supply of the token, the amount of possible votes
+    pub fn total_supply(&self) -> U256 {
+        self.total_supply
+    }
+}
+
+fn parse_balance(s: &str) -> Result<U256, String> {
+    U256::from_dec_str(s.trim()).map_err(|err| format!("{:?}", err))
+}
+
+fn parse_json_balance(value: &Value) -> Result<U256, String> {
+    match value {
+        Value::String(s) => parse_balance(s),
+        Value::Number(n) => n.as_u64().map(U256::from)
+            .ok_or(format!("Invalid balance {}, large balances must be provided as strings", n)),
+        _ => Err(format!("Invalid balance {}", value)),
+    }
+}
+
+fn sum_balances(balances: &HashMap<Address, U256>) -> Result<U256, String> {
+    balances.values()
+        .try_fold(U256::zero(), |acc, balance| acc.checked_add(*balance))
+        .ok_or("The sum of the balances overflows".to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::representation::SolidityRepresentable;
+    use super::*;
+
+    fn address(i: u64) -> Address {
+        Address::from_low_u64_be(i)
+    }
+
+    #[test]
+    fn parses_csv_snapshot_with_header() {
+        let csv = format!("address,balance\n{},10\n\n{},20\n", address(1).solidity_repr(), address(2).solidity_repr());
+        let snapshot = BalanceSnapshot::from_csv(&csv).unwrap();
+        assert_eq!(snapshot.balance_of(&address(1)), U256::from(10));
+        assert_eq!(snapshot.balance_of(&address(2)), U256::from(20));
+        assert_eq!(snapshot.balance_of(&address(3)), U256::zero());
+        assert_eq!(snapshot.total_supply, U256::from(30));
+    }
+
+    #[test]
+    fn parses_json_snapshot() {
+        let json = format!("{{\"totalSupply\": \"1000\", \"balances\": {{\"{}\": 10, \"{}\": \"20\"}}}}", address(1).solidity_repr(), address(2).solidity_repr());
+        let snapshot = BalanceSnapshot::from_json(&json).unwrap();
+        assert_eq!(snapshot.balance_of(&address(2)), U256::from(20));
+        assert_eq!(snapshot.total_supply, U256::from(1000));
+
+        let json = format!("{{\"{}\": 10}}", address(1).solidity_repr());
+        let snapshot = BalanceSnapshot::from_json(&json).unwrap();
+        assert_eq!(snapshot.total_supply, U256::from(10));
+    }
+}
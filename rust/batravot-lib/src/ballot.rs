@@ -0,0 +1,229 @@
+//! The ballots as the voters give them to the batcher
+//! The batcher identifies each admitted ballot by its hash, and gives the voter a receipt signed by its Ethereum account
+
+use std::str::FromStr;
+use sha3::{Digest, Keccak256};
+use web3::types::{Address, H256};
+
+use crate::{DleqProof, G1, Vote};
+use crate::ethereum::{self, EthSignature, SecretKey};
+use crate::representation::{FromStrCustom, SolidityRepresentable};
+
+/// The version of the JSON, JSON Lines and CSV ballot formats
+/// The voter sets it in the `version` field of the ballots it writes, the batcher only reads the ballots of this version
+pub const BALLOT_FORMAT_VERSION: u64 = 1;
+
+/// This function returns the hash of a ballot, it identifies the ballot in the receipts given to the voters
+/// It is the Keccak256 hash of the ballot in the file format, `<public key>|<vote>|<vote proof>|<address>`
+pub fn ballot_hash(public_key: &G1, vote: Vote, vote_proof: &G1, address: &Address) -> H256 {
+    let vote = match vote {
+        Vote::For => "for",
+        Vote::Against => "against",
+    };
+    let line = format!("{}|{}|{}|{}", public_key.solidity_repr(), vote, vote_proof.solidity_repr(), address.solidity_repr());
+    H256::from_slice(&Keccak256::digest(line.as_bytes()))
+}
+
+/// A ballot as a line of a ballot file, the format the voter writes and the batcher reads
+/// `<public key>|<vote>|<vote proof>|<address>[|<sequence>|<signature>[|<dleq proof>[|<weight>]]]`
+/// public_key - The public key of the voter, in format `x,y`, with both `x` and `y` in hex format with 0x prefix
+/// vote - The vote of the voter, `for` or `against`
+/// vote_proof - The proof of the vote, in the same format as the public key
+/// address - The Ethereum address of the voter
+/// signature - The sequence and the EIP-712 signature of the ballot, if the ballot is signed
+/// dleq_proof - The DLEQ proof of the vote proof, in format `c,s`, if the voter provides it
+/// weight - The declared weight of the vote, if the ballot is weighted
+/// The optional elements can be left empty, e.g. `<public key>|<vote>|<vote proof>|<address>|||<dleq proof>|<weight>` for an unsigned ballot
+/// The points are only parsed, they are checked with the vote proof
+pub struct BallotLine {
+    pub public_key: G1,
+    pub vote: Vote,
+    pub vote_proof: G1,
+    pub address: Address,
+    pub signature: Option<(u64, EthSignature)>,
+    pub dleq_proof: Option<DleqProof>,
+    pub weight: Option<u64>,
+}
+
+impl BallotLine {
+    /// This function parses a ballot line, the whitespace around the elements is ignored
+    /// It returns an error if an element is not valid, or if there are more elements than expected
+    pub fn parse(line: &str) -> Result<BallotLine, String> {
+        let mut iter = line.trim().split('|').map(|element| element.trim());
+
+        // Parse the required elements
+        let public_key = G1::from_str_c(iter.next().ok_or("Error reading the public key")?)
+            .map_err(|err| format!("Error parsing the public key: {}", err))?;
+        let vote = Vote::from_str(iter.next().ok_or("Error reading the vote")?)
+            .map_err(|err| format!("Error parsing the vote: {}", err))?;
+        let vote_proof = G1::from_str_c(iter.next().ok_or("Error reading the vote proof")?)
+            .map_err(|err| format!("Error parsing the vote proof: {}", err))?;
+        let address = Address::from_str_c(iter.next().ok_or("Error reading the address")?)
+            .map_err(|err| format!("Error parsing the address: {}", err))?;
+
+        // The optional elements are missing if they are empty
+        let mut optional = || iter.next().filter(|element| !element.is_empty());
+
+        // Parse the sequence and the signature, if the ballot is signed
+        let sequence = optional()
+            .map(|sequence| sequence.parse::<u64>().map_err(|err| format!("Error parsing the sequence: {}", err)))
+            .transpose()?;
+        let signature = match (sequence, optional()) {
+            (None, None) => None,
+            (Some(sequence), Some(signature)) => Some((sequence, EthSignature::from_str_c(signature)
+                .map_err(|err| format!("Error parsing the signature: {}", err))?)),
+            (Some(_), None) => return Err("The ballot has a sequence but no signature".to_string()),
+            (None, Some(_)) => return Err("The ballot has a signature but no sequence".to_string()),
+        };
+
+        // Parse the DLEQ proof, if the voter provides it
+        let dleq_proof = optional()
+            .map(|dleq_proof| DleqProof::from_str_c(dleq_proof).map_err(|err| format!("Error parsing the DLEQ proof: {}", err)))
+            .transpose()?;
+
+        // Parse the weight, if the ballot is weighted
+        let weight = optional()
+            .map(|weight| weight.parse::<u64>().map_err(|err| format!("Error parsing the weight: {}", err)).and_then(check_weight))
+            .transpose()?;
+
+        // Check that there are no more elements
+        if iter.next().is_some() {
+            return Err("There are more elements than expected".to_string());
+        }
+
+        Ok(BallotLine {
+            public_key,
+            vote,
+            vote_proof,
+            address,
+            signature,
+            dleq_proof,
+            weight,
+        })
+    }
+}
+
+/// This function checks the declared weight of a ballot, a zero weight would make any vote proof valid
+pub fn check_weight(weight: u64) -> Result<u64, String> {
+    match weight {
+        0 => Err("The weight of a weighted ballot must be positive".to_string()),
+        weight => Ok(weight),
+    }
+}
+
+/// This function parses the options of an approval or a ranked ballot, in format `0,2` or `[0,2]`
+/// Whether the options exist is checked with the specifiers of the election, see `ApprovalSpecifiers::check_selection`
+pub fn parse_options(options: &str) -> Result<Vec<usize>, String> {
+    let options = options.trim();
+    let options = options.strip_prefix('[').and_then(|options| options.strip_suffix(']')).unwrap_or(options);
+    options.split(',')
+        .filter(|option| !option.trim().is_empty())
+        .map(|option| option.trim().parse::<usize>().map_err(|err| format!("Invalid option `{}`: {}", option.trim(), err)))
+        .collect()
+}
+
+/// The first line of the message of a receipt, the version is part of it so that a new format gives new signatures
+pub const RECEIPT_MESSAGE: &str = "BatRaVot receipt v1";
+
+/// The receipt the batcher gives for an admitted ballot
+/// The batcher signs it with `personal_sign`, so that the voter can prove that the batcher admitted the ballot
+/// election_id: The election the ballot is for
+/// address: The Ethereum address of the voter
+/// sequence: The number of the ballot in the order of admission, starting from 1
+/// ballot_hash: The hash of the admitted ballot, see `ballot_hash`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BallotReceipt {
+    pub election_id: u64,
+    pub address: Address,
+    pub sequence: u64,
+    pub ballot_hash: H256,
+}
+
+impl BallotReceipt {
+    /// Returns the message that is signed, `BatRaVot receipt v1` followed by one field of the receipt per line
+    pub fn message(&self) -> String {
+        format!("{}\nelection {}\naddress {}\nsequence {}\nballot {:?}",
+            RECEIPT_MESSAGE, self.election_id, self.address.solidity_repr(), self.sequence, self.ballot_hash)
+    }
+
+    /// This function signs the receipt with the key of the Ethereum account of the batcher
+    pub fn sign(&self, secret_key: &SecretKey) -> EthSignature {
+        ethereum::sign_personal_message(secret_key, self.message().as_bytes())
+    }
+
+    /// This function returns the address of the account that signed the receipt
+    pub fn recover_signer(&self, signature: &EthSignature) -> Result<Address, String> {
+        ethereum::recover_personal_signer(self.message().as_bytes(), signature)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{ElectionSpecifiers, ScalarField, voter};
+    use super::*;
+
+    #[test]
+    fn test_ballot_hash_binds_the_ballot() {
+        let prk = ScalarField::from(3u64);
+        let public_key = voter::generate_public_key(&prk);
+        let vote_proof = voter::generate_vote_proof(&prk, &ElectionSpecifiers::from(1).forr.0);
+        let address = Address::from_low_u64_be(1);
+
+        let line = format!("{}|for|{}|{}", public_key.solidity_repr(), vote_proof.solidity_repr(), address.solidity_repr());
+        let hash = ballot_hash(&public_key, Vote::For, &vote_proof, &address);
+        assert_eq!(hash, H256::from_slice(&Keccak256::digest(line.as_bytes())));
+        assert_ne!(hash, ballot_hash(&public_key, Vote::Against, &vote_proof, &address));
+        assert_ne!(hash, ballot_hash(&public_key, Vote::For, &vote_proof, &Address::from_low_u64_be(2)));
+    }
+
+    #[test]
+    fn test_ballot_line() {
+        let prk = ScalarField::from(3u64);
+        let public_key = voter::generate_public_key(&prk).solidity_repr();
+        let vote_proof = voter::generate_vote_proof(&prk, &ElectionSpecifiers::from(1).forr.0).solidity_repr();
+        let address = Address::from_low_u64_be(1).solidity_repr();
+
+        let ballot = BallotLine::parse(&format!(" {} | for | {} | {} ", public_key, vote_proof, address)).unwrap();
+        assert_eq!((ballot.public_key.solidity_repr(), ballot.vote, ballot.address), (public_key.clone(), Vote::For, Address::from_low_u64_be(1)));
+        assert!(ballot.signature.is_none() && ballot.dleq_proof.is_none() && ballot.weight.is_none());
+
+        // The sequence and the signature of a weighted ballot that is not signed are empty
+        let ballot = BallotLine::parse(&format!("{}|against|{}|{}||||40", public_key, vote_proof, address)).unwrap();
+        assert_eq!((ballot.vote, ballot.weight, ballot.signature.is_none()), (Vote::Against, Some(40), true));
+
+        for line in [
+            format!("{}|for|{}", public_key, vote_proof),
+            format!("{}|maybe|{}|{}", public_key, vote_proof, address),
+            format!("{}|for|{}|{}|7", public_key, vote_proof, address),
+            format!("{}|for|{}|{}||0x01", public_key, vote_proof, address),
+            format!("{}|for|{}|{}||||0", public_key, vote_proof, address),
+            format!("{}|for|{}|{}||||40|", public_key, vote_proof, address),
+        ] {
+            assert!(BallotLine::parse(&line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_receipt_signature() {
+        let secret_key = ethereum::parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let receipt = BallotReceipt { election_id: 7, address: Address::from_low_u64_be(1), sequence: 2, ballot_hash: H256::from_low_u64_be(5) };
+        assert_eq!(receipt.message(), "BatRaVot receipt v1\nelection 7\naddress 0x0000000000000000000000000000000000000001\nsequence 2\n\
+            ballot 0x0000000000000000000000000000000000000000000000000000000000000005");
+
+        let signature = receipt.sign(&secret_key);
+        assert_eq!(receipt.recover_signer(&signature).unwrap(), ethereum::address(&secret_key));
+        // The signature is only valid for the same receipt
+        for other in [BallotReceipt { sequence: 3, ..receipt }, BallotReceipt { ballot_hash: H256::from_low_u64_be(6), ..receipt }] {
+            assert_ne!(other.recover_signer(&signature).unwrap(), ethereum::address(&secret_key));
+        }
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(parse_options("[0, 2]"), Ok(vec![0, 2]));
+        assert_eq!(parse_options(" 1,"), Ok(vec![1]));
+        assert_eq!(parse_options("[]"), Ok(vec![]));
+        assert!(parse_options("0,a").is_err_and(|err| err.starts_with("Invalid option `a`")));
+    }
+}
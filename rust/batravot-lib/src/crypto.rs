@@ -4,6 +4,7 @@ pub mod voter {
     use ark_ec::ProjectiveCurve;
     use ark_ff::PrimeField;
     use crate::el_curve::{G1, ScalarField};
+    use crate::election_specifiers::{ApprovalSpecifiers, RankedSpecifiers};
 
     /// This function generates a vote proof from a correct specifier and a private key
     /// specifier: The correct specifier for the vote
@@ -13,6 +14,39 @@ pub mod voter {
         (&specifier).mul(prk)
     }
 
+    /// This function generates the vote proof of a weighted vote, which is the vote proof scaled by the weight
+    /// It is checked against the public key scaled by the same weight, so a proof does not hold with another weight alone
+    /// As anyone can scale the proof and the weight together, the ballot must be signed to bind the weight to the voter
+    /// prk: The private key of the voter
+    /// specifier: The correct specifier for the vote
+    /// weight: The declared weight of the vote, it must not be zero
+    /// return: The weighted vote proof
+    pub fn generate_weighted_vote_proof(prk: &ScalarField, specifier: &G1, weight: u64) -> G1 {
+        generate_vote_proof(&(*prk * ScalarField::from(weight)), specifier)
+    }
+
+    /// This function generates the vote proof of an approval ballot, which approves a subset of the options
+    /// The vote proof is the sum of the specifiers of the approved options, raised to the private key
+    /// prk: The private key of the voter
+    /// specifiers: The specifiers of the options of the election
+    /// selection: The approved options, checked with `ApprovalSpecifiers::check_selection`
+    /// return: The vote proof, or an error if the selection is not valid
+    pub fn generate_approval_vote_proof(prk: &ScalarField, specifiers: &ApprovalSpecifiers, selection: &[usize]) -> Result<G1, String> {
+        let selection = specifiers.check_selection(selection)?;
+        Ok(generate_vote_proof(prk, &specifiers.selection_specifier(&selection)))
+    }
+
+    /// This function generates the vote proof of a ranked ballot, which ranks the options in the order of preference
+    /// The vote proof is the sum of the specifiers of the ranked options at their rank, raised to the private key
+    /// prk: The private key of the voter
+    /// specifiers: The specifiers of the ranked election
+    /// ranking: The ranked options, the most preferred first, checked with `RankedSpecifiers::check_ranking`
+    /// return: The vote proof, or an error if the ranking is not valid
+    pub fn generate_ranked_vote_proof(prk: &ScalarField, specifiers: &RankedSpecifiers, ranking: &[usize]) -> Result<G1, String> {
+        specifiers.check_ranking(ranking)?;
+        Ok(generate_vote_proof(prk, &specifiers.ranking_specifier(ranking)))
+    }
+
     /// This function based on the voters private key generates a public key
     /// prk: The private key of the voter
     /// return: The public key of the voter
@@ -30,18 +64,21 @@ pub mod voter {
 
 /// Batcher is the person who batches the votes and generates the election proof
 pub mod batcher {
-    use ark_ff::Zero;
-    use crate::el_curve::G1;
+    use crate::el_curve::{G1, sum_points};
 
     /// This function generates a proof for vote aggregation
     /// vote_proofs: The proofs of the votes to aggregate
     pub fn generate_batched_election_proof(vote_proofs: &Vec<&G1>) -> G1 {
 
         // The proof is the sum of all the vote proofs
-        let proof = vote_proofs.iter()
-            .fold(G1::zero(), |acc, x| acc + *x);
+        sum_points(vote_proofs)
+    }
 
-        proof
+    /// This function aggregates the proofs of several elections into a single proof, so that one submission covers them all
+    /// As each election proof is a sum of vote proofs, the aggregate is the sum of the vote proofs of all the elections
+    /// election_proofs: The proofs of the elections to aggregate, as generated by `generate_batched_election_proof`
+    pub fn generate_multi_election_proof(election_proofs: &Vec<&G1>) -> G1 {
+        sum_points(election_proofs)
     }
 
     #[cfg(test)]
@@ -63,6 +100,14 @@ pub mod batcher {
             assert_eq!(proof, proof1 + proof2 + proof3);
         }
 
+        #[test]
+        fn multi_election_proof_is_the_sum_of_the_vote_proofs() {
+            let proofs: Vec<G1> = (1..=4u64).map(|i| G1::prime_subgroup_generator().mul(ScalarField::from(i).0)).collect();
+            let first = generate_batched_election_proof(&vec![&proofs[0], &proofs[1]]);
+            let second = generate_batched_election_proof(&vec![&proofs[2], &proofs[3]]);
+            assert_eq!(generate_multi_election_proof(&vec![&first, &second]), generate_batched_election_proof(&proofs.iter().collect()));
+        }
+
         #[test]
         fn election_proof_of_one_proof_equals_to_that_proof() {
             let proof = G1::prime_subgroup_generator();
@@ -76,10 +121,16 @@ pub mod batcher {
 /// Verifier is the person who verifies the election proof to check if the election is valid
 pub mod verifier {
     use std::ops::Neg;
+    use ark_ec::msm::VariableBaseMSM;
     use ark_ec::{PairingEngine, ProjectiveCurve};
-    use ark_ff::{One, Zero};
-    use crate::el_curve::{Curve, G1, G2};
+    use ark_ff::{One, PrimeField, Zero};
+    use ark_std::rand::Rng;
+    use crate::el_curve::{Curve, G1, G2, ScalarField, sum_points};
+    use crate::crypto::dleq::DleqProof;
+    use crate::crypto::election_verifier::ElectionVerifier;
     use crate::election_specifiers;
+    use crate::election_specifiers::{ApprovalSpecifiers, RankedSpecifiers};
+    use crate::types::Vote;
 
     /// This function verifies votes proof
     /// It requires operations on G2, which are not supported inside the current EVM
@@ -87,25 +138,35 @@ pub mod verifier {
     /// no_vote_keys: The public keys of the voters who voted no
     /// proof: The proof that the votes are valid
     /// specifiers: The election specifiers
+    /// To verify several proofs of the same election, create an `ElectionVerifier` once instead
     pub fn validate_election_proof(for_vote_keys: &Vec<&G1>, against_vote_keys: &Vec<&G1>, proof: &G1, specifiers: &election_specifiers::ElectionSpecifiers) -> bool {
-        // Calculate the sum of all the vote proofs for those who voted yes and no
-        let for_voter_sum = for_vote_keys.iter()
-            .fold(G1::zero(), |acc, x| acc + *x);
+        // If the election proof is valid, the pairing of the valid votes and the generator is equal to the product of the pairings of the sums of keys with the specifiers of their vote
+        // The three pairings are computed with a single Miller loop and a single final exponentiation
+        ElectionVerifier::new(specifiers).validate_election_proof(for_vote_keys, against_vote_keys, proof)
+    }
 
-        let against_voter_sum = against_vote_keys.iter()
-            .fold(G1::zero(), |acc, x| acc + *x);
+    /// This function verifies the proof of an election with weighted votes, see `voter::generate_weighted_vote_proof`
+    /// The proof is checked against the sums of the public keys scaled by their weights, but it does not prove the weights:
+    /// anyone can scale a weighted vote proof and its weight together, so the weights must be bound by the signatures of the ballots, or bounded by the balances
+    /// A vote that is not weighted has the weight 1
+    /// for_votes: The public keys of the voters who voted for, with their weights
+    /// against_votes: The public keys of the voters who voted against, with their weights
+    /// proof: The proof that the votes are valid
+    /// specifiers: The election specifiers
+    pub fn validate_weighted_election_proof(for_votes: &[(&G1, u64)], against_votes: &[(&G1, u64)], proof: &G1, specifiers: &election_specifiers::ElectionSpecifiers) -> bool {
+        ElectionVerifier::new(specifiers).validate_key_sums(&weighted_key_sum(for_votes), &weighted_key_sum(against_votes), proof)
+    }
 
-        // Compute the proof pairing
-        let lhs = Curve::pairing(proof.clone(), G2::prime_subgroup_generator());
-        // Compute the product of pairing of the sum of keys that voted yes and the yes specifier
-        // And the pairing of the sum of keys that voted no and the no specifier
-        let rhs =
-            Curve::pairing(for_voter_sum, specifiers.forr.1.clone())
-                *
-                (Curve::pairing(against_voter_sum, specifiers.against.1.clone()));
+    /// This function returns the sum of the public keys scaled by their weights, with a single multi-scalar multiplication
+    pub fn weighted_key_sum(votes: &[(&G1, u64)]) -> G1 {
+        let keys: Vec<G1> = votes.iter().map(|(public_key, _)| **public_key).collect();
+        let weights: Vec<_> = votes.iter().map(|(_, weight)| ScalarField::from(*weight).into_repr()).collect();
+        VariableBaseMSM::multi_scalar_mul(&G1::batch_normalization_into_affine(&keys), &weights)
+    }
 
-        // If the election proof is valid, the pairing of the valid votes and the generator should be equal to the product of the pairings of the sum of keys that voted yes and the yes specifier
-        lhs == rhs
+    /// This function returns the public key scaled by the weight, the key a weighted vote proof is checked against
+    pub fn weighted_public_key(public_key: &G1, weight: u64) -> G1 {
+        public_key.mul(ScalarField::from(weight).into_repr())
     }
 
     /// This function verifies votes proof
@@ -116,11 +177,8 @@ pub mod verifier {
     /// specifiers: The election specifiers
     pub fn validate_election_proof_evm(for_vote_keys: &Vec<G1>, against_vote_keys: &Vec<G1>, proof: &G1, specifiers: &election_specifiers::ElectionSpecifiers) -> bool {
         // Calculate the sum of all the vote proofs for those who voted yes and no
-        let for_key_sum = for_vote_keys.iter()
-            .fold(G1::zero(), |acc, x| acc + x);
-
-        let against_key_sum = against_vote_keys.iter()
-            .fold(G1::zero(), |acc, x| acc + x);
+        let for_key_sum = sum_points(for_vote_keys);
+        let against_key_sum = sum_points(against_vote_keys);
 
         // We will branch based on if there were voters or not for particular type
         // We will calculate the product of correct pairings
@@ -138,8 +196,366 @@ pub mod verifier {
         // If the product is one, everything went as planned
         product.is_one()
     }
-}
 
+    /// This function verifies many batches of the same election with a single pairing product, e.g. for an auditor re-checking all the submitted batches
+    /// Each batch is weighted with a random 128 bit scalar, so that the combined equation only holds if the equation of each batch holds, except with negligible probability
+    /// The combination of all the batches needs 3 pairings, whatever the number of batches
+    /// If the combined check fails, the batches are split in halves which are checked the same way, until the failing batches are found
+    /// batches: The public keys of the voters who voted for, those who voted against, and the election proof of each batch
+    /// specifiers: The election specifiers
+    /// rng: A random number generator for the weights, it must not be predictable by the batcher
+    /// return: Whether each batch is valid, in the order of the batches
+    pub fn validate_election_proofs(batches: &[(&[G1], &[G1], &G1)], specifiers: &election_specifiers::ElectionSpecifiers, rng: &mut impl Rng) -> Vec<bool> {
+        // The key sums are computed once, the bisection only combines them
+        let sums: Vec<(G1, G1, G1)> = batches.iter()
+            .map(|(for_keys, against_keys, proof)| (for_keys.iter().sum(), against_keys.iter().sum(), **proof))
+            .collect();
+
+        let mut valid = vec![false; batches.len()];
+        bisect_election_proofs(&sums, &mut valid, &ElectionVerifier::new(specifiers), rng);
+        valid
+    }
+
+    /// This function marks the batches as valid if their combined check passes, otherwise it checks both halves
+    /// A single batch that fails is left as invalid
+    fn bisect_election_proofs(sums: &[(G1, G1, G1)], valid: &mut [bool], verifier: &ElectionVerifier, rng: &mut impl Rng) {
+        if sums.is_empty() {
+            return;
+        }
+        if combined_election_proof_check(sums, verifier, rng) {
+            valid.fill(true);
+            return;
+        }
+        if sums.len() == 1 {
+            return;
+        }
+
+        let middle = sums.len() / 2;
+        let (valid_left, valid_right) = valid.split_at_mut(middle);
+        bisect_election_proofs(&sums[..middle], valid_left, verifier, rng);
+        bisect_election_proofs(&sums[middle..], valid_right, verifier, rng);
+    }
+
+    /// This function checks the random linear combination of the batches:
+    /// e(sum r_i * proof_i, -g2) * e(sum r_i * for_sum_i, for specifier) * e(sum r_i * against_sum_i, against specifier) == 1
+    fn combined_election_proof_check(sums: &[(G1, G1, G1)], verifier: &ElectionVerifier, rng: &mut impl Rng) -> bool {
+        let mut proof = G1::zero();
+        let mut for_sum = G1::zero();
+        let mut against_sum = G1::zero();
+        for (batch_for_sum, batch_against_sum, batch_proof) in sums {
+            // The weight must not be zero, otherwise the batch would not be checked at all
+            let weight = ScalarField::from(rng.gen::<u128>() | 1).into_repr();
+            proof += batch_proof.mul(weight);
+            for_sum += batch_for_sum.mul(weight);
+            against_sum += batch_against_sum.mul(weight);
+        }
+
+        verifier.validate_key_sums(&for_sum, &against_sum, &proof)
+    }
+
+    /// This function verifies the aggregated proof of several elections with a single pairing product
+    /// For k elections, the product has 2k+1 pairings: the proof with the negated generator, and the key sums of each election with its specifiers
+    /// It requires operations on G2, which are not supported inside the current EVM
+    /// elections: The sum of the public keys of the voters who voted for, the sum of those who voted against, and the specifiers of each election
+    /// proof: The aggregated proof of the elections, as generated by `generate_multi_election_proof`
+    pub fn validate_multi_election_proof(elections: &[(G1, G1, &election_specifiers::ElectionSpecifiers)], proof: &G1) -> bool {
+        let mut pairs: Vec<(<Curve as PairingEngine>::G1Prepared, <Curve as PairingEngine>::G2Prepared)> = Vec::with_capacity(2 * elections.len() + 1);
+        pairs.push((proof.into_affine().into(), G2::prime_subgroup_generator().neg().into_affine().into()));
+        for (for_key_sum, against_key_sum, specifiers) in elections {
+            pairs.push((for_key_sum.into_affine().into(), specifiers.forr.1.into_affine().into()));
+            pairs.push((against_key_sum.into_affine().into(), specifiers.against.1.into_affine().into()));
+        }
+
+        // The Miller loops of all the pairings are computed together, with a single final exponentiation
+        Curve::product_of_pairings(&pairs).is_one()
+    }
+
+    /// This function verifies the vote proofs of single votes, e.g. the ballots received by the batcher
+    /// As the proof of an election with one vote is the proof of that vote, each vote is checked as an election
+    /// With the `parallel` feature, the votes are checked on all the threads
+    /// votes: The public key of the voter, their vote and the vote proof
+    /// specifiers: The election specifiers
+    /// return: Whether each vote proof is valid, in the order of the votes
+    pub fn validate_vote_proofs(votes: &[(&G1, Vote, &G1)], specifiers: &election_specifiers::ElectionSpecifiers) -> Vec<bool> {
+        // The specifiers are prepared once for all the votes
+        let verifier = ElectionVerifier::new(specifiers);
+        let validate = |(public_key, vote, proof): &(&G1, Vote, &G1)| verifier.validate_vote_proof(public_key, *vote, proof);
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            votes.par_iter().map(validate).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            votes.iter().map(validate).collect()
+        }
+    }
+
+    /// This function verifies the proof of an approval election, see `voter::generate_approval_vote_proof`
+    /// As a vote proof is the sum of the vote proofs of each approved option, the proof is checked against the key sums of each option:
+    /// e(proof, -g2) * e(key_sum_1, specifier_1) * ... * e(key_sum_n, specifier_n) == 1
+    /// For n options, the product has n+1 pairings, whatever the number of voters
+    /// option_keys: The public keys of the voters who approved each option, in the order of the options
+    /// proof: The proof that the votes are valid
+    /// specifiers: The specifiers of the options of the election
+    pub fn validate_approval_election_proof(option_keys: &[Vec<&G1>], proof: &G1, specifiers: &ApprovalSpecifiers) -> bool {
+        if option_keys.len() != specifiers.options.len() {
+            return false;
+        }
+        validate_specifier_key_sums(option_keys.iter().zip(specifiers.options.iter().map(|(_, specifier)| specifier)), proof)
+    }
+
+    /// This function verifies the vote proofs of single approval ballots, e.g. the ballots received by the batcher
+    /// Each vote proof is checked against the sum of the specifiers of its approved options, with two pairings
+    /// With the `parallel` feature, the votes are checked on all the threads
+    /// votes: The public key of the voter, the approved options and the vote proof
+    /// specifiers: The specifiers of the options of the election
+    /// return: Whether each vote proof is valid, in the order of the votes, a selection that is not valid is not
+    pub fn validate_approval_vote_proofs(votes: &[(&G1, &[usize], &G1)], specifiers: &ApprovalSpecifiers) -> Vec<bool> {
+        validate_summed_vote_proofs(votes, |selection| specifiers.check_selection(selection).ok()
+            .map(|selection| specifiers.selection_specifier_g2(&selection)))
+    }
+
+    /// This function verifies the proof of a ranked election, see `voter::generate_ranked_vote_proof`
+    /// The proof is checked against the key sums of each option at each rank, so for n options, the product has n*n+1 pairings
+    /// rank_keys: The public keys of the voters who ranked each option at each rank, `rank_keys[rank][option]`
+    /// proof: The proof that the votes are valid
+    /// specifiers: The specifiers of the ranked election
+    pub fn validate_ranked_election_proof(rank_keys: &[Vec<Vec<&G1>>], proof: &G1, specifiers: &RankedSpecifiers) -> bool {
+        if rank_keys.len() != specifiers.ranks.len() || rank_keys.iter().any(|option_keys| option_keys.len() != specifiers.ranks.len()) {
+            return false;
+        }
+        let keys_and_specifiers = rank_keys.iter().zip(specifiers.ranks.iter())
+            .flat_map(|(option_keys, rank)| option_keys.iter().zip(rank.iter().map(|(_, specifier)| specifier)));
+        validate_specifier_key_sums(keys_and_specifiers, proof)
+    }
+
+    /// This function verifies the vote proofs of single ranked ballots, e.g. the ballots received by the batcher
+    /// Each vote proof is checked against the sum of the specifiers of its ranked options at their rank, with two pairings
+    /// votes: The public key of the voter, the ranked options, the most preferred first, and the vote proof
+    /// specifiers: The specifiers of the ranked election
+    /// return: Whether each vote proof is valid, in the order of the votes, a ranking that is not valid is not
+    pub fn validate_ranked_vote_proofs(votes: &[(&G1, &[usize], &G1)], specifiers: &RankedSpecifiers) -> Vec<bool> {
+        validate_summed_vote_proofs(votes, |ranking| specifiers.check_ranking(ranking).ok()
+            .map(|_| specifiers.ranking_specifier_g2(ranking)))
+    }
+
+    /// This function checks a proof against the key sums of several specifiers, with one pairing product
+    /// e(proof, -g2) * e(key_sum_1, specifier_1) * ... * e(key_sum_n, specifier_n) == 1
+    fn validate_specifier_key_sums<'a>(keys_and_specifiers: impl Iterator<Item = (&'a Vec<&'a G1>, &'a G2)>, proof: &G1) -> bool {
+        let mut pairs: Vec<(<Curve as PairingEngine>::G1Prepared, <Curve as PairingEngine>::G2Prepared)> =
+            vec![(proof.into_affine().into(), G2::prime_subgroup_generator().neg().into_affine().into())];
+        for (keys, specifier) in keys_and_specifiers {
+            pairs.push((sum_points(keys).into_affine().into(), specifier.into_affine().into()));
+        }
+        Curve::product_of_pairings(&pairs).is_one()
+    }
+
+    /// This function checks the vote proofs of single ballots whose specifier is a sum of specifiers, e.g. of the approved options
+    /// With the `parallel` feature, the votes are checked on all the threads
+    /// specifier: Returns the G2 specifier of the options of a ballot, or `None` if they are not valid
+    fn validate_summed_vote_proofs(votes: &[(&G1, &[usize], &G1)], specifier: impl Fn(&[usize]) -> Option<G2> + Sync) -> Vec<bool> {
+        let generator: <Curve as PairingEngine>::G2Prepared = G2::prime_subgroup_generator().neg().into_affine().into();
+        let validate = |(public_key, options, proof): &(&G1, &[usize], &G1)| match specifier(options) {
+            Some(specifier) => Curve::product_of_pairings(&[
+                (proof.into_affine().into(), generator.clone()),
+                (public_key.into_affine().into(), specifier.into_affine().into()),
+            ]).is_one(),
+            None => false,
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            votes.par_iter().map(validate).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            votes.iter().map(validate).collect()
+        }
+    }
+
+    /// This function verifies the vote proofs of single votes with their DLEQ proofs, e.g. the ballots received by the batcher
+    /// The DLEQ proof shows that the vote proof is the specifier of the vote raised to the private key of the public key,
+    /// which only needs G1 arithmetic, so it is cheaper than the pairing check of `validate_vote_proofs`
+    /// With the `parallel` feature, the votes are checked on all the threads
+    /// votes: The public key of the voter, their vote, the vote proof and its DLEQ proof
+    /// specifiers: The election specifiers
+    /// return: Whether each vote proof is valid, in the order of the votes
+    pub fn validate_vote_dleq_proofs(votes: &[(&G1, Vote, &G1, &DleqProof)], specifiers: &election_specifiers::ElectionSpecifiers) -> Vec<bool> {
+        let validate = |(public_key, vote, proof, dleq_proof): &(&G1, Vote, &G1, &DleqProof)| match vote {
+            Vote::For => dleq_proof.verify(public_key, &specifiers.forr.0, proof),
+            Vote::Against => dleq_proof.verify(public_key, &specifiers.against.0, proof),
+        };
+
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            votes.par_iter().map(validate).collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        {
+            votes.iter().map(validate).collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::{ScalarField, voter};
+        use super::*;
+
+        #[test]
+        fn validates_each_vote_proof() {
+            let specifiers = election_specifiers::ElectionSpecifiers::from(3);
+            let prk = ScalarField::from(11u64);
+            let public_key = voter::generate_public_key(&prk);
+            let for_proof = voter::generate_vote_proof(&prk, &specifiers.forr.0);
+            let against_proof = voter::generate_vote_proof(&prk, &specifiers.against.0);
+
+            let votes = vec![
+                (&public_key, Vote::For, &for_proof),
+                (&public_key, Vote::Against, &for_proof),
+                (&public_key, Vote::Against, &against_proof),
+            ];
+            assert_eq!(validate_vote_proofs(&votes, &specifiers), vec![true, false, true]);
+        }
+
+        #[test]
+        fn validates_the_weighted_key_sums() {
+            let specifiers = election_specifiers::ElectionSpecifiers::from(8);
+            let keys: Vec<ScalarField> = [11u64, 12, 14].into_iter().map(ScalarField::from).collect();
+            let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+            let proof = voter::generate_weighted_vote_proof(&keys[0], &specifiers.forr.0, 30)
+                + voter::generate_vote_proof(&keys[1], &specifiers.forr.0)
+                + voter::generate_weighted_vote_proof(&keys[2], &specifiers.against.0, 5);
+
+            let for_votes = [(&public_keys[0], 30), (&public_keys[1], 1)];
+            let against_votes = [(&public_keys[2], 5)];
+            assert!(validate_weighted_election_proof(&for_votes, &against_votes, &proof, &specifiers));
+            assert!(ElectionVerifier::new(&specifiers).validate_vote_proof(&weighted_public_key(&public_keys[2], 5), Vote::Against,
+                &voter::generate_weighted_vote_proof(&keys[2], &specifiers.against.0, 5)));
+
+            // A declared weight other than the one of the proof is not valid
+            assert!(!validate_weighted_election_proof(&[(&public_keys[0], 31), (&public_keys[1], 1)], &against_votes, &proof, &specifiers));
+            assert!(!validate_weighted_election_proof(&for_votes, &[(&public_keys[2], 1)], &proof, &specifiers));
+            assert_eq!(weighted_key_sum(&[]), G1::zero());
+        }
+
+        #[test]
+        fn validates_the_approval_proofs() {
+            let specifiers = ApprovalSpecifiers::from(4, 3).unwrap();
+            let keys: Vec<ScalarField> = [11u64, 12, 14].into_iter().map(ScalarField::from).collect();
+            let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+            let selections: [&[usize]; 3] = [&[0, 2], &[1], &[2]];
+            let proofs: Vec<G1> = keys.iter().zip(selections.iter())
+                .map(|(key, selection)| voter::generate_approval_vote_proof(key, &specifiers, selection).unwrap())
+                .collect();
+            assert!(voter::generate_approval_vote_proof(&keys[0], &specifiers, &[3]).is_err());
+
+            let votes: Vec<(&G1, &[usize], &G1)> = (0..3).map(|i| (&public_keys[i], selections[i], &proofs[i])).collect();
+            assert_eq!(validate_approval_vote_proofs(&votes, &specifiers), vec![true; 3]);
+            let swapped = [(&public_keys[0], selections[1], &proofs[0]), (&public_keys[1], &[1, 1][..], &proofs[1])];
+            assert_eq!(validate_approval_vote_proofs(&swapped, &specifiers), vec![false, false]);
+
+            // The option 2 is approved by the voters 0 and 2
+            let proof = sum_points(&proofs);
+            let option_keys = vec![vec![&public_keys[0]], vec![&public_keys[1]], vec![&public_keys[0], &public_keys[2]]];
+            assert!(validate_approval_election_proof(&option_keys, &proof, &specifiers));
+            let moved = vec![vec![&public_keys[0]], vec![&public_keys[1], &public_keys[2]], vec![&public_keys[0]]];
+            assert!(!validate_approval_election_proof(&moved, &proof, &specifiers));
+            assert!(!validate_approval_election_proof(&option_keys[..2], &proof, &specifiers));
+        }
+
+        #[test]
+        fn validates_the_ranked_proofs() {
+            let specifiers = RankedSpecifiers::from(4, 3).unwrap();
+            let keys: Vec<ScalarField> = [11u64, 12].into_iter().map(ScalarField::from).collect();
+            let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+            let rankings: [&[usize]; 2] = [&[2, 0, 1], &[0]];
+            let proofs: Vec<G1> = keys.iter().zip(rankings.iter())
+                .map(|(key, ranking)| voter::generate_ranked_vote_proof(key, &specifiers, ranking).unwrap())
+                .collect();
+
+            let votes: Vec<(&G1, &[usize], &G1)> = (0..2).map(|i| (&public_keys[i], rankings[i], &proofs[i])).collect();
+            assert_eq!(validate_ranked_vote_proofs(&votes, &specifiers), vec![true; 2]);
+            // The same options in another order
+            assert_eq!(validate_ranked_vote_proofs(&[(&public_keys[0], &[0, 2, 1][..], &proofs[0])], &specifiers), vec![false]);
 
+            let proof = sum_points(&proofs);
+            let mut rank_keys: Vec<Vec<Vec<&G1>>> = vec![vec![Vec::new(); 3]; 3];
+            for (voter, ranking) in rankings.iter().enumerate() {
+                for (rank, option) in ranking.iter().enumerate() {
+                    rank_keys[rank][*option].push(&public_keys[voter]);
+                }
+            }
+            assert!(validate_ranked_election_proof(&rank_keys, &proof, &specifiers));
+            rank_keys[0].swap(0, 2);
+            assert!(!validate_ranked_election_proof(&rank_keys, &proof, &specifiers));
+        }
+
+        #[test]
+        fn finds_the_invalid_batches_of_a_combined_check() {
+            let rng = &mut ark_std::test_rng();
+            let specifiers = election_specifiers::ElectionSpecifiers::from(5);
+            let keys: Vec<ScalarField> = (1..=10u64).map(ScalarField::from).collect();
+            let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+
+            // Each batch has a voter for and a voter against
+            let mut proofs: Vec<G1> = keys.chunks(2)
+                .map(|pair| voter::generate_vote_proof(&pair[0], &specifiers.forr.0) + voter::generate_vote_proof(&pair[1], &specifiers.against.0))
+                .collect();
+            let batches = |proofs: &[G1]| -> Vec<bool> {
+                let batches: Vec<(&[G1], &[G1], &G1)> = public_keys.chunks(2).zip(proofs.iter())
+                    .map(|(pair, proof)| (&pair[..1], &pair[1..], proof))
+                    .collect();
+                validate_election_proofs(&batches, &specifiers, &mut ark_std::test_rng())
+            };
+            assert_eq!(batches(&proofs), vec![true; 5]);
+
+            // The invalid batches are pinpointed, even if their errors cancel out in an unweighted sum
+            let shift = voter::generate_vote_proof(&keys[0], &specifiers.forr.0);
+            proofs[1] += shift;
+            proofs[4] -= shift;
+            assert_eq!(batches(&proofs), vec![true, false, true, true, false]);
+            assert_eq!(validate_election_proofs(&[], &specifiers, rng), Vec::<bool>::new());
+        }
+
+        #[test]
+        fn validates_the_proof_of_several_elections() {
+            let keys: Vec<ScalarField> = (1..=4u64).map(ScalarField::from).collect();
+            let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+            let first = election_specifiers::ElectionSpecifiers::from(1);
+            let second = election_specifiers::ElectionSpecifiers::from(2);
+
+            // In the first election the voters 0 and 1 vote for and against, in the second one the voters 2 and 3 vote for
+            let proofs = [
+                voter::generate_vote_proof(&keys[0], &first.forr.0),
+                voter::generate_vote_proof(&keys[1], &first.against.0),
+                voter::generate_vote_proof(&keys[2], &second.forr.0),
+                voter::generate_vote_proof(&keys[3], &second.forr.0),
+            ];
+            let proof = sum_points(&proofs);
+            let elections = [
+                (public_keys[0], public_keys[1], &first),
+                (public_keys[2] + public_keys[3], G1::zero(), &second),
+            ];
+            assert!(validate_multi_election_proof(&elections, &proof));
+
+            // The vote of a voter can not be moved to the other election, nor can the proof miss a vote
+            let moved = [
+                (public_keys[0], public_keys[1], &first),
+                (public_keys[2], G1::zero(), &second),
+                (public_keys[3], G1::zero(), &first),
+            ];
+            assert!(!validate_multi_election_proof(&moved, &proof));
+            assert!(!validate_multi_election_proof(&elections, &(proof - proofs[3])));
+        }
+    }
+}
 
-pub(crate) mod schnorr;
\ No newline at end of file
+pub(crate) mod schnorr;
+pub(crate) mod dleq;
+pub(crate) mod shamir;
+pub(crate) mod election_verifier;
+pub(crate) mod fixed_base;
\ No newline at end of file
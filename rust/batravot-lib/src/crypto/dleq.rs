@@ -0,0 +1,108 @@
+use ark_ec::ProjectiveCurve;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::rand::Rng;
+use ark_std::UniformRand;
+use sha3::{Keccak256, Digest};
+use crate::el_curve::{G1, ScalarField};
+
+/// The domain of the challenge hash, so that a DLEQ proof can not be mistaken for another proof
+const DLEQ_DOMAIN: &[u8] = b"BatRaVot DLEQ v1";
+
+/// Represents a [Chaum-Pedersen](https://link.springer.com/chapter/10.1007/3-540-48071-4_7) proof of discrete logarithm equality
+/// We use it to prove that the vote proof is the vote specifier raised to the private key of the public key,
+/// so that a ballot can be checked with G1 arithmetic only, instead of a pairing product
+/// c: The challenge, computed with the Fiat-Shamir heuristic
+/// s: The response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DleqProof {
+    pub c: ScalarField,
+    pub s: ScalarField,
+}
+
+impl DleqProof {
+    /// Function to create a proof that `log_g(public key) = log_specifier(vote proof)`
+    /// We use Fiat-Shamir Heuristic to make the protocol non interactive
+    /// prk: The private key of the voter
+    /// specifier: The G1 specifier of the vote
+    /// rng: A random number generator
+    /// Returns a DLEQ proof
+    pub fn generate_proof(prk: &ScalarField, specifier: &G1, rng: &mut impl Rng) -> Self {
+        // Commit to a random scalar on both bases
+        let r = ScalarField::rand(rng);
+        let a = G1::prime_subgroup_generator().mul(r.into_repr());
+        let b = specifier.mul(r.into_repr());
+
+        let public_key = G1::prime_subgroup_generator().mul(prk.into_repr());
+        let vote_proof = specifier.mul(prk.into_repr());
+        let c = DleqProof::challenge(specifier, &public_key, &vote_proof, &a, &b);
+
+        DleqProof { c, s: r + c * prk }
+    }
+
+    /// Function to verify a DLEQ proof
+    /// The public key and the vote proof must be points of the curve, otherwise the equations could hold on another curve
+    /// public_key: The public key of the voter
+    /// specifier: The G1 specifier of the vote
+    /// vote_proof: The vote proof of the ballot
+    /// Returns true if the vote proof is the specifier raised to the private key of the public key, false otherwise
+    pub fn verify(&self, public_key: &G1, specifier: &G1, vote_proof: &G1) -> bool {
+        if !public_key.into_affine().is_on_curve() || !vote_proof.into_affine().is_on_curve() {
+            return false;
+        }
+
+        // Recompute the commitments as g^s / y^c and h^s / z^c
+        let a = G1::prime_subgroup_generator().mul(self.s.into_repr()) - public_key.mul(self.c.into_repr());
+        let b = specifier.mul(self.s.into_repr()) - vote_proof.mul(self.c.into_repr());
+
+        // The challenge only matches if the commitments are the ones of the prover
+        self.c == DleqProof::challenge(specifier, public_key, vote_proof, &a, &b)
+    }
+
+    /// Function to hash the statement and the commitments into a scalar
+    /// Uses Keccak256 over the domain and the affine coordinates of the points, in Big Endian
+    fn challenge(specifier: &G1, public_key: &G1, vote_proof: &G1, a: &G1, b: &G1) -> ScalarField {
+        let mut hasher = Keccak256::new();
+        hasher.update(DLEQ_DOMAIN);
+        for point in [&G1::prime_subgroup_generator(), specifier, public_key, vote_proof, a, b] {
+            let affine = point.into_affine();
+            hasher.update(affine.x.into_repr().to_bytes_be());
+            hasher.update(affine.y.into_repr().to_bytes_be());
+        }
+        ScalarField::from_be_bytes_mod_order(&hasher.finalize())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{ElectionSpecifiers, Vote, verifier, voter};
+    use crate::representation::{FromStrCustom, SolidityRepresentable};
+    use super::*;
+
+    #[test]
+    fn test_dleq_proof_matches_the_pairing_check() {
+        let rng = &mut ark_std::test_rng();
+        let specifiers = ElectionSpecifiers::from(3);
+        let prk = ScalarField::rand(rng);
+        let public_key = voter::generate_public_key(&prk);
+        let vote_proof = voter::generate_vote_proof(&prk, &specifiers.forr.0);
+
+        let proof = DleqProof::generate_proof(&prk, &specifiers.forr.0, rng);
+        assert!(proof.verify(&public_key, &specifiers.forr.0, &vote_proof));
+        assert!(verifier::validate_vote_proofs(&[(&public_key, Vote::For, &vote_proof)], &specifiers)[0]);
+
+        // The proof does not hold for the other vote, another key or another vote proof
+        assert!(!proof.verify(&public_key, &specifiers.against.0, &vote_proof));
+        let other_key = voter::generate_public_key(&ScalarField::rand(rng));
+        assert!(!proof.verify(&other_key, &specifiers.forr.0, &vote_proof));
+        let against_proof = voter::generate_vote_proof(&prk, &specifiers.against.0);
+        assert!(!proof.verify(&public_key, &specifiers.forr.0, &against_proof));
+    }
+
+    #[test]
+    fn test_dleq_proof_representation() {
+        let rng = &mut ark_std::test_rng();
+        let proof = DleqProof::generate_proof(&ScalarField::rand(rng), &ElectionSpecifiers::from(3).against.0, rng);
+        assert_eq!(DleqProof::from_str_c(&proof.solidity_repr()).unwrap(), proof);
+    }
+}
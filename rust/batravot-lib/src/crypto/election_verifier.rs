@@ -0,0 +1,100 @@
+use std::ops::Neg;
+use ark_ec::{PairingEngine, ProjectiveCurve};
+use ark_ff::One;
+use crate::el_curve::{Curve, G1, G2, sum_points};
+use crate::election_specifiers::ElectionSpecifiers;
+use crate::types::Vote;
+
+type G1Prepared = <Curve as PairingEngine>::G1Prepared;
+type G2Prepared = <Curve as PairingEngine>::G2Prepared;
+
+/// Represents the verifier of the proofs of a single election
+/// The line coefficients of the G2 points of the pairings only depend on the election, so they are computed once, when the verifier is created
+/// Each check is then a single Miller loop over the three pairings, followed by a single final exponentiation
+/// generator: The negated generator of G2, prepared
+/// forr: The G2 specifier of the `For` vote, prepared
+/// against: The G2 specifier of the `Against` vote, prepared
+#[derive(Debug, Clone)]
+pub struct ElectionVerifier {
+    generator: G2Prepared,
+    forr: G2Prepared,
+    against: G2Prepared,
+}
+
+impl ElectionVerifier {
+    /// Function to create the verifier of an election from its specifiers
+    /// specifiers: The election specifiers
+    pub fn new(specifiers: &ElectionSpecifiers) -> Self {
+        ElectionVerifier {
+            generator: G2::prime_subgroup_generator().neg().into_affine().into(),
+            forr: specifiers.forr.1.into_affine().into(),
+            against: specifiers.against.1.into_affine().into(),
+        }
+    }
+
+    /// Function to verify an election proof, same as `verifier::validate_election_proof`
+    /// for_vote_keys: The public keys of the voters who voted for
+    /// against_vote_keys: The public keys of the voters who voted against
+    /// proof: The proof that the votes are valid
+    pub fn validate_election_proof(&self, for_vote_keys: &[&G1], against_vote_keys: &[&G1], proof: &G1) -> bool {
+        self.validate_key_sums(&sum_points(for_vote_keys), &sum_points(against_vote_keys), proof)
+    }
+
+    /// Function to verify an election proof from the sums of the public keys of the voters
+    /// The proof is valid if e(proof, -g2) * e(for_key_sum, for specifier) * e(against_key_sum, against specifier) == 1
+    /// for_key_sum: The sum of the public keys of the voters who voted for
+    /// against_key_sum: The sum of the public keys of the voters who voted against
+    /// proof: The proof that the votes are valid
+    pub fn validate_key_sums(&self, for_key_sum: &G1, against_key_sum: &G1, proof: &G1) -> bool {
+        // The Miller loop takes the pairs by reference, so the prepared points are copied, which is far cheaper than preparing them again
+        let pairs: [(G1Prepared, G2Prepared); 3] = [
+            (proof.into_affine().into(), self.generator.clone()),
+            (for_key_sum.into_affine().into(), self.forr.clone()),
+            (against_key_sum.into_affine().into(), self.against.clone()),
+        ];
+        Curve::final_exponentiation(&Curve::miller_loop(&pairs))
+            .is_some_and(|product| product.is_one())
+    }
+
+    /// Function to verify the proof of a single vote, as the proof of an election with one vote
+    /// public_key: The public key of the voter
+    /// vote: The vote of the voter
+    /// proof: The vote proof
+    pub fn validate_vote_proof(&self, public_key: &G1, vote: Vote, proof: &G1) -> bool {
+        match vote {
+            Vote::For => self.validate_key_sums(public_key, &G1::default(), proof),
+            Vote::Against => self.validate_key_sums(&G1::default(), public_key, proof),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{verifier, voter};
+    use crate::el_curve::ScalarField;
+    use super::*;
+
+    #[test]
+    fn test_election_verifier_matches_the_verifier() {
+        let specifiers = ElectionSpecifiers::from(4);
+        let election_verifier = ElectionVerifier::new(&specifiers);
+        let keys: Vec<ScalarField> = [11u64, 12, 14].into_iter().map(ScalarField::from).collect();
+        let public_keys: Vec<G1> = keys.iter().map(voter::generate_public_key).collect();
+        let proof = voter::generate_vote_proof(&keys[0], &specifiers.forr.0)
+            + voter::generate_vote_proof(&keys[1], &specifiers.forr.0)
+            + voter::generate_vote_proof(&keys[2], &specifiers.against.0);
+
+        let for_keys = vec![&public_keys[0], &public_keys[1]];
+        let against_keys = vec![&public_keys[2]];
+        assert!(election_verifier.validate_election_proof(&for_keys, &against_keys, &proof));
+        assert!(verifier::validate_election_proof_evm(&public_keys[..2].to_vec(), &public_keys[2..].to_vec(), &proof, &specifiers));
+
+        // The votes are swapped, or the proof is of another election
+        assert!(!election_verifier.validate_election_proof(&against_keys, &for_keys, &proof));
+        assert!(!ElectionVerifier::new(&ElectionSpecifiers::from(5)).validate_election_proof(&for_keys, &against_keys, &proof));
+
+        assert!(election_verifier.validate_vote_proof(&public_keys[2], Vote::Against, &voter::generate_vote_proof(&keys[2], &specifiers.against.0)));
+        assert!(!election_verifier.validate_vote_proof(&public_keys[2], Vote::For, &voter::generate_vote_proof(&keys[2], &specifiers.against.0)));
+    }
+}
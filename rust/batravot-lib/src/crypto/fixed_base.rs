@@ -0,0 +1,117 @@
+use ark_ec::msm::FixedBaseMSM;
+use ark_ec::ProjectiveCurve;
+use ark_ff::{FpParameters, PrimeField};
+use ark_std::rand::Rng;
+use ark_std::UniformRand;
+use crate::crypto::schnorr::SchnorrKnowledgeProof;
+use crate::el_curve::{G1, ScalarField};
+use crate::election_specifiers::ElectionSpecifiers;
+use crate::types::Vote;
+
+/// The number of bits of the scalars, the number of rows of a table depends on it
+const SCALAR_BITS: usize = <ScalarField as PrimeField>::Params::MODULUS_BITS as usize;
+
+/// Represents a fixed-base window table of a G1 point
+/// The table holds the multiples of the point for each window of the scalar bits, so that a multiplication is only
+/// one mixed addition per window, instead of a double and add for each bit
+/// window: The number of bits of a window
+/// table: The multiples `j * 2^(window * i) * base` for each window `i` and each `j < 2^window`
+#[derive(Debug, Clone)]
+pub struct FixedBaseTable {
+    window: usize,
+    table: Vec<Vec<<G1 as ProjectiveCurve>::Affine>>,
+}
+
+impl FixedBaseTable {
+    /// Function to precompute the table of a point
+    /// The window grows with the number of multiplications, so that the cost of the table is shared by them
+    /// base: The point to multiply
+    /// multiplications: The expected number of multiplications of the point
+    pub fn new(base: &G1, multiplications: usize) -> Self {
+        let window = FixedBaseMSM::get_mul_window_size(multiplications);
+        FixedBaseTable {
+            window,
+            table: FixedBaseMSM::get_window_table(SCALAR_BITS, window, *base),
+        }
+    }
+
+    /// Function to multiply the point of the table by a scalar
+    pub fn mul(&self, scalar: &ScalarField) -> G1 {
+        let windows = SCALAR_BITS.div_ceil(self.window);
+        FixedBaseMSM::windowed_mul(windows, self.window, &self.table, scalar)
+    }
+}
+
+/// Represents the fixed-base tables of an election, to generate many ballots of it, e.g. to simulate a large election
+/// The tables are built from the generator and the G1 specifiers of the election
+/// specifiers: The specifiers of the election
+/// generator: The table of the generator of G1, for the public keys and the Schnorr proofs
+/// forr: The table of the G1 specifier of the `For` vote
+/// against: The table of the G1 specifier of the `Against` vote
+#[derive(Clone)]
+pub struct ElectionTables {
+    specifiers: ElectionSpecifiers,
+    generator: FixedBaseTable,
+    forr: FixedBaseTable,
+    against: FixedBaseTable,
+}
+
+impl ElectionTables {
+    /// Function to precompute the tables of an election
+    /// specifiers: The specifiers of the election
+    /// voters: The expected number of voters, a ballot needs one multiplication of the specifier and two of the generator
+    pub fn new(specifiers: &ElectionSpecifiers, voters: usize) -> Self {
+        ElectionTables {
+            specifiers: specifiers.clone(),
+            generator: FixedBaseTable::new(&G1::prime_subgroup_generator(), 2 * voters),
+            forr: FixedBaseTable::new(&specifiers.forr.0, voters),
+            against: FixedBaseTable::new(&specifiers.against.0, voters),
+        }
+    }
+
+    /// Returns the specifiers the tables were built from
+    pub fn specifiers(&self) -> &ElectionSpecifiers {
+        &self.specifiers
+    }
+
+    /// Function to generate a public key, same as `voter::generate_public_key`
+    pub fn generate_public_key(&self, prk: &ScalarField) -> G1 {
+        self.generator.mul(prk)
+    }
+
+    /// Function to generate the vote proof of a vote, same as `voter::generate_vote_proof` with the specifier of the vote
+    pub fn generate_vote_proof(&self, prk: &ScalarField, vote: Vote) -> G1 {
+        match vote {
+            Vote::For => self.forr.mul(prk),
+            Vote::Against => self.against.mul(prk),
+        }
+    }
+
+    /// Function to generate a Schnorr Knowledge Proof of the private key, same as `SchnorrKnowledgeProof::generate_key_proof`
+    pub fn generate_key_proof(&self, prk: &ScalarField, rng: &mut impl Rng) -> SchnorrKnowledgeProof {
+        let r = ScalarField::rand(rng);
+        SchnorrKnowledgeProof::from_commitment(prk, &r, self.generator.mul(&r), &self.generator.mul(prk))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::voter;
+    use super::*;
+
+    #[test]
+    fn test_tables_match_the_scalar_multiplication() {
+        let rng = &mut ark_std::test_rng();
+        let specifiers = ElectionSpecifiers::from(6);
+        let tables = ElectionTables::new(&specifiers, 100);
+
+        for prk in [ScalarField::from(0u64), ScalarField::from(1u64), -ScalarField::from(1u64), ScalarField::rand(rng)] {
+            let public_key = tables.generate_public_key(&prk);
+            assert_eq!(public_key, voter::generate_public_key(&prk));
+            assert_eq!(tables.generate_vote_proof(&prk, Vote::For), voter::generate_vote_proof(&prk, &specifiers.forr.0));
+            assert_eq!(tables.generate_vote_proof(&prk, Vote::Against), voter::generate_vote_proof(&prk, &specifiers.against.0));
+            assert!(tables.generate_key_proof(&prk, rng).verify(&public_key));
+        }
+    }
+}
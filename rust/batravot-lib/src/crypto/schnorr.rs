@@ -31,12 +31,21 @@ impl SchnorrKnowledgeProof {
         // Generate a public key
         let y = G1::prime_subgroup_generator().mul(prk.into_repr());
 
+        SchnorrKnowledgeProof::from_commitment(prk, &r, t, &y)
+    }
+
+    /// Function to complete a Schnorr Knowledge Proof from its commitment, so that the points can be computed with precomputed tables
+    /// prk: The private key of the voter
+    /// r: The random scalar of the commitment
+    /// t: The commitment, g^r
+    /// y: The public key of the voter, g^prk
+    pub(crate) fn from_commitment(prk: &ScalarField, r: &ScalarField, t: G1, y: &G1) -> Self {
         // Compute challenge using Fiat-Shamir Heuristic
         // We concatenate the coordinates of both points t and public key
-        let c : ScalarField = SchnorrKnowledgeProof::hash_points_into_scalar_field(vec![&t, &y]);
+        let c : ScalarField = SchnorrKnowledgeProof::hash_points_into_scalar_field(vec![&t, y]);
 
         // Computer proof
-        let s = r + c * prk;
+        let s = *r + c * prk;
 
         SchnorrKnowledgeProof {
             t,
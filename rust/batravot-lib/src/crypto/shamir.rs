@@ -0,0 +1,192 @@
+use ark_ff::{BigInteger, Field, One, PrimeField, Zero};
+use ark_std::rand::Rng;
+use ark_std::UniformRand;
+use sha3::{Digest, Keccak256};
+use zeroize::Zeroizing;
+use crate::crypto::voter;
+use crate::el_curve::ScalarField;
+use crate::representation::{FromStrCustom, SolidityRepresentable};
+
+/// The version of the encoding of the shares
+const SHARE_VERSION: u8 = 1;
+/// The length of an encoded share: version, threshold, index, key id, value and checksum
+const SHARE_LENGTH: usize = 1 + 1 + 1 + 4 + 32 + 4;
+
+/// Represents a share of an election private key, split with [Shamir's Secret Sharing](https://en.wikipedia.org/wiki/Shamir%27s_secret_sharing)
+/// Any `threshold` shares of the key reconstruct it, fewer shares reveal nothing about it
+/// threshold: The number of shares needed to reconstruct the key
+/// index: The point at which the polynomial is evaluated, from 1 to 255
+/// key_id: The first 4 bytes of the hash of the public key, so that the shares of different keys are not mixed
+/// value: The value of the polynomial at the index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Share {
+    pub threshold: u8,
+    pub index: u8,
+    pub key_id: [u8; 4],
+    pub value: ScalarField,
+}
+
+impl Share {
+    /// This function splits the private key into `shares` shares, any `threshold` of which reconstruct the key
+    /// The key is the constant term of a random polynomial of degree `threshold - 1`, the shares are its values at 1..=shares
+    /// prk: The private key of the voter
+    /// threshold: The number of shares needed to reconstruct the key
+    /// shares: The number of shares, at most 255
+    /// rng: A random number generator
+    pub fn split(prk: &ScalarField, threshold: u8, shares: u8, rng: &mut impl Rng) -> Result<Vec<Share>, String> {
+        if threshold == 0 || threshold > shares {
+            return Err(format!("The threshold must be between 1 and the number of shares, got {} of {}", threshold, shares));
+        }
+
+        let mut coefficients = Zeroizing::new(vec![*prk]);
+        for _ in 1..threshold {
+            coefficients.push(ScalarField::rand(rng));
+        }
+
+        let key_id = key_id(prk);
+        Ok((1..=shares).map(|index| {
+            // Horner's rule, from the highest coefficient down
+            let x = ScalarField::from(index as u64);
+            let value = coefficients.iter().rev().fold(ScalarField::zero(), |acc, coefficient| acc * x + coefficient);
+            Share { threshold, index, key_id, value }
+        }).collect())
+    }
+
+    /// This function reconstructs the private key from the shares with Lagrange interpolation at zero
+    /// The shares must be of the same key, and there must be at least `threshold` shares with distinct indexes
+    /// The reconstructed key is checked against the key id, so that a wrong share is detected
+    pub fn combine(shares: &[Share]) -> Result<ScalarField, String> {
+        let first = shares.first().ok_or("No shares provided")?;
+        if shares.iter().any(|share| share.key_id != first.key_id || share.threshold != first.threshold) {
+            return Err("The shares are not shares of the same key".to_string());
+        }
+        for (i, share) in shares.iter().enumerate() {
+            if shares[..i].iter().any(|other| other.index == share.index) {
+                return Err(format!("The share #{} is provided twice", share.index));
+            }
+        }
+        if shares.len() < first.threshold as usize {
+            return Err(format!("{} shares are needed to reconstruct the key, only {} provided", first.threshold, shares.len()));
+        }
+
+        let shares = &shares[..first.threshold as usize];
+        let mut prk = ScalarField::zero();
+        for share in shares {
+            let x_i = ScalarField::from(share.index as u64);
+            // The Lagrange basis polynomial of the share at zero: prod(x_j / (x_j - x_i))
+            let mut numerator = ScalarField::one();
+            let mut denominator = ScalarField::one();
+            for other in shares.iter().filter(|other| other.index != share.index) {
+                let x_j = ScalarField::from(other.index as u64);
+                numerator *= x_j;
+                denominator *= x_j - x_i;
+            }
+            let inverse = denominator.inverse().ok_or("Failed to invert the Lagrange denominator")?;
+            prk += share.value * numerator * inverse;
+        }
+
+        if key_id(&prk) != first.key_id {
+            return Err("The reconstructed key does not match the key of the shares, one of the shares is wrong".to_string());
+        }
+        Ok(prk)
+    }
+}
+
+/// Returns the first 4 bytes of the hash of the public key of the private key
+fn key_id(prk: &ScalarField) -> [u8; 4] {
+    let public_key = voter::generate_public_key(prk);
+    let hash = Keccak256::digest(public_key.solidity_repr().as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Returns the first 4 bytes of the hash of the data, to detect a mistyped share
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Keccak256::digest(data);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// The share is written in hex format, with a checksum at the end
+impl SolidityRepresentable for Share {
+    fn solidity_repr(&self) -> String {
+        let mut bytes = vec![SHARE_VERSION, self.threshold, self.index];
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(&self.value.into_repr().to_bytes_be());
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        format!("0x{}", hex::encode(bytes))
+    }
+}
+
+impl FromStrCustom for Share {
+    type Err = String;
+
+    fn from_str_c(s: &str) -> Result<Self, Self::Err> where Self: Sized {
+        let bytes = hex::decode(s.trim().trim_start_matches("0x")).map_err(|e| e.to_string())?;
+        if bytes.len() != SHARE_LENGTH {
+            return Err(format!("Invalid share length: expected {}, got {}", SHARE_LENGTH, bytes.len()));
+        }
+        let (data, checksum_bytes) = bytes.split_at(SHARE_LENGTH - 4);
+        if checksum(data) != checksum_bytes {
+            return Err("Invalid share checksum, the share is mistyped".to_string());
+        }
+        if data[0] != SHARE_VERSION {
+            return Err(format!("The share version {} is not supported", data[0]));
+        }
+        if data[1] == 0 || data[2] == 0 {
+            return Err("Invalid share, the threshold and the index must not be zero".to_string());
+        }
+
+        let value = ScalarField::from_be_bytes_mod_order(&data[7..]);
+        if value.into_repr().to_bytes_be() != data[7..] {
+            return Err("Invalid share value, it is not in the scalar field".to_string());
+        }
+        Ok(Share {
+            threshold: data[1],
+            index: data[2],
+            key_id: [data[3], data[4], data[5], data[6]],
+            value,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_any_threshold_shares_reconstruct_the_key() {
+        let rng = &mut ark_std::test_rng();
+        let prk = ScalarField::rand(rng);
+        let shares = Share::split(&prk, 3, 5, rng).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for (a, b, c) in [(0, 1, 2), (4, 2, 0), (1, 3, 4)] {
+            let prk_combined = Share::combine(&[shares[a], shares[b], shares[c]]).unwrap();
+            assert_eq!(voter::generate_public_key(&prk_combined), voter::generate_public_key(&prk));
+        }
+        assert!(Share::combine(&shares[..2]).is_err());
+        assert!(Share::combine(&[shares[0], shares[0], shares[1]]).is_err());
+
+        // A share of another key is detected
+        let other = Share::split(&ScalarField::rand(rng), 3, 5, rng).unwrap();
+        assert!(Share::combine(&[shares[0], shares[1], other[2]]).is_err());
+        let mut wrong = shares[2];
+        wrong.value += ScalarField::one();
+        assert!(Share::combine(&[shares[0], shares[1], wrong]).is_err());
+    }
+
+    #[test]
+    fn test_share_representation() {
+        let rng = &mut ark_std::test_rng();
+        let shares = Share::split(&ScalarField::rand(rng), 2, 3, rng).unwrap();
+
+        let encoded = shares[1].solidity_repr();
+        assert_eq!(Share::from_str_c(&encoded).unwrap(), shares[1]);
+
+        // A single mistyped digit is caught by the checksum
+        let mut mistyped = encoded.into_bytes();
+        mistyped[20] = if mistyped[20] == b'0' { b'1' } else { b'0' };
+        assert!(Share::from_str_c(&String::from_utf8(mistyped).unwrap()).is_err());
+    }
+}
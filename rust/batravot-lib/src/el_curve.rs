@@ -1,3 +1,4 @@
+use std::borrow::Borrow;
 use ark_ff::{One, PrimeField, Zero};
 
 
@@ -43,4 +44,20 @@ pub(crate) fn convert_to_point(x: &[u8]) -> (G1, G2) {
         scalar += ScalarField::one();
     }
 
-}
\ No newline at end of file
+}
+
+/// The function sums the points of the curve
+/// With the `parallel` feature, the points are split between the threads and the partial sums are added together
+pub(crate) fn sum_points<P: Borrow<G1> + Sync>(points: &[P]) -> G1 {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        points.par_iter()
+            .fold(G1::zero, |acc, point| acc + point.borrow())
+            .reduce(G1::zero, |acc, sum| acc + sum)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        points.iter().fold(G1::zero(), |acc, point| acc + point.borrow())
+    }
+}
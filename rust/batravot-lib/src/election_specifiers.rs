@@ -9,7 +9,7 @@ use crate::el_curve::{convert_to_point, G1, G2};
 ///! It contains the logic to create the election specifiers as well as the logic to check the election specifiers
 
 /// The election specifiers are used to generate the ballots for a given election
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct ElectionSpecifiers {
     pub against: (G1, G2),
     pub forr: (G1, G2),
@@ -65,3 +65,247 @@ impl ElectionSpecifiers {
         self == &recreated_specifiers
     }
 }
+
+
+/// The largest number of options of an approval or a ranked election
+/// A ranked election has a specifier for each option at each rank, so their number grows with the square of the number of options
+pub const MAX_OPTIONS: usize = 64;
+
+/// The specifiers of an approval election, in which a ballot approves a subset of the options
+/// The vote proof of a ballot combines the specifiers of all the options it approves
+/// options: The specifiers of each option, in the order of the options
+#[derive(Clone, PartialEq)]
+pub struct ApprovalSpecifiers {
+    pub options: Vec<(G1, G2)>,
+}
+
+impl ApprovalSpecifiers {
+    /// The function creates the specifiers of the options of an approval election from the election id
+    /// We hash the election id with a 2 and the index of the option, so that they differ from the specifiers of a `For` or `Against` vote
+    /// election_id: The id of the election
+    /// options: The number of options of the election
+    /// Returns an error if there are no options or more than `MAX_OPTIONS`
+    pub fn new(election_id: BigInteger256, options: usize) -> Result<ApprovalSpecifiers, String> {
+        check_option_count(options)?;
+        let options = (0..options)
+            .map(|option| Self::option_specifier(&election_id, option))
+            .collect::<Result<_, String>>()?;
+
+        Ok(ApprovalSpecifiers { options })
+    }
+
+    pub fn from(election_id: u64, options: usize) -> Result<ApprovalSpecifiers, String> {
+        Self::new(BigInteger256::from(election_id), options)
+    }
+
+    /// This function creates the specifier of a single option, the election id is hashed with a 2 and the index of the option
+    fn option_specifier(election_id: &BigInteger256, option: usize) -> Result<(G1, G2), String> {
+        let mut unhashed_specifier = election_id.to_bytes_le();
+        unhashed_specifier.push(2u8);
+        unhashed_specifier.extend_from_slice(&option_index(option)?.to_be_bytes());
+        Ok(ElectionSpecifiers::hash_into_ec_points(&unhashed_specifier))
+    }
+
+    /// This function checks a selection of options, it must not be empty and each option must exist and be selected only once
+    /// Returns the selected options in increasing order
+    pub fn check_selection(&self, selection: &[usize]) -> Result<Vec<usize>, String> {
+        check_options(selection, Some(self.options.len()), "selection", "approve")?;
+        let mut sorted = selection.to_vec();
+        sorted.sort_unstable();
+        Ok(sorted)
+    }
+
+    /// Returns the sum of the G1 specifiers of the selected options, without creating the specifiers of the other options
+    /// The number of options of the election is not known, so the options are only checked against `MAX_OPTIONS`
+    /// This is what a voter needs to make the vote proof of a selection, see `voter::generate_vote_proof`
+    pub fn selection_specifier_of(election_id: u64, selection: &[usize]) -> Result<G1, String> {
+        check_options(selection, None, "selection", "approve")?;
+        let election_id = BigInteger256::from(election_id);
+        selection.iter()
+            .map(|option| Self::option_specifier(&election_id, *option).map(|specifier| specifier.0))
+            .sum()
+    }
+
+    /// Returns the sum of the G1 specifiers of the selected options, which the vote proof of the selection is made with
+    /// The options of the selection must exist, see `check_selection`
+    pub fn selection_specifier(&self, selection: &[usize]) -> G1 {
+        selection.iter().map(|option| self.options[*option].0).sum()
+    }
+
+    /// Returns the sum of the G2 specifiers of the selected options, which the vote proof of the selection is checked with
+    pub fn selection_specifier_g2(&self, selection: &[usize]) -> G2 {
+        selection.iter().map(|option| self.options[*option].1).sum()
+    }
+
+    /// This function checks that the provided specifiers have been generated from the provided election id
+    pub fn check_specifiers(&self, election_id: BigInteger256) -> bool {
+        ApprovalSpecifiers::new(election_id, self.options.len()).is_ok_and(|specifiers| self == &specifiers)
+    }
+}
+
+/// The specifiers of a ranked election, in which a ballot ranks the options in the order of preference
+/// Each option has a specifier for each rank, so the vote proof of a ballot combines the specifier of each of its options at its rank
+/// ranks: The specifiers of each option at each rank, `ranks[rank][option]`, the first rank is the most preferred
+#[derive(Clone, PartialEq)]
+pub struct RankedSpecifiers {
+    pub ranks: Vec<Vec<(G1, G2)>>,
+}
+
+impl RankedSpecifiers {
+    /// The function creates the specifiers of a ranked election from the election id
+    /// We hash the election id with a 3, the rank and the index of the option, so that they differ from the specifiers of the other elections
+    /// election_id: The id of the election
+    /// options: The number of options of the election, which is also the number of ranks
+    /// Returns an error if there are no options or more than `MAX_OPTIONS`
+    pub fn new(election_id: BigInteger256, options: usize) -> Result<RankedSpecifiers, String> {
+        check_option_count(options)?;
+        let ranks = (0..options)
+            .map(|rank| (0..options)
+                .map(|option| Self::rank_specifier(&election_id, rank, option))
+                .collect())
+            .collect::<Result<_, String>>()?;
+
+        Ok(RankedSpecifiers { ranks })
+    }
+
+    pub fn from(election_id: u64, options: usize) -> Result<RankedSpecifiers, String> {
+        Self::new(BigInteger256::from(election_id), options)
+    }
+
+    /// This function creates the specifier of an option at a rank, the election id is hashed with a 3, the rank and the index of the option
+    fn rank_specifier(election_id: &BigInteger256, rank: usize, option: usize) -> Result<(G1, G2), String> {
+        let mut unhashed_specifier = election_id.to_bytes_le();
+        unhashed_specifier.push(3u8);
+        unhashed_specifier.extend_from_slice(&option_index(rank)?.to_be_bytes());
+        unhashed_specifier.extend_from_slice(&option_index(option)?.to_be_bytes());
+        Ok(ElectionSpecifiers::hash_into_ec_points(&unhashed_specifier))
+    }
+
+    /// This function checks a ranking of the options, it must not be empty and each option must exist and be ranked only once
+    /// A ranking does not need to rank all the options, the options that are not ranked are the least preferred
+    pub fn check_ranking(&self, ranking: &[usize]) -> Result<(), String> {
+        check_options(ranking, Some(self.ranks.len()), "ranking", "rank")
+    }
+
+    /// Returns the sum of the G1 specifiers of the options of the ranking at their rank, without creating the specifiers of the other options
+    /// The number of options of the election is not known, so the options are only checked against `MAX_OPTIONS`
+    /// This is what a voter needs to make the vote proof of a ranking, see `voter::generate_vote_proof`
+    pub fn ranking_specifier_of(election_id: u64, ranking: &[usize]) -> Result<G1, String> {
+        check_options(ranking, None, "ranking", "rank")?;
+        let election_id = BigInteger256::from(election_id);
+        ranking.iter().enumerate()
+            .map(|(rank, option)| Self::rank_specifier(&election_id, rank, *option).map(|specifier| specifier.0))
+            .sum()
+    }
+
+    /// Returns the sum of the G1 specifiers of the options of the ranking at their rank, which the vote proof of the ranking is made with
+    /// The options of the ranking must exist, see `check_ranking`
+    pub fn ranking_specifier(&self, ranking: &[usize]) -> G1 {
+        ranking.iter().enumerate().map(|(rank, option)| self.ranks[rank][*option].0).sum()
+    }
+
+    /// Returns the sum of the G2 specifiers of the options of the ranking at their rank, which the vote proof of the ranking is checked with
+    pub fn ranking_specifier_g2(&self, ranking: &[usize]) -> G2 {
+        ranking.iter().enumerate().map(|(rank, option)| self.ranks[rank][*option].1).sum()
+    }
+
+    /// This function checks that the provided specifiers have been generated from the provided election id
+    pub fn check_specifiers(&self, election_id: BigInteger256) -> bool {
+        RankedSpecifiers::new(election_id, self.ranks.len()).is_ok_and(|specifiers| self == &specifiers)
+    }
+}
+
+/// This function checks the number of options of an election, it must have at least one and at most `MAX_OPTIONS`
+fn check_option_count(options: usize) -> Result<(), String> {
+    match options {
+        1..=MAX_OPTIONS => Ok(()),
+        _ => Err(format!("An election must have between 1 and {} options, not {}", MAX_OPTIONS, options)),
+    }
+}
+
+/// This function checks the options of a ballot, they must not be empty and each option must exist and be in the ballot only once
+/// count: The number of options of the election, if it is not known the options are checked against `MAX_OPTIONS`
+/// noun and verb: What the ballot is and does with the options, for the messages, e.g. `selection` and `approve`
+fn check_options(options: &[usize], count: Option<usize>, noun: &str, verb: &str) -> Result<(), String> {
+    if options.is_empty() {
+        return Err(format!("The {} must {} at least one option", noun, verb));
+    }
+    match count {
+        Some(count) => if let Some(option) = options.iter().find(|option| **option >= count) {
+            return Err(format!("The option {} does not exist, the election has {} options", option, count));
+        },
+        None => if let Some(option) = options.iter().find(|option| **option >= MAX_OPTIONS) {
+            return Err(format!("The option {} does not exist, an election has at most {} options", option, MAX_OPTIONS));
+        },
+    }
+    let mut sorted = options.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() != options.len() {
+        return Err(format!("The {} {}s an option more than once", noun, verb));
+    }
+    Ok(())
+}
+
+/// Returns the index of an option or a rank as it is hashed into a specifier, in 4 bytes
+fn option_index(index: usize) -> Result<u32, String> {
+    u32::try_from(index).map_err(|_| format!("The option {} is too large, an option must fit in 4 bytes", index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approval_specifiers_are_distinct() {
+        let specifiers = ApprovalSpecifiers::from(3, 4).unwrap();
+        let election = ElectionSpecifiers::from(3);
+        for (i, option) in specifiers.options.iter().enumerate() {
+            assert!(option != &election.forr && option != &election.against);
+            assert!(specifiers.options[i + 1..].iter().all(|other| other != option));
+        }
+        assert!(specifiers.check_specifiers(BigInteger256::from(3)));
+        assert!(!specifiers.check_specifiers(BigInteger256::from(4)));
+    }
+
+    #[test]
+    fn checks_the_ranking() {
+        let specifiers = RankedSpecifiers::from(3, 3).unwrap();
+        assert!(specifiers.check_ranking(&[2, 0]).is_ok());
+        assert!(specifiers.check_ranking(&[]).is_err());
+        assert!(specifiers.check_ranking(&[1, 0, 1]).is_err());
+        assert!(specifiers.check_ranking(&[3]).is_err());
+        // The same options in another order have another specifier
+        assert!(specifiers.ranking_specifier(&[2, 0]) != specifiers.ranking_specifier(&[0, 2]));
+        assert!(specifiers.check_specifiers(BigInteger256::from(3)));
+    }
+
+    #[test]
+    fn checks_the_selection() {
+        let specifiers = ApprovalSpecifiers::from(3, 4).unwrap();
+        assert_eq!(specifiers.check_selection(&[3, 0]), Ok(vec![0, 3]));
+        assert!(specifiers.check_selection(&[]).is_err());
+        assert!(specifiers.check_selection(&[1, 1]).is_err());
+        assert!(specifiers.check_selection(&[4]).is_err());
+    }
+
+    #[test]
+    fn creates_only_the_needed_specifiers() {
+        let approval = ApprovalSpecifiers::from(3, 4).unwrap();
+        assert_eq!(ApprovalSpecifiers::selection_specifier_of(3, &[3, 0]), Ok(approval.selection_specifier(&[0, 3])));
+        assert!(ApprovalSpecifiers::selection_specifier_of(3, &[]).is_err());
+        assert!(ApprovalSpecifiers::selection_specifier_of(3, &[1, 1]).is_err());
+
+        let ranked = RankedSpecifiers::from(3, 3).unwrap();
+        assert_eq!(RankedSpecifiers::ranking_specifier_of(3, &[2, 0]), Ok(ranked.ranking_specifier(&[2, 0])));
+        assert!(RankedSpecifiers::ranking_specifier_of(3, &[0, 2, 0]).is_err());
+        assert!(RankedSpecifiers::ranking_specifier_of(3, &[MAX_OPTIONS]).is_err());
+        assert!(ApprovalSpecifiers::selection_specifier_of(3, &[MAX_OPTIONS - 1]).is_ok());
+
+        // A library caller can not ask for the specifiers of more options than an election can have
+        assert!(ApprovalSpecifiers::from(3, 0).is_err());
+        assert!(ApprovalSpecifiers::from(3, MAX_OPTIONS + 1).is_err());
+        assert!(RankedSpecifiers::from(3, 0).is_err());
+        assert!(RankedSpecifiers::from(3, MAX_OPTIONS + 1).is_err());
+    }
+}
@@ -0,0 +1,375 @@
+//! This module contains an in-process emulator of the `BatRaVot` contract.
+//! It follows the logic of `solidity/contracts/BatRaVot.sol` step by step, so that the whole
+//! voter -> batcher -> verifier flow can be tested with `cargo test`, without Hardhat.
+//! Every function returns the `require` message of the contract as an error.
+
+use std::collections::HashMap;
+use ark_ff::Zero;
+use web3::types::{Address, U256};
+
+use crate::{ElectionResult, ElectionSpecifiers, G1, SchnorrKnowledgeProof, verifier, Vote};
+
+/// The state of an election, mirrors the `State` enum of the contract
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElectionState {
+    Init,
+    Vote,
+    End,
+}
+
+/// A mock of the ERC20 voting token
+/// Only the balances and the total supply are tracked, as this is all the contract needs
+#[derive(Debug, Clone, Default)]
+pub struct MockToken {
+    balances: HashMap<Address, U256>,
+    total_supply: U256,
+}
+
+impl MockToken {
+    pub fn new() -> MockToken {
+        MockToken::default()
+    }
+
+    /// Creates new tokens for the provided address and increases the total supply
+    pub fn mint(&mut self, to: Address, amount: U256) {
+        *self.balances.entry(to).or_default() += amount;
+        self.total_supply += amount;
+    }
+
+    /// Moves tokens between two addresses
+    /// Returns an error if the sender does not have enough tokens
+    pub fn transfer(&mut self, from: Address, to: Address, amount: U256) -> Result<(), String> {
+        let from_balance = self.balance_of(&from);
+        if from_balance < amount {
+            return Err("ERC20: transfer amount exceeds balance".to_string());
+        }
+        self.balances.insert(from, from_balance - amount);
+        *self.balances.entry(to).or_default() += amount;
+        Ok(())
+    }
+
+    pub fn balance_of(&self, owner: &Address) -> U256 {
+        self.balances.get(owner).cloned().unwrap_or_default()
+    }
+
+    pub fn total_supply(&self) -> U256 {
+        self.total_supply
+    }
+}
+
+/// The metadata of a single election, mirrors the `Election` struct of the contract
+struct Election {
+    topic: String,
+    votes: HashMap<Address, Vote>,
+    specifiers: ElectionSpecifiers,
+    state: ElectionState,
+    result: ElectionResult,
+}
+
+/// The emulator of the `BatRaVot` contract
+/// The `sender` arguments stand for the `msg.sender` of the corresponding contract call
+pub struct BatRaVotEmulator {
+    elections: Vec<Election>,
+    voters: Vec<Address>,
+    census: HashMap<Address, G1>,
+    pub voting_token: MockToken,
+}
+
+impl BatRaVotEmulator {
+    /// Deploys a new emulated contract using the provided token for the vote weights
+    pub fn new(voting_token: MockToken) -> BatRaVotEmulator {
+        BatRaVotEmulator {
+            elections: Vec::new(),
+            voters: Vec::new(),
+            census: HashMap::new(),
+            voting_token,
+        }
+    }
+
+    /// Registers a voter public key in the census, mirrors `registerVoter`
+    /// The sender must prove the ownership of the key with a Schnorr Knowledge Proof and hold voting tokens
+    /// Note that, same as in the contract, registering twice adds the sender to the list of voters twice
+    pub fn register_voter(&mut self, sender: Address, pub_key: &G1, key_proof: &SchnorrKnowledgeProof) -> Result<(), String> {
+        if !key_proof.verify(pub_key) {
+            return Err("Invalid Key Proof".to_string());
+        }
+        if self.voting_token.balance_of(&sender).is_zero() {
+            return Err("You must have voting tokens to register".to_string());
+        }
+
+        self.voters.push(sender);
+        self.census.insert(sender, *pub_key);
+        Ok(())
+    }
+
+    /// Starts a new election, mirrors `createElection`
+    /// Same as the contract, the specifiers are trusted to have been generated from the election id
+    /// Returns the id of the new election
+    pub fn create_election(&mut self, topic: &str, specifiers: ElectionSpecifiers) -> u64 {
+        self.elections.push(Election {
+            topic: topic.to_string(),
+            votes: HashMap::new(),
+            specifiers,
+            state: ElectionState::Init,
+            result: ElectionResult::default(),
+        });
+        let election_id = self.elections.len() - 1;
+
+        // Same as the contract, the election moves to the `Vote` state straight away
+        self.elections[election_id].state = ElectionState::Vote;
+        election_id as u64
+    }
+
+    /// Returns the G1 specifiers `(For, Against)` of a running election, mirrors `getG1Specifiers`
+    pub fn get_g1_specifiers(&self, election_id: u64) -> Result<(G1, G1), String> {
+        let election = self.elections.get(election_id as usize)
+            .ok_or("Requesting specifiers for election that does not yet exist")?;
+        if election.state != ElectionState::Vote {
+            return Err("Requesting specifiers for election that is not active".to_string());
+        }
+        Ok((election.specifiers.forr.0, election.specifiers.against.0))
+    }
+
+    /// Submits the votes of a batch together with the election proof, mirrors `submitVotesWithProof`
+    /// The proof is checked with the same pairing product as the contract uses
+    /// If a voter has already voted, their vote is overwritten
+    pub fn submit_votes_with_proof(&mut self, election_id: u64, voters_for: &[Address], voters_against: &[Address], election_proof: &G1) -> Result<(), String> {
+        let election = self.elections.get_mut(election_id as usize)
+            .ok_or("Requesting specifiers for election that does not yet exist")?;
+        if election.state != ElectionState::Vote {
+            return Err("Providing proof for election that is not active".to_string());
+        }
+        if voters_for.is_empty() && voters_against.is_empty() {
+            return Err("Can not submit a proof for no voters".to_string());
+        }
+
+        // Same as the contract, only the first voter of each list is checked to be in the census
+        // Unknown keys are read as the zero point by the contract, which we reproduce here
+        for voters in [voters_for, voters_against] {
+            if let Some(first) = voters.first() {
+                if !self.census.contains_key(first) {
+                    return Err("Voter does not exist".to_string());
+                }
+            }
+        }
+        let census_keys = |voters: &[Address]| -> Vec<G1> {
+            voters.iter().map(|voter| self.census.get(voter).cloned().unwrap_or_else(G1::zero)).collect()
+        };
+
+        let proof_is_correct = verifier::validate_election_proof_evm(
+            &census_keys(voters_for),
+            &census_keys(voters_against),
+            election_proof,
+            &election.specifiers
+        );
+        if !proof_is_correct {
+            return Err("Verification check did not pass".to_string());
+        }
+
+        for voter in voters_against {
+            election.votes.insert(*voter, Vote::Against);
+        }
+        for voter in voters_for {
+            election.votes.insert(*voter, Vote::For);
+        }
+        Ok(())
+    }
+
+    /// Ends an ongoing election and tallies the votes, mirrors `closeElection`
+    /// The votes are weighted by the token balances at the moment of the call
+    pub fn close_election(&mut self, election_id: u64) -> Result<(), String> {
+        let election = self.elections.get_mut(election_id as usize)
+            .ok_or("The election does not yet exist")?;
+        if election.state != ElectionState::Vote {
+            return Err("The election must be in Vote state".to_string());
+        }
+        election.state = ElectionState::End;
+
+        let mut yes_voters = U256::zero();
+        let mut no_voters = U256::zero();
+        let mut yes_votes = U256::zero();
+        let mut no_votes = U256::zero();
+
+        for voter in self.voters.iter() {
+            match election.votes.get(voter) {
+                Some(Vote::For) => {
+                    yes_votes += self.voting_token.balance_of(voter);
+                    yes_voters += U256::one();
+                }
+                Some(Vote::Against) => {
+                    no_votes += self.voting_token.balance_of(voter);
+                    no_voters += U256::one();
+                }
+                None => {}
+            }
+        }
+
+        election.result = ElectionResult {
+            possible_votes: self.voting_token.total_supply(),
+            total_voters: yes_voters + no_voters,
+            yes_voters,
+            total_votes: yes_votes + no_votes,
+            yes_votes,
+        };
+        Ok(())
+    }
+
+    /// Returns the number of elections ever created
+    pub fn election_count(&self) -> u64 {
+        self.elections.len() as u64
+    }
+
+    pub fn election_topic(&self, election_id: u64) -> Option<&str> {
+        self.elections.get(election_id as usize).map(|election| election.topic.as_str())
+    }
+
+    pub fn election_state(&self, election_id: u64) -> Option<ElectionState> {
+        self.elections.get(election_id as usize).map(|election| election.state)
+    }
+
+    /// Returns the result of the election, it is only filled in once the election is closed
+    pub fn election_result(&self, election_id: u64) -> Option<&ElectionResult> {
+        self.elections.get(election_id as usize).map(|election| &election.result)
+    }
+
+    /// Returns the last recorded vote of the voter in the election
+    pub fn vote_of(&self, election_id: u64, voter: &Address) -> Option<Vote> {
+        self.elections.get(election_id as usize)
+            .and_then(|election| election.votes.get(voter).cloned())
+    }
+
+    /// Returns the public key the address has registered in the census
+    pub fn census_key(&self, voter: &Address) -> Option<&G1> {
+        self.census.get(voter)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ark_std::UniformRand;
+    use crate::{batcher, ScalarField};
+    use crate::voter::{generate_public_key, generate_vote_proof};
+    use super::*;
+
+    struct TestVoter {
+        prk: ScalarField,
+        address: Address,
+    }
+
+    /// Deploys the emulator with `balances.len()` registered voters, the `i`th voter holding `balances[i]` tokens
+    fn setup(balances: &[u64]) -> (BatRaVotEmulator, Vec<TestVoter>) {
+        let mut rng = ark_std::test_rng();
+        let mut token = MockToken::new();
+        let voters: Vec<TestVoter> = balances.iter().enumerate().map(|(i, balance)| {
+            let address = Address::from_low_u64_be(i as u64 + 1);
+            token.mint(address, U256::from(*balance));
+            TestVoter { prk: ScalarField::rand(&mut rng), address }
+        }).collect();
+        // Some tokens are held by addresses that do not vote
+        token.mint(Address::from_low_u64_be(1000), U256::from(100));
+
+        let mut contract = BatRaVotEmulator::new(token);
+        for voter in voters.iter() {
+            let key_proof = SchnorrKnowledgeProof::generate_key_proof(&voter.prk, &mut rng);
+            contract.register_voter(voter.address, &generate_public_key(&voter.prk), &key_proof).unwrap();
+        }
+        (contract, voters)
+    }
+
+    /// Generates the batch of the provided voters, as a batcher would
+    fn batch(voters: &[(&TestVoter, Vote)], specifiers: &ElectionSpecifiers) -> (Vec<Address>, Vec<Address>, G1) {
+        let vote_proofs: Vec<G1> = voters.iter().map(|(voter, vote)| {
+            let specifier = match vote {
+                Vote::For => &specifiers.forr.0,
+                Vote::Against => &specifiers.against.0,
+            };
+            generate_vote_proof(&voter.prk, specifier)
+        }).collect();
+        let proof = batcher::generate_batched_election_proof(&vote_proofs.iter().collect());
+
+        let voters_for = voters.iter().filter(|(_, vote)| *vote == Vote::For).map(|(voter, _)| voter.address).collect();
+        let voters_against = voters.iter().filter(|(_, vote)| *vote == Vote::Against).map(|(voter, _)| voter.address).collect();
+        (voters_for, voters_against, proof)
+    }
+
+    #[test]
+    fn full_election_is_tallied_by_token_balances() {
+        let (mut contract, voters) = setup(&[10, 20, 30, 40]);
+        let election_id = contract.create_election("Test", ElectionSpecifiers::from(0));
+        assert_eq!(contract.election_state(election_id), Some(ElectionState::Vote));
+
+        let specifiers = ElectionSpecifiers::from(election_id);
+        let (voters_for, voters_against, proof) = batch(&[(&voters[0], Vote::For), (&voters[1], Vote::Against), (&voters[2], Vote::For)], &specifiers);
+        contract.submit_votes_with_proof(election_id, &voters_for, &voters_against, &proof).unwrap();
+
+        contract.close_election(election_id).unwrap();
+        assert_eq!(contract.election_state(election_id), Some(ElectionState::End));
+        assert_eq!(contract.election_result(election_id).unwrap(), &ElectionResult {
+            possible_votes: U256::from(200),
+            total_voters: U256::from(3),
+            yes_voters: U256::from(2),
+            total_votes: U256::from(60),
+            yes_votes: U256::from(40),
+        });
+    }
+
+    #[test]
+    fn later_batch_overwrites_the_vote() {
+        let (mut contract, voters) = setup(&[10, 20]);
+        let election_id = contract.create_election("Test", ElectionSpecifiers::from(0));
+        let specifiers = ElectionSpecifiers::from(election_id);
+
+        let (voters_for, voters_against, proof) = batch(&[(&voters[0], Vote::For)], &specifiers);
+        contract.submit_votes_with_proof(election_id, &voters_for, &voters_against, &proof).unwrap();
+        let (voters_for, voters_against, proof) = batch(&[(&voters[0], Vote::Against), (&voters[1], Vote::Against)], &specifiers);
+        contract.submit_votes_with_proof(election_id, &voters_for, &voters_against, &proof).unwrap();
+
+        assert_eq!(contract.vote_of(election_id, &voters[0].address), Some(Vote::Against));
+        contract.close_election(election_id).unwrap();
+        assert_eq!(contract.election_result(election_id).unwrap().yes_voters, U256::zero());
+        assert_eq!(contract.election_result(election_id).unwrap().total_votes, U256::from(30));
+    }
+
+    #[test]
+    fn rejects_a_proof_with_a_flipped_vote() {
+        let (mut contract, voters) = setup(&[10, 20]);
+        let election_id = contract.create_election("Test", ElectionSpecifiers::from(0));
+        let specifiers = ElectionSpecifiers::from(election_id);
+
+        // The proof is generated for `For`, but the batcher claims the voter voted `Against`
+        let (voters_for, _, proof) = batch(&[(&voters[0], Vote::For)], &specifiers);
+        let result = contract.submit_votes_with_proof(election_id, &[], &voters_for, &proof);
+        assert_eq!(result, Err("Verification check did not pass".to_string()));
+        assert_eq!(contract.vote_of(election_id, &voters[0].address), None);
+    }
+
+    #[test]
+    fn rejects_an_invalid_registration() {
+        let mut rng = ark_std::test_rng();
+        let mut token = MockToken::new();
+        let rich = Address::from_low_u64_be(1);
+        token.mint(rich, U256::from(1));
+        let mut contract = BatRaVotEmulator::new(token);
+
+        let prk = ScalarField::rand(&mut rng);
+        let key_proof = SchnorrKnowledgeProof::generate_key_proof(&prk, &mut rng);
+        let other_key = generate_public_key(&ScalarField::rand(&mut rng));
+
+        assert_eq!(contract.register_voter(rich, &other_key, &key_proof), Err("Invalid Key Proof".to_string()));
+        assert_eq!(contract.register_voter(Address::from_low_u64_be(2), &generate_public_key(&prk), &key_proof), Err("You must have voting tokens to register".to_string()));
+        assert!(contract.register_voter(rich, &generate_public_key(&prk), &key_proof).is_ok());
+    }
+
+    #[test]
+    fn closed_election_does_not_accept_votes() {
+        let (mut contract, voters) = setup(&[10]);
+        let election_id = contract.create_election("Test", ElectionSpecifiers::from(0));
+        contract.close_election(election_id).unwrap();
+
+        let (voters_for, voters_against, proof) = batch(&[(&voters[0], Vote::For)], &ElectionSpecifiers::from(election_id));
+        assert!(contract.submit_votes_with_proof(election_id, &voters_for, &voters_against, &proof).is_err());
+        assert!(contract.close_election(election_id).is_err());
+        assert!(contract.get_g1_specifiers(election_id).is_err());
+    }
+}
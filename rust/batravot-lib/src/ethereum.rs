@@ -0,0 +1,378 @@
+//! The Ethereum accounts of the voters
+//! A voter signs its ballot with the key of its Ethereum account, to prove to the batcher that it controls the address of the ballot
+//! The ballots are signed as EIP-712 typed data, other messages with the `personal_sign` of the Ethereum wallets (EIP-191)
+
+use ark_ec::ProjectiveCurve;
+use ark_ff::{BigInteger, PrimeField};
+use secp256k1::{Message, PublicKey, Secp256k1};
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
+use sha3::{Digest, Keccak256};
+use web3::types::{Address, H256};
+
+use crate::{G1, ScalarField, Vote};
+use crate::representation::{FromStrCustom, SolidityRepresentable};
+
+pub use secp256k1::SecretKey;
+
+/// A recoverable secp256k1 signature in the Ethereum format, `r || s || v` with `v` being 27 or 28
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EthSignature(pub [u8; 65]);
+
+/// This function parses the secret key of an Ethereum account from a hex string of 32 bytes
+/// The 0x prefix and the surrounding whitespace are optional
+pub fn parse_secret_key(s: &str) -> Result<SecretKey, String> {
+    let bytes = hex::decode(s.trim().trim_start_matches("0x"))
+        .map_err(|err| format!("Failed to decode the Ethereum private key: {}", err))?;
+    SecretKey::from_slice(&bytes)
+        .map_err(|err| format!("Invalid Ethereum private key: {}", err))
+}
+
+/// This function returns the Ethereum address of the account of the secret key
+/// The address is the last 20 bytes of the Keccak256 hash of the uncompressed public key
+pub fn address(secret_key: &SecretKey) -> Address {
+    let public_key = PublicKey::from_secret_key(&Secp256k1::signing_only(), secret_key);
+    public_key_address(&public_key)
+}
+
+fn public_key_address(public_key: &PublicKey) -> Address {
+    Address::from_slice(&Keccak256::digest(&public_key.serialize_uncompressed()[1..])[12..])
+}
+
+/// This function returns the hash signed by `personal_sign` for the message
+/// It is the Keccak256 hash of `"\x19Ethereum Signed Message:\n" || len(message) || message`
+pub fn personal_message_hash(message: &[u8]) -> H256 {
+    let mut hasher = Keccak256::new();
+    hasher.update(format!("\x19Ethereum Signed Message:\n{}", message.len()).as_bytes());
+    hasher.update(message);
+    H256::from_slice(&hasher.finalize())
+}
+
+/// This function signs the message as `personal_sign` does, the signature is deterministic (RFC 6979)
+pub fn sign_personal_message(secret_key: &SecretKey, message: &[u8]) -> EthSignature {
+    sign_hash(secret_key, &personal_message_hash(message))
+}
+
+/// This function returns the address of the account that signed the message with `personal_sign`
+pub fn recover_personal_signer(message: &[u8], signature: &EthSignature) -> Result<Address, String> {
+    recover_signer(&personal_message_hash(message), signature)
+}
+
+/// The message the voter signs with its Ethereum wallet to derive its election private key, followed by the address of the contract
+/// The version is part of the message, so that a new derivation gives new keys
+pub const ELECTION_KEY_MESSAGE: &str = "BatRaVot election key v1";
+
+/// This function returns the message that is signed with `personal_sign` to derive the election private key for the contract
+/// The message is `BatRaVot election key v1` and the address of the contract in lower case hex, separated by a new line
+pub fn election_key_message(contract: &Address) -> String {
+    format!("{}\n{}", ELECTION_KEY_MESSAGE, contract.solidity_repr())
+}
+
+/// This function derives the election private key from the `personal_sign` signature of the `election_key_message`
+/// The wallets sign deterministically (RFC 6979), so the voter can derive the same key again from its wallet
+/// The `s` of the signature is normalized and `v` is not used, so that the equivalent encodings of the signature give the same key
+/// The key is the 64 bytes `keccak256(r || s || 0x00) || keccak256(r || s || 0x01)`, reduced modulo the order of the scalar field
+pub fn election_key_from_signature(signature: &EthSignature) -> Result<ScalarField, String> {
+    let mut standard = secp256k1::ecdsa::Signature::from_compact(&signature.0[..64])
+        .map_err(|err| format!("Invalid signature: {}", err))?;
+    standard.normalize_s();
+    let r_s = standard.serialize_compact();
+
+    let mut wide = Vec::new();
+    for counter in 0u8..2 {
+        let mut hasher = Keccak256::new();
+        hasher.update(r_s);
+        hasher.update([counter]);
+        wide.extend(hasher.finalize());
+    }
+    Ok(ScalarField::from_be_bytes_mod_order(&wide))
+}
+
+/// This function derives the election private key of the voter for the contract from the key of its Ethereum account
+/// It is the same key as the one derived from the signature of the wallet of the account
+pub fn derive_election_key(secret_key: &SecretKey, contract: &Address) -> ScalarField {
+    let signature = sign_personal_message(secret_key, election_key_message(contract).as_bytes());
+    election_key_from_signature(&signature).expect("The signature is valid")
+}
+
+/// The EIP-712 domain of the ballots, `EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)`
+pub const BALLOT_DOMAIN_NAME: &str = "BatRaVot";
+pub const BALLOT_DOMAIN_VERSION: &str = "1";
+pub const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+/// The EIP-712 type of the ballots
+/// The vote is `true` for `For`, the vote proof is the `[x, y]` of the G1 point, as in the contract
+pub const BALLOT_TYPE: &str = "Ballot(uint256 electionId,bool vote,uint256[2] voteProof,uint256 sequence)";
+
+/// The chain and the contract of the election, they are part of the EIP-712 domain of the ballots
+/// A ballot signed for an election of a contract can not be replayed with another contract, or with the same contract on another chain
+/// chain_id - The EIP-155 id of the chain the contract is deployed to, 1 for the Ethereum mainnet
+/// verifying_contract - The address of the election contract
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BallotDomain {
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl BallotDomain {
+    /// Returns the EIP-712 domain separator of the ballots
+    pub fn separator(&self) -> H256 {
+        domain_separator(BALLOT_DOMAIN_NAME, BALLOT_DOMAIN_VERSION, self.chain_id, &self.verifying_contract)
+    }
+}
+
+/// Returns the `hashStruct` of an `EIP712Domain` with the name, the version, the chain id and the verifying contract
+fn domain_separator(name: &str, version: &str, chain_id: u64, verifying_contract: &Address) -> H256 {
+    let mut encoded = Vec::new();
+    encoded.extend(Keccak256::digest(EIP712_DOMAIN_TYPE.as_bytes()));
+    encoded.extend(Keccak256::digest(name.as_bytes()));
+    encoded.extend(Keccak256::digest(version.as_bytes()));
+    encoded.extend(uint256(chain_id));
+    encoded.extend(H256::from(*verifying_contract).as_bytes());
+    H256::from_slice(&Keccak256::digest(&encoded))
+}
+
+/// Returns the hash that is signed for a typed message, `keccak256("\x19\x01" || domainSeparator || hashStruct(message))`
+fn typed_data_hash(domain_separator: &H256, hash_struct: &H256) -> H256 {
+    let mut encoded = vec![0x19, 0x01];
+    encoded.extend(domain_separator.as_bytes());
+    encoded.extend(hash_struct.as_bytes());
+    H256::from_slice(&Keccak256::digest(&encoded))
+}
+
+/// The ballot as it is signed by the Ethereum account of the voter, following EIP-712
+/// election_id - The election the ballot is for, so that the signature can not be used in another election
+/// vote - The vote of the ballot
+/// vote_proof - The proof of the vote, it is only valid with the public key of the voter, so it binds the ballot to the key
+/// sequence - The number of the ballot among the ballots of the voter, the batcher only accepts a later ballot with a higher sequence
+pub struct TypedBallot {
+    pub election_id: u64,
+    pub vote: Vote,
+    pub vote_proof: G1,
+    pub sequence: u64,
+}
+
+impl TypedBallot {
+    /// Returns the EIP-712 `hashStruct` of the ballot
+    pub fn hash_struct(&self) -> H256 {
+        let vote_proof = self.vote_proof.into_affine();
+        let mut coordinates = Vec::new();
+        coordinates.extend(vote_proof.x.into_repr().to_bytes_be());
+        coordinates.extend(vote_proof.y.into_repr().to_bytes_be());
+
+        let mut encoded = Vec::new();
+        encoded.extend(Keccak256::digest(BALLOT_TYPE.as_bytes()));
+        encoded.extend(uint256(self.election_id));
+        encoded.extend(uint256((self.vote == Vote::For) as u64));
+        encoded.extend(Keccak256::digest(&coordinates));
+        encoded.extend(uint256(self.sequence));
+        H256::from_slice(&Keccak256::digest(&encoded))
+    }
+
+    /// Returns the hash that is signed, `keccak256("\x19\x01" || domainSeparator || hashStruct(ballot))`
+    pub fn signing_hash(&self, domain: &BallotDomain) -> H256 {
+        typed_data_hash(&domain.separator(), &self.hash_struct())
+    }
+
+    /// This function signs the ballot as `eth_signTypedData_v4` does
+    pub fn sign(&self, domain: &BallotDomain, secret_key: &SecretKey) -> EthSignature {
+        sign_hash(secret_key, &self.signing_hash(domain))
+    }
+
+    /// This function returns the address of the account that signed the ballot
+    pub fn recover_signer(&self, domain: &BallotDomain, signature: &EthSignature) -> Result<Address, String> {
+        recover_signer(&self.signing_hash(domain), signature)
+    }
+}
+
+/// Returns the 32 bytes ABI encoding of the number
+fn uint256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// This function signs the hash, the signature is deterministic (RFC 6979)
+fn sign_hash(secret_key: &SecretKey, hash: &H256) -> EthSignature {
+    let hash = Message::from_slice(hash.as_bytes()).expect("The hash is 32 bytes long");
+    let (recovery_id, signature) = Secp256k1::signing_only()
+        .sign_ecdsa_recoverable(&hash, secret_key)
+        .serialize_compact();
+
+    let mut bytes = [0u8; 65];
+    bytes[..64].copy_from_slice(&signature);
+    bytes[64] = 27 + recovery_id.to_i32() as u8;
+    EthSignature(bytes)
+}
+
+/// This function returns the address of the account that signed the hash
+/// Both the `v` values 27/28 and 0/1 are accepted, as the wallets use either
+fn recover_signer(hash: &H256, signature: &EthSignature) -> Result<Address, String> {
+    let v = match signature.0[64] {
+        v @ 27..=28 => v - 27,
+        v => v,
+    };
+    let recovery_id = RecoveryId::from_i32(v as i32)
+        .map_err(|_| format!("Invalid recovery id of the signature: {}", signature.0[64]))?;
+    let signature = RecoverableSignature::from_compact(&signature.0[..64], recovery_id)
+        .map_err(|err| format!("Invalid signature: {}", err))?;
+    let hash = Message::from_slice(hash.as_bytes()).expect("The hash is 32 bytes long");
+
+    let public_key = Secp256k1::verification_only().recover_ecdsa(&hash, &signature)
+        .map_err(|err| format!("Failed to recover the signer of the signature: {}", err))?;
+    Ok(public_key_address(&public_key))
+}
+
+impl SolidityRepresentable for EthSignature {
+    fn solidity_repr(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+}
+impl FromStrCustom for EthSignature {
+    type Err = String;
+
+    fn from_str_c(s: &str) -> Result<Self, Self::Err> where Self: Sized {
+        let bytes = hex::decode(s.trim().trim_start_matches("0x")).map_err(|e| e.to_string())?;
+        let bytes: [u8; 65] = bytes.as_slice().try_into()
+            .map_err(|_| format!("Invalid signature length: expected 65, got {}", bytes.len()))?;
+        Ok(EthSignature(bytes))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The account used in the examples of the web3.js documentation
+    const SECRET_KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    const ADDRESS: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+    #[test]
+    fn test_address_of_secret_key() {
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        assert_eq!(address(&secret_key).solidity_repr(), ADDRESS);
+    }
+
+    #[test]
+    fn test_personal_sign_vector() {
+        // `web3.eth.accounts.sign('Some data', SECRET_KEY)`
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        assert_eq!(format!("{:?}", personal_message_hash(b"Some data")), "0x1da44b586eb0729ff70a73c326926f6ed5a25f5b056e7f47fbc6e58d86871655");
+        let signature = sign_personal_message(&secret_key, b"Some data");
+        assert_eq!(signature.solidity_repr(), "0xb91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a0291c");
+    }
+
+    #[test]
+    fn test_recover_personal_signer() {
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        let signature = sign_personal_message(&secret_key, b"Some data");
+        let parsed = EthSignature::from_str_c(&signature.solidity_repr()).unwrap();
+        assert_eq!(recover_personal_signer(b"Some data", &parsed).unwrap().solidity_repr(), ADDRESS);
+        // A signature of another message recovers another address
+        assert_ne!(recover_personal_signer(b"Other data", &parsed).unwrap().solidity_repr(), ADDRESS);
+    }
+
+    #[test]
+    fn test_typed_ballot_signature() {
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        let domain = BallotDomain { chain_id: 11155111, verifying_contract: Address::from_low_u64_be(1) };
+        let vote_proof = crate::voter::generate_vote_proof(&crate::ScalarField::from(3u64), &crate::ElectionSpecifiers::from(1).forr.0);
+        let ballot = TypedBallot { election_id: 1, vote: Vote::For, vote_proof, sequence: 1 };
+        let signature = ballot.sign(&domain, &secret_key);
+        assert_eq!(ballot.recover_signer(&domain, &signature).unwrap().solidity_repr(), ADDRESS);
+
+        // The signature is only valid for the same election, vote and sequence
+        for other in [TypedBallot { election_id: 2, ..ballot }, TypedBallot { vote: Vote::Against, ..ballot }, TypedBallot { sequence: 2, ..ballot }] {
+            assert_ne!(other.recover_signer(&domain, &signature).unwrap().solidity_repr(), ADDRESS);
+        }
+        // And for the same chain and contract
+        for other in [BallotDomain { chain_id: 1, ..domain }, BallotDomain { verifying_contract: Address::from_low_u64_be(2), ..domain }] {
+            assert_ne!(ballot.recover_signer(&other, &signature).unwrap().solidity_repr(), ADDRESS);
+        }
+    }
+
+    #[test]
+    fn test_eip712_vector() {
+        // The `Mail` example of EIP-712, signed with the key `keccak256("cow")` as `eth_signTypedData_v4` does
+        let contract = Address::from_str_c("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC").unwrap();
+        let separator = domain_separator("Ether Mail", "1", 1, &contract);
+        assert_eq!(format!("{:?}", separator), "0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f");
+        let mail_hash = H256::from_slice(&hex::decode("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e").unwrap());
+        let hash = typed_data_hash(&separator, &mail_hash);
+        assert_eq!(format!("{:?}", hash), "0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2");
+
+        let secret_key = SecretKey::from_slice(&Keccak256::digest(b"cow")).unwrap();
+        assert_eq!(address(&secret_key), Address::from_str_c("0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826").unwrap());
+        assert_eq!(sign_hash(&secret_key, &hash).solidity_repr(), "0x\
+            4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d\
+            07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562\
+            1c");
+    }
+
+    #[test]
+    fn test_typed_ballot_encoding() {
+        // The ballot is encoded as `abi.encode` of its fields, with the vote proof hashed as an array
+        use web3::ethabi::{self, Token};
+        let vote_proof = crate::voter::generate_vote_proof(&crate::ScalarField::from(3u64), &crate::ElectionSpecifiers::from(1).forr.0);
+        let ballot = TypedBallot { election_id: 5, vote: Vote::For, vote_proof, sequence: 1_700_000_000_000 };
+        let affine = vote_proof.into_affine();
+        let coordinates = ethabi::encode(&[Token::Uint(affine.x.into_repr().to_bytes_be().as_slice().into()), Token::Uint(affine.y.into_repr().to_bytes_be().as_slice().into())]);
+        let encoded = ethabi::encode(&[
+            Token::FixedBytes(Keccak256::digest(BALLOT_TYPE.as_bytes()).to_vec()),
+            Token::Uint(5.into()),
+            Token::Bool(true),
+            Token::FixedBytes(Keccak256::digest(&coordinates).to_vec()),
+            Token::Uint(1_700_000_000_000u64.into()),
+        ]);
+        assert_eq!(ballot.hash_struct(), H256::from_slice(&Keccak256::digest(&encoded)));
+
+        let domain = BallotDomain { chain_id: 1, verifying_contract: Address::from_low_u64_be(7) };
+        let encoded = ethabi::encode(&[
+            Token::FixedBytes(Keccak256::digest(EIP712_DOMAIN_TYPE.as_bytes()).to_vec()),
+            Token::FixedBytes(Keccak256::digest(b"BatRaVot").to_vec()),
+            Token::FixedBytes(Keccak256::digest(b"1").to_vec()),
+            Token::Uint(1.into()),
+            Token::Address(Address::from_low_u64_be(7)),
+        ]);
+        assert_eq!(domain.separator(), H256::from_slice(&Keccak256::digest(&encoded)));
+    }
+
+    #[test]
+    fn test_election_key_vectors() {
+        // The account of the web3.js documentation and the election contract deployed to Sepolia
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        let contract = Address::from_str_c("0x306d7b4BFcb45b9690a239Cd36084C2e8EE89776").unwrap();
+        assert_eq!(election_key_message(&contract), "BatRaVot election key v1\n0x306d7b4bfcb45b9690a239cd36084c2e8ee89776");
+
+        let signature = sign_personal_message(&secret_key, election_key_message(&contract).as_bytes());
+        assert_eq!(signature.solidity_repr(), "0xeab89c203858b1fc386866138895624ef1a10265802e1cdc102527b5a4b689ef15ea66a5c8ea33aef66fd9caecfa3aaee29a1fc30bd80b71da2a6e4c6a4bfa8a1c");
+        let election_key = derive_election_key(&secret_key, &contract);
+        assert_eq!(election_key.solidity_repr(), "0x2e0ee26526d670b2a7ba93a0f4b43a7a1f36c62cb2a0fdafddf2d23669c3dc20");
+        assert_eq!(election_key_from_signature(&signature).unwrap(), election_key);
+        assert_eq!(crate::voter::generate_public_key(&election_key).solidity_repr(),
+            "[0x0d71f8453bdbc9bab32f8294256180cbb300d880c62527f59989450be0cbd267,0x25153a33dba2c69717ad2b65db6397e6b22d345422f8e569005f2dd085d9e614]");
+
+        // The key of the same account is different for another contract
+        assert_ne!(derive_election_key(&secret_key, &Address::from_low_u64_be(1)), election_key);
+    }
+
+    #[test]
+    fn test_election_key_ignores_the_signature_encoding() {
+        let secret_key = parse_secret_key(SECRET_KEY).unwrap();
+        let signature = sign_personal_message(&secret_key, election_key_message(&Address::from_low_u64_be(1)).as_bytes());
+
+        // The same signature with `v` as 0/1 and with the high `s`, as some wallets return them
+        let mut other_encoding = signature;
+        other_encoding.0[64] -= 27;
+        let mut high_s = secp256k1::ecdsa::Signature::from_compact(&signature.0[..64]).unwrap().serialize_compact();
+        let order = hex::decode("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").unwrap();
+        let mut borrow = 0i16;
+        for i in (0..32).rev() {
+            let difference = order[i] as i16 - high_s[32 + i] as i16 - borrow;
+            high_s[32 + i] = difference.rem_euclid(256) as u8;
+            borrow = (difference < 0) as i16;
+        }
+        other_encoding.0[..64].copy_from_slice(&high_s);
+
+        assert_eq!(election_key_from_signature(&other_encoding).unwrap(), election_key_from_signature(&signature).unwrap());
+    }
+}
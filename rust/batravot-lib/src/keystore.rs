@@ -0,0 +1,298 @@
+//! The encrypted keystore of the election private keys, modeled on the Ethereum keystore v3
+//! The key is encrypted with AES-256-GCM, with a key derived from the password with scrypt
+//! The public key is stored in clear, so that the keystore can be listed without the password
+
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use aes_gcm::aead::{Aead, Payload};
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::rand::RngCore;
+use serde_json::{json, Value};
+use zeroize::Zeroizing;
+
+use crate::{G1, ScalarField, voter};
+use crate::representation::{FromStrCustom, SolidityRepresentable};
+
+/// The version of the keystore format
+pub const KEYSTORE_VERSION: u64 = 1;
+
+/// The parameters of the scrypt key derivation, `n` is `2^log_n`
+/// The default is the `n = 2^18, r = 8, p = 1` of the Ethereum keystores, it takes about a second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KdfParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams { log_n: 18, r: 8, p: 1 }
+    }
+}
+
+/// The highest scrypt parameters of a keystore that is read, `n = 2^20, r = 8, p = 16`
+/// The memory of scrypt is `128 * n * r` bytes and its time grows with `n * r * p`, so a crafted keystore could otherwise exhaust the machine
+pub const MAX_KDF_PARAMS: KdfParams = KdfParams { log_n: 20, r: 8, p: 16 };
+
+/// An election private key encrypted with a password
+/// id - A random UUID of the keystore
+/// label - The name the voter gives to the key
+/// public_key - The public key of the encrypted key, it is authenticated by the encryption
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keystore {
+    pub id: String,
+    pub label: String,
+    pub public_key: G1,
+    kdf_params: KdfParams,
+    salt: [u8; 32],
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    /// This function encrypts the election private key with the password
+    /// The salt, the nonce and the id are random
+    pub fn encrypt(label: &str, key: &ScalarField, password: &[u8], kdf_params: KdfParams, rng: &mut impl RngCore) -> Result<Keystore, String> {
+        let mut salt = [0u8; 32];
+        let mut nonce = [0u8; 12];
+        let mut id = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut nonce);
+        rng.fill_bytes(&mut id);
+
+        let public_key = voter::generate_public_key(key);
+        let plaintext = Zeroizing::new(key.into_repr().to_bytes_be());
+        let cipher = cipher(password, &salt, &kdf_params)?;
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: public_key.solidity_repr().as_bytes() })
+            .map_err(|_| "Failed to encrypt the key".to_string())?;
+
+        Ok(Keystore {
+            id: uuid(id),
+            label: label.to_string(),
+            public_key,
+            kdf_params,
+            salt,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// This function decrypts the election private key with the password
+    /// A wrong password and a modified keystore give the same error, as the encryption is authenticated
+    /// The key is cleared from memory when it is dropped
+    pub fn decrypt(&self, password: &[u8]) -> Result<Zeroizing<ScalarField>, String> {
+        let cipher = cipher(password, &self.salt, &self.kdf_params)?;
+        let plaintext = cipher.decrypt(Nonce::from_slice(&self.nonce), Payload { msg: &self.ciphertext, aad: self.public_key.solidity_repr().as_bytes() })
+            .map(Zeroizing::new)
+            .map_err(|_| "Failed to decrypt the key, the password is wrong or the keystore is corrupted".to_string())?;
+
+        let key = Zeroizing::new(ScalarField::from_be_bytes_mod_order(&plaintext));
+        if voter::generate_public_key(&key) != self.public_key {
+            return Err("The decrypted key does not match the public key of the keystore".to_string());
+        }
+        Ok(key)
+    }
+
+    /// This function returns the keystore as JSON, in the layout of the Ethereum keystore v3
+    pub fn to_json(&self) -> Value {
+        self.ordered_json().into_value()
+    }
+
+    /// This function returns the keystore as pretty printed JSON, with the fields in the order of the Ethereum keystore v3 files
+    /// The order does not depend on the features of `serde_json`, which sort the fields of a `Value` by default
+    pub fn to_json_string(&self) -> String {
+        let mut json = String::new();
+        self.ordered_json().write(0, &mut json);
+        json
+    }
+
+    fn ordered_json(&self) -> OrderedJson {
+        use OrderedJson::{Object, Value as Leaf};
+        Object(vec![
+            ("version", Leaf(json!(KEYSTORE_VERSION))),
+            ("id", Leaf(json!(self.id))),
+            ("label", Leaf(json!(self.label))),
+            ("publicKey", Leaf(json!(self.public_key.solidity_repr()))),
+            ("crypto", Object(vec![
+                ("cipher", Leaf(json!("aes-256-gcm"))),
+                ("ciphertext", Leaf(json!(hex::encode(&self.ciphertext)))),
+                ("cipherparams", Object(vec![("nonce", Leaf(json!(hex::encode(self.nonce))))])),
+                ("kdf", Leaf(json!("scrypt"))),
+                ("kdfparams", Object(vec![
+                    ("dklen", Leaf(json!(32))),
+                    ("n", Leaf(json!(1u64 << self.kdf_params.log_n))),
+                    ("r", Leaf(json!(self.kdf_params.r))),
+                    ("p", Leaf(json!(self.kdf_params.p))),
+                    ("salt", Leaf(json!(hex::encode(self.salt)))),
+                ])),
+            ])),
+        ])
+    }
+
+    /// This function parses a keystore from JSON, only the version 1 with scrypt and AES-256-GCM is supported
+    pub fn from_json(json: &Value) -> Result<Keystore, String> {
+        let field = |value: &Value, name: &str| value.get(name).and_then(|value| value.as_str()).map(|value| value.to_string())
+            .ok_or(format!("Error reading the `{}` field of the keystore", name));
+        let number = |value: &Value, name: &str| value.get(name).and_then(|value| value.as_u64())
+            .ok_or(format!("Error reading the `{}` field of the keystore", name));
+        let bytes = |value: &Value, name: &str| hex::decode(field(value, name)?)
+            .map_err(|err| format!("Error parsing the `{}` field of the keystore: {}", name, err));
+
+        let version = number(json, "version")?;
+        if version != KEYSTORE_VERSION {
+            return Err(format!("The keystore version {} is not supported, the supported version is {}", version, KEYSTORE_VERSION));
+        }
+        let crypto = json.get("crypto").ok_or("Error reading the `crypto` field of the keystore")?;
+        if field(crypto, "cipher")? != "aes-256-gcm" || field(crypto, "kdf")? != "scrypt" {
+            return Err("Only the keystores with the `scrypt` KDF and the `aes-256-gcm` cipher are supported".to_string());
+        }
+        let cipher_params = crypto.get("cipherparams").ok_or("Error reading the `cipherparams` field of the keystore")?;
+        let kdf_params_json = crypto.get("kdfparams").ok_or("Error reading the `kdfparams` field of the keystore")?;
+
+        let n = number(kdf_params_json, "n")?;
+        if !n.is_power_of_two() || n < 2 || number(kdf_params_json, "dklen")? != 32 {
+            return Err("Invalid scrypt parameters of the keystore".to_string());
+        }
+        let parameter = |name: &str| u32::try_from(number(kdf_params_json, name)?)
+            .map_err(|_| format!("The `{}` scrypt parameter of the keystore is too large", name));
+        let kdf_params = KdfParams {
+            // `n` is a power of two of at most 64 bits
+            log_n: n.trailing_zeros() as u8,
+            r: parameter("r")?,
+            p: parameter("p")?,
+        };
+        if kdf_params.log_n > MAX_KDF_PARAMS.log_n || kdf_params.r == 0 || kdf_params.r > MAX_KDF_PARAMS.r
+            || kdf_params.p == 0 || kdf_params.p > MAX_KDF_PARAMS.p {
+            return Err(format!("The scrypt parameters of the keystore are out of the limits, at most n = 2^{}, r = {} and p = {}",
+                MAX_KDF_PARAMS.log_n, MAX_KDF_PARAMS.r, MAX_KDF_PARAMS.p));
+        }
+
+        Ok(Keystore {
+            id: field(json, "id")?,
+            label: field(json, "label")?,
+            public_key: G1::from_str_c(&field(json, "publicKey")?)?,
+            salt: bytes(kdf_params_json, "salt")?.try_into()
+                .map_err(|_| "The salt of the keystore must be 32 bytes long".to_string())?,
+            nonce: bytes(cipher_params, "nonce")?.try_into()
+                .map_err(|_| "The nonce of the keystore must be 12 bytes long".to_string())?,
+            ciphertext: bytes(crypto, "ciphertext")?,
+            kdf_params,
+        })
+    }
+}
+
+/// A JSON value whose objects keep the order of their fields
+enum OrderedJson {
+    Value(Value),
+    Object(Vec<(&'static str, OrderedJson)>),
+}
+
+impl OrderedJson {
+    fn into_value(self) -> Value {
+        match self {
+            OrderedJson::Value(value) => value,
+            OrderedJson::Object(fields) => Value::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value.into_value())).collect()),
+        }
+    }
+
+    /// This function writes the value in the same layout as `serde_json::to_string_pretty`
+    fn write(&self, indent: usize, json: &mut String) {
+        match self {
+            OrderedJson::Value(value) => json.push_str(&value.to_string()),
+            OrderedJson::Object(fields) => {
+                json.push('{');
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    json.push_str(if index == 0 { "\n" } else { ",\n" });
+                    json.push_str(&format!("{}{}: ", "  ".repeat(indent + 1), Value::from(*name)));
+                    value.write(indent + 1, json);
+                }
+                json.push_str(&format!("\n{}}}", "  ".repeat(indent)));
+            }
+        }
+    }
+}
+
+/// Returns the cipher with the key derived from the password
+fn cipher(password: &[u8], salt: &[u8], kdf_params: &KdfParams) -> Result<Aes256Gcm, String> {
+    let params = scrypt::Params::new(kdf_params.log_n, kdf_params.r, kdf_params.p, 32)
+        .map_err(|err| format!("Invalid scrypt parameters: {}", err))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password, salt, &params, key.as_mut())
+        .map_err(|err| format!("Failed to derive the key from the password: {}", err))?;
+    Aes256Gcm::new_from_slice(key.as_ref()).map_err(|err| err.to_string())
+}
+
+/// Returns the random bytes formatted as a version 4 UUID
+fn uuid(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ark_std::UniformRand;
+    use super::*;
+
+    /// Light parameters, so that the tests are fast
+    const TEST_PARAMS: KdfParams = KdfParams { log_n: 10, r: 8, p: 1 };
+
+    #[test]
+    fn test_keystore_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let key = ScalarField::rand(rng);
+        let keystore = Keystore::encrypt("main", &key, b"password", TEST_PARAMS, rng).unwrap();
+
+        let parsed = Keystore::from_json(&keystore.to_json()).unwrap();
+        assert_eq!(parsed, keystore);
+        assert_eq!(parsed.label, "main");
+        assert_eq!(*parsed.decrypt(b"password").unwrap(), key);
+        assert!(parsed.decrypt(b"other password").is_err());
+    }
+
+    #[test]
+    fn test_keystore_is_authenticated() {
+        let rng = &mut ark_std::test_rng();
+        let keystore = Keystore::encrypt("main", &ScalarField::rand(rng), b"password", TEST_PARAMS, rng).unwrap();
+
+        // The public key can not be replaced, as it is the associated data of the encryption
+        let mut json = keystore.to_json();
+        json["publicKey"] = Value::from(voter::generate_public_key(&ScalarField::rand(rng)).solidity_repr());
+        assert!(Keystore::from_json(&json).unwrap().decrypt(b"password").is_err());
+
+        let mut json = keystore.to_json();
+        json["version"] = Value::from(3);
+        assert!(Keystore::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_keystore_rejects_costly_parameters() {
+        let rng = &mut ark_std::test_rng();
+        let keystore = Keystore::encrypt("main", &ScalarField::rand(rng), b"password", TEST_PARAMS, rng).unwrap();
+
+        for (name, value) in [("n", json!(1u64 << 21)), ("r", json!(9)), ("p", json!(17)), ("p", json!(0)), ("r", json!(1u64 << 32))] {
+            let mut json = keystore.to_json();
+            json["crypto"]["kdfparams"][name] = value;
+            assert!(Keystore::from_json(&json).is_err(), "{}", json["crypto"]["kdfparams"]);
+        }
+        let mut json = keystore.to_json();
+        json["crypto"]["kdfparams"]["p"] = json!(16);
+        assert!(Keystore::from_json(&json).is_ok());
+    }
+
+    #[test]
+    fn test_keystore_file_layout() {
+        let rng = &mut ark_std::test_rng();
+        let keystore = Keystore::encrypt("main", &ScalarField::rand(rng), b"password", TEST_PARAMS, rng).unwrap();
+
+        // The fields are in the order of the Ethereum keystore v3 files, and the file is parsed as the same JSON
+        let content = keystore.to_json_string();
+        let positions: Vec<usize> = ["\"version\"", "\"id\"", "\"label\"", "\"publicKey\"", "\"crypto\"", "\"cipher\"", "\"kdfparams\"", "\"dklen\"", "\"salt\""]
+            .iter().map(|field| content.find(field).unwrap()).collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", content);
+        assert_eq!(serde_json::from_str::<Value>(&content).unwrap(), keystore.to_json());
+        assert!(content.starts_with("{\n  \"version\": 1,\n  \"id\": "));
+    }
+}
@@ -3,13 +3,23 @@ pub(crate) mod election_specifiers;
 mod crypto;
 pub(crate) mod types;
 pub mod representation;
+pub mod ballot;
+pub mod balances;
+pub mod emulator;
+pub mod ethereum;
+pub mod keystore;
+pub mod submission;
 
 /// Re-export key functionality to consumers of the library
 /// Re-export cryptography functions of the BatRaVot
 pub use crypto::{voter, batcher, verifier};
 pub use crypto::schnorr::SchnorrKnowledgeProof;
+pub use crypto::dleq::DleqProof;
+pub use crypto::shamir::Share;
+pub use crypto::election_verifier::ElectionVerifier;
+pub use crypto::fixed_base::{ElectionTables, FixedBaseTable};
 pub use el_curve::{G1, G2, ScalarField, CurveBaseField};
 
 // Re-export useful structs
-pub use election_specifiers::ElectionSpecifiers;
-pub use types::{Vote};
+pub use election_specifiers::{ElectionSpecifiers, ApprovalSpecifiers, RankedSpecifiers, MAX_OPTIONS};
+pub use types::{Vote, ElectionResult};
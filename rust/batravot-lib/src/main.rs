@@ -9,10 +9,9 @@ use secp256k1::{PublicKey, Secp256k1, SecretKey};
 use sha3::{Keccak256, Digest};
 use web3::types::{Address, H160};
 use batravot_lib;
-use batravot_lib::{G1, ScalarField, SchnorrKnowledgeProof, representation::JavaScriptRepresentable, Vote, ElectionSpecifiers};
+use batravot_lib::{G1, ScalarField, SchnorrKnowledgeProof, representation::JavaScriptRepresentable, Vote, ElectionSpecifiers, ElectionTables};
 use batravot_lib::batcher::generate_batched_election_proof;
 use batravot_lib::verifier::validate_election_proof;
-use batravot_lib::voter::{generate_public_key, generate_vote_proof};
 
 
 struct Voter {
@@ -85,15 +84,17 @@ fn get_election_setup() -> (u32, u32) {
 
 fn simulate_election(mut rng: &mut StdRng, for_voter_amount: u32, against_voter_amount: u32, election_id: BigInteger256) {
     let specifiers = ElectionSpecifiers::new(election_id);
+    // Every voter multiplies the same points, so they are multiplied with precomputed tables
+    let tables = ElectionTables::new(&specifiers, for_voter_amount as usize + against_voter_amount as usize);
 
     let mut for_voters = Vec::new();
     for _ in 0..for_voter_amount {
-        for_voters.push(generate_voter(&mut rng, &specifiers.forr.0, Vote::For));
+        for_voters.push(generate_voter(&mut rng, &tables, Vote::For));
     }
 
     let mut agaist_voters = Vec::new();
     for _ in 0..against_voter_amount {
-        agaist_voters.push(generate_voter(&mut rng, &specifiers.against.0, Vote::Against));
+        agaist_voters.push(generate_voter(&mut rng, &tables, Vote::Against));
     }
 
 
@@ -181,13 +182,13 @@ fn simulate_election(mut rng: &mut StdRng, for_voter_amount: u32, against_voter_
 }
 
 
-fn generate_voter(mut rng: &mut impl Rng, vote_specifier: &G1, vote: Vote) -> Voter {
+fn generate_voter(mut rng: &mut impl Rng, tables: &ElectionTables, vote: Vote) -> Voter {
     let prk = ScalarField::rand(&mut rng);
-    let pbk = generate_public_key(&prk);
+    let pbk = tables.generate_public_key(&prk);
 
 
-    let vote_proof = generate_vote_proof(&prk, vote_specifier);
-    let key_proof = SchnorrKnowledgeProof::generate_key_proof(&prk, &mut rng);
+    let vote_proof = tables.generate_vote_proof(&prk, vote);
+    let key_proof = tables.generate_key_proof(&prk, &mut rng);
 
 
     let secp = Secp256k1::new();
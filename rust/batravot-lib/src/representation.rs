@@ -4,7 +4,7 @@ use ark_ff::{BigInteger, BigInteger256, PrimeField};
 use web3::types::Address;
 
 use crate::el_curve::{curve, CurveBaseField, CurveExtensionField, ScalarField};
-use crate::{ElectionSpecifiers, G1, G2, SchnorrKnowledgeProof};
+use crate::{DleqProof, ElectionSpecifiers, G1, G2, SchnorrKnowledgeProof};
 
 /// This trait is used to convert a complex type to a type that Solidity can understand
 pub trait SolidityRepresentable {
@@ -249,6 +249,68 @@ impl JavaScriptRepresentable for SchnorrKnowledgeProof {
         format!("[{}, {}, {}]", t_affine.x.javascript_repr(), t_affine.y.javascript_repr(), self.s.javascript_repr())
     }
 }
+impl FromStrCustom for SchnorrKnowledgeProof {
+    type Err = String;
+
+    /// Converts a string to a `SchnorrKnowledgeProof`
+    /// Accepts the format of the Solidity representation: `[[x,y],s]`, which stands for the point `t` followed by the scalar `s`.
+    /// The brackets are optional, the 3 elements can also be provided as a plain comma separated list.
+    ///
+    /// # Arguments
+    /// * `s` - The string to parse
+    /// # Returns
+    /// * `Ok(SchnorrKnowledgeProof)` - If the string is valid
+    /// * `Err(String)` - If the string is invalid
+    /// # Example
+    /// ```
+    /// use batravot_lib::{ScalarField, SchnorrKnowledgeProof, voter};
+    /// use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+    /// let prk = ScalarField::from(42u64);
+    /// let proof = SchnorrKnowledgeProof::generate_key_proof(&prk, &mut ark_std::test_rng());
+    /// let proof_parsed = SchnorrKnowledgeProof::from_str_c(&proof.solidity_repr()).unwrap();
+    /// assert!(proof_parsed.verify(&voter::generate_public_key(&prk)));
+    /// ```
+    fn from_str_c(s: &str) -> Result<Self, Self::Err> {
+        let elements: Vec<String> = s.trim().replace(" ", "").replace("[", "").replace("]", "").split(",")
+            .map(|element| element.to_string()).collect();
+
+        if elements.len() != 3 {
+            return Err(format!("Invalid Schnorr key proof format: expected 3 elements, got {}", elements.len()));
+        }
+
+        Ok(SchnorrKnowledgeProof {
+            t: G1::from_str_c(&elements[0..2].join(","))?,
+            s: ScalarField::from_str_c(&elements[2])?,
+        })
+    }
+}
+
+impl SolidityRepresentable for DleqProof {
+    fn solidity_repr(&self) -> String {
+        format!("[{},{}]", self.c.solidity_repr(), self.s.solidity_repr())
+    }
+}
+impl FromStrCustom for DleqProof {
+    type Err = String;
+
+    /// Converts a string to a `DleqProof`
+    /// Accepts the format of the Solidity representation: `[c,s]`, the brackets are optional
+    ///
+    /// # Arguments
+    /// * `s` - The string to parse
+    /// # Returns
+    /// * `Ok(DleqProof)` - If the string is valid
+    /// * `Err(String)` - If the string is invalid
+    fn from_str_c(s: &str) -> Result<Self, Self::Err> {
+        let parsed_s = s.trim().replace(" ", "").replace("[", "").replace("]", "");
+        let (c, s) = parsed_s.split_once(",").ok_or("Invalid DLEQ proof format")?;
+        Ok(DleqProof {
+            c: ScalarField::from_str_c(c)?,
+            s: ScalarField::from_str_c(s)?,
+        })
+    }
+}
+
 
 
 impl SolidityRepresentable for ElectionSpecifiers {
@@ -261,6 +323,47 @@ impl JavaScriptRepresentable for ElectionSpecifiers {
         format!("{}, {}, {}, {}", self.forr.0.javascript_repr(), self.forr.1.javascript_repr(), self.against.0.javascript_repr(), self.against.1.javascript_repr())
     }
 }
+impl FromStrCustom for ElectionSpecifiers {
+    type Err = String;
+
+    /// Converts a string to `ElectionSpecifiers`
+    /// Accepts the format of the Solidity representation: `[x,y], [x_c1,x_c0,y_c1,y_c0], [x,y], [x_c1,x_c0,y_c1,y_c0]`,
+    /// which stands for the `For` G1 and G2 specifiers followed by the `Against` G1 and G2 specifiers.
+    /// The brackets are optional, the 12 coordinates can also be provided as a plain comma separated list.
+    ///
+    /// # Arguments
+    /// * `s` - The string to parse
+    /// # Returns
+    /// * `Ok(ElectionSpecifiers)` - If the string is valid
+    /// * `Err(String)` - If the string is invalid
+    /// # Example
+    /// ```
+    /// use batravot_lib::ElectionSpecifiers;
+    /// use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+    /// let specifiers = ElectionSpecifiers::from(1);
+    /// let specifiers_parsed = ElectionSpecifiers::from_str_c(&specifiers.solidity_repr()).unwrap();
+    /// assert!(specifiers == specifiers_parsed);
+    /// ```
+    fn from_str_c(s: &str) -> Result<Self, Self::Err> {
+        let elements: Vec<String> = s.trim().replace(" ", "").replace("[", "").replace("]", "").split(",")
+            .map(|element| element.to_string()).collect();
+
+        if elements.len() != 12 {
+            return Err(format!("Invalid election specifiers format: expected 12 coordinates, got {}", elements.len()));
+        }
+
+        let forr = (
+            G1::from_str_c(&elements[0..2].join(","))?,
+            G2::from_str_c(&elements[2..6].join(","))?,
+        );
+        let against = (
+            G1::from_str_c(&elements[6..8].join(","))?,
+            G2::from_str_c(&elements[8..12].join(","))?,
+        );
+
+        Ok(ElectionSpecifiers { against, forr })
+    }
+}
 
 
 impl SolidityRepresentable for Address {
@@ -373,6 +476,15 @@ mod test_representations {
 
 
 
+    #[test]
+    fn test_election_specifiers_representations() {
+        let specifiers = ElectionSpecifiers::from(42);
+        let specifiers_str = specifiers.solidity_repr();
+        let specifiers_parsed = ElectionSpecifiers::from_str_c(&specifiers_str).unwrap();
+        assert!(specifiers == specifiers_parsed);
+        assert!(ElectionSpecifiers::from_str_c(&specifiers.forr.0.solidity_repr()).is_err());
+    }
+
     #[test]
     fn test_address_representations() {
         let rng = &mut ark_std::test_rng();
@@ -382,5 +494,20 @@ mod test_representations {
         assert_eq!(address, address_parsed);
     }
 
+    #[test]
+    fn test_schnorr_key_proof_representations() {
+        let rng = &mut ark_std::test_rng();
+        let prk = ScalarField::rand(rng);
+        let proof = SchnorrKnowledgeProof::generate_key_proof(&prk, rng);
+        let proof_str = proof.solidity_repr();
+        let proof_parsed = SchnorrKnowledgeProof::from_str_c(&proof_str).unwrap();
+        assert_eq!((proof.t, proof.s), (proof_parsed.t, proof_parsed.s));
+        // The brackets are optional
+        let proof_parsed = SchnorrKnowledgeProof::from_str_c(&proof_str.replace("[", "").replace("]", "")).unwrap();
+        assert_eq!((proof.t, proof.s), (proof_parsed.t, proof_parsed.s));
+        assert!(SchnorrKnowledgeProof::from_str_c(&proof.t.solidity_repr()).is_err());
+        assert!(SchnorrKnowledgeProof::from_str_c(&format!("{},{}", proof_str, proof.s.solidity_repr())).is_err());
+    }
+
 
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+//! The submission of the batches of several elections with a single aggregated proof
+//! A batcher serving several elections at once can submit all of their batches together, the proof is then checked
+//! with one pairing product, see `verifier::validate_multi_election_proof`
+//! The contract has no function for such a submission yet, it only accepts the batch of one election with `submitVotesWithProof`
+
+use std::collections::HashSet;
+use ark_ec::ProjectiveCurve;
+use serde_json::{json, Value};
+use web3::types::Address;
+
+use crate::{batcher, G1};
+use crate::representation::{FromStrCustom, SolidityRepresentable};
+
+/// The votes of a single election in a multi-election submission
+/// election_id: The id of the election
+/// voters_for: The addresses of the voters who voted for
+/// voters_against: The addresses of the voters who voted against
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectionVotes {
+    pub election_id: u64,
+    pub voters_for: Vec<Address>,
+    pub voters_against: Vec<Address>,
+}
+
+/// The batches of several elections, submitted with the sum of their election proofs
+/// elections: The votes of each election, an election appears only once
+/// election_proof: The aggregated proof of all the elections
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiElectionSubmission {
+    pub elections: Vec<ElectionVotes>,
+    pub election_proof: G1,
+}
+
+impl MultiElectionSubmission {
+    /// This function aggregates the batches of several elections into one submission
+    /// batches: The votes of each election with its election proof
+    /// Returns an error if there are no batches, if a batch has no votes or if an election is provided twice
+    pub fn aggregate(batches: Vec<(ElectionVotes, G1)>) -> Result<MultiElectionSubmission, String> {
+        if batches.is_empty() {
+            return Err("Can not submit a proof for no elections".to_string());
+        }
+        let mut election_ids = HashSet::new();
+        for (election, _) in batches.iter() {
+            if !election_ids.insert(election.election_id) {
+                return Err(format!("The election {} is provided twice", election.election_id));
+            }
+            if election.voters_for.is_empty() && election.voters_against.is_empty() {
+                return Err(format!("The election {} has no votes", election.election_id));
+            }
+        }
+
+        let election_proof = batcher::generate_multi_election_proof(&batches.iter().map(|(_, proof)| proof).collect());
+        Ok(MultiElectionSubmission {
+            elections: batches.into_iter().map(|(election, _)| election).collect(),
+            election_proof,
+        })
+    }
+
+    /// This function returns the submission as JSON, the fields of each election are the ones of a single election batch
+    /// `{"elections": [{"electionId": ..., "votersFor": [...], "votersAgainst": [...]}], "electionProof": "[0x..., 0x...]"}`
+    pub fn to_json(&self) -> Value {
+        let addresses = |addresses: &[Address]| addresses.iter().map(|address| address.solidity_repr()).collect::<Vec<_>>();
+        let elections: Vec<Value> = self.elections.iter().map(|election| json!({
+            "electionId": election.election_id,
+            "votersFor": addresses(&election.voters_for),
+            "votersAgainst": addresses(&election.voters_against),
+        })).collect();
+        json!({
+            "elections": elections,
+            "electionProof": self.election_proof.solidity_repr(),
+        })
+    }
+
+    /// This function parses the submission from the JSON written by `to_json`
+    /// The election proof must be a point of the curve
+    pub fn from_json(json: &Value) -> Result<MultiElectionSubmission, String> {
+        let addresses = |election: &Value, field: &str| -> Result<Vec<Address>, String> {
+            election[field].as_array().ok_or(format!("The {} field is missing", field))?.iter()
+                .map(|address| address.as_str().ok_or(format!("The {} field must contain strings", field))
+                    .and_then(|address| Address::from_str_c(address).map_err(|err| format!("Error parsing the address {}: {}", address, err))))
+                .collect()
+        };
+        let elections = json["elections"].as_array().ok_or("The elections field is missing")?.iter()
+            .map(|election| Ok(ElectionVotes {
+                election_id: election["electionId"].as_u64().ok_or("The electionId field is missing or is not a number")?,
+                voters_for: addresses(election, "votersFor")?,
+                voters_against: addresses(election, "votersAgainst")?,
+            }))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let election_proof = json["electionProof"].as_str().ok_or("The electionProof field is missing".to_string())
+            .and_then(|proof| G1::from_str_c(proof).map_err(|err| format!("Error parsing the election proof: {}", err)))?;
+        if !election_proof.into_affine().is_on_curve() {
+            return Err("The election proof is not a point of the curve".to_string());
+        }
+        Ok(MultiElectionSubmission { elections, election_proof })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::{ElectionSpecifiers, ScalarField, verifier, voter};
+    use super::*;
+
+    /// Returns the votes and the proof of an election in which the voters with the keys `keys_for` vote for
+    fn election(election_id: u64, keys_for: &[u64]) -> (ElectionVotes, G1, G1) {
+        let specifiers = ElectionSpecifiers::from(election_id);
+        let keys: Vec<ScalarField> = keys_for.iter().map(|key| ScalarField::from(*key)).collect();
+        let proofs: Vec<G1> = keys.iter().map(|key| voter::generate_vote_proof(key, &specifiers.forr.0)).collect();
+        let key_sum = keys.iter().map(voter::generate_public_key).sum();
+        let votes = ElectionVotes {
+            election_id,
+            voters_for: keys_for.iter().map(|key| Address::from_low_u64_be(*key)).collect(),
+            voters_against: Vec::new(),
+        };
+        (votes, batcher::generate_batched_election_proof(&proofs.iter().collect()), key_sum)
+    }
+
+    #[test]
+    fn aggregated_submission_is_verified_at_once() {
+        let (first, first_proof, first_keys) = election(1, &[2, 3]);
+        let (second, second_proof, second_keys) = election(2, &[4]);
+        let submission = MultiElectionSubmission::aggregate(vec![(first.clone(), first_proof), (second, second_proof)]).unwrap();
+
+        let specifiers = [ElectionSpecifiers::from(1), ElectionSpecifiers::from(2)];
+        let elections = [(first_keys, G1::default(), &specifiers[0]), (second_keys, G1::default(), &specifiers[1])];
+        assert!(verifier::validate_multi_election_proof(&elections, &submission.election_proof));
+
+        assert!(MultiElectionSubmission::aggregate(vec![(first.clone(), first_proof), (first, first_proof)]).is_err());
+        assert!(MultiElectionSubmission::aggregate(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn submission_encodings() {
+        let (first, first_proof, _) = election(1, &[2, 3]);
+        let (second, second_proof, _) = election(7, &[4]);
+        let submission = MultiElectionSubmission::aggregate(vec![(first, first_proof), (second, second_proof)]).unwrap();
+        assert_eq!(MultiElectionSubmission::from_json(&submission.to_json()).unwrap(), submission);
+
+    }
+}
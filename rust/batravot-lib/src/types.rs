@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use web3::types::U256;
 
 /// Represents a single vote of a voter
 ///
@@ -9,7 +10,7 @@ use std::str::FromStr;
 /// let vote = Vote::For;
 /// ```
 ///
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Vote {
     For,
     Against,
@@ -27,4 +28,21 @@ impl FromStr for Vote {
             _ => Err(format!("Invalid vote: {}", s)),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Represents the result of an election
+/// It mirrors the `ElectionResult` struct of the `BatRaVot` contract
+/// The weighted votes are expressed in the units of the voting token
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElectionResult {
+    /// How many votes were possible
+    pub possible_votes: U256,
+    /// How many voters voted
+    pub total_voters: U256,
+    /// How many voters voted `For`
+    pub yes_voters: U256,
+    /// How many weighted votes were cast
+    pub total_votes: U256,
+    /// How many weighted votes were cast `For`
+    pub yes_votes: U256,
+}
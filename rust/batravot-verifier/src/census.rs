@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::fs;
+use web3::types::Address;
+use batravot_lib::G1;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+
+/// The census of the election, mirrors the `census` mapping of the contract
+/// It maps the Ethereum address of each registered voter to their voting public key
+pub(crate) struct Census {
+    keys: HashMap<Address, G1>,
+}
+
+impl Census {
+    /// The function that reads the census from a file
+    /// We assume that each registered voter is on a new line
+    /// And that the address and the public key are separated by a | character: `<address>|<public key>`
+    /// If an address is registered several times, the last public key is used, same as in the contract
+    pub(crate) fn from_file(file_path: &str) -> Result<Census, String> {
+        let content = fs::read_to_string(file_path)
+            .map_err(|err| format!("Error reading the census file: {}", err))?;
+
+        let mut keys = HashMap::new();
+        for (i, line) in content.lines().enumerate() {
+            // If the line is empty, skip it
+            if line.trim() == "" {
+                continue;
+            }
+
+            let (address_str, public_key_str) = line.split_once('|')
+                .ok_or(format!("Error parsing the census at line {}: expected `<address>|<public key>`", i + 1))?;
+            let address = Address::from_str_c(address_str)
+                .map_err(|err| format!("Error parsing the address at line {}: {}", i + 1, err))?;
+            let public_key = G1::from_str_c(public_key_str)
+                .map_err(|err| format!("Error parsing the public key at line {}: {}", i + 1, err))?;
+
+            keys.insert(address, public_key);
+        }
+
+        Ok(Census { keys })
+    }
+
+    /// This function looks up the public keys of the provided voters
+    /// Unlike the contract, which reads unknown voters as the zero point, we return an error
+    pub(crate) fn public_keys(&self, voters: &[Address]) -> Result<Vec<G1>, String> {
+        voters.iter()
+            .map(|voter| self.keys.get(voter).cloned()
+                .ok_or(format!("Voter {} is not registered in the census", voter.solidity_repr())))
+            .collect()
+    }
+}
+
+/// This function parses a list of voter addresses
+/// Accepts the format printed by the batcher, `[0x..., 0x...]`, as well as a plain comma separated list
+/// If the value starts with `@`, the rest is a path to a file containing the list
+pub(crate) fn parse_voter_list(value: &str) -> Result<Vec<Address>, String> {
+    let list = match value.strip_prefix('@') {
+        Some(file_path) => fs::read_to_string(file_path)
+            .map_err(|err| format!("Error reading the voter list {}: {}", file_path, err))?,
+        None => value.to_string(),
+    };
+
+    list.replace(['[', ']', '"', '\n', '\r'], "")
+        .split(',')
+        .filter(|address| address.trim() != "")
+        .map(|address| Address::from_str_c(address)
+            .map_err(|err| format!("Error parsing the voter address {}: {}", address.trim(), err)))
+        .collect()
+}
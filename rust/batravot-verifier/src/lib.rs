@@ -0,0 +1,825 @@
+mod census;
+
+use std::collections::HashSet;
+use std::fs;
+use ark_ec::ProjectiveCurve;
+use ark_ff::BigInteger256;
+use colored::Colorize;
+use serde_json::{json, Value};
+use web3::types::{Address, U256};
+use batravot_lib::{ApprovalSpecifiers, ElectionSpecifiers, G1, RankedSpecifiers, verifier};
+use batravot_lib::balances::BalanceSnapshot;
+use batravot_lib::ballot::check_weight;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use batravot_lib::submission::MultiElectionSubmission;
+use crate::census::{Census, parse_voter_list};
+
+/// The exit codes of the verifier application
+/// They allow scripts to tell apart a rejected batch from a batch that could not be checked
+pub const EXIT_VALID: i32 = 0;
+pub const EXIT_INVALID: i32 = 1;
+pub const EXIT_USAGE_ERROR: i32 = 2;
+pub const EXIT_INPUT_ERROR: i32 = 3;
+pub const EXIT_INCONSISTENT: i32 = 4;
+
+/// The main logic function, it re-checks a submitted batch against the census
+/// It returns an error if the inputs could not be read or parsed
+pub fn run(config: VerifierConfig) -> Result<Report, String> {
+    if let Some(submission_file) = &config.submission_file {
+        return run_submission(submission_file, &config.census_file);
+    }
+    if let Some(approval_file) = &config.approval_file {
+        if config.specifiers_file.is_some() {
+            return Err("The specifiers of an approval election are generated from its id, `--specifiers` can not be set with `--approval`".to_string());
+        }
+        return run_approval(approval_file, config.election_id, &config.census_file);
+    }
+    if let Some(ranked_file) = &config.ranked_file {
+        if config.specifiers_file.is_some() {
+            return Err("The specifiers of a ranked election are generated from its id, `--specifiers` can not be set with `--ranked`".to_string());
+        }
+        return run_ranked(ranked_file, config.election_id, &config.census_file);
+    }
+
+    // Get the election specifiers, either from the election id or from the file
+    // If both are provided, we check that the specifiers in the file were generated from the election id
+    let provided_specifiers = match &config.specifiers_file {
+        Some(file_path) => {
+            let specifiers_str = fs::read_to_string(file_path)
+                .map_err(|err| format!("Error reading the specifiers file: {}", err))?;
+            Some(ElectionSpecifiers::from_str_c(&specifiers_str)
+                .map_err(|err| format!("Error parsing the specifiers: {}", err))?)
+        }
+        None => None,
+    };
+    let (specifiers, specifiers_check) = match (config.election_id, provided_specifiers) {
+        (Some(election_id), Some(specifiers)) => {
+            let specifiers_check = specifiers.check_specifiers(BigInteger256::from(election_id));
+            (specifiers, Some(specifiers_check))
+        }
+        (Some(election_id), None) => (ElectionSpecifiers::from(election_id), None),
+        (None, Some(specifiers)) => (specifiers, None),
+        (None, None) => return Err("Either the election id or the specifiers file must be provided".to_string()),
+    };
+
+    // Read the census and find the public keys of those who voted
+    let census = Census::from_file(&config.census_file)?;
+    if let Some(batches_file) = &config.batches_file {
+        let balances = match &config.balances_file {
+            Some(balances_file) => Some(BalanceSnapshot::from_file(balances_file)?),
+            None => None,
+        };
+        return run_batches(batches_file, config.election_id, &specifiers, specifiers_check, &census, balances.as_ref());
+    }
+    if config.balances_file.is_some() {
+        return Err("The balances only bound the weights of the weighted batches, `--balances` can only be set with `--batches`".to_string());
+    }
+    let for_voters = parse_voter_list(&config.for_voters)?;
+    let against_voters = parse_voter_list(&config.against_voters)?;
+    if for_voters.is_empty() && against_voters.is_empty() {
+        return Err("Can not verify a proof for no voters".to_string());
+    }
+    let for_keys = census.public_keys(&for_voters)?;
+    let against_keys = census.public_keys(&against_voters)?;
+
+    let proof = parse_proof(&config.proof)?;
+
+    // Run both the pairing check and the check the EVM does
+    let pairing_check = verifier::validate_election_proof(
+        &for_keys.iter().collect(),
+        &against_keys.iter().collect(),
+        &proof,
+        &specifiers
+    );
+    let evm_check = verifier::validate_election_proof_evm(&for_keys, &against_keys, &proof, &specifiers);
+
+    Ok(Report {
+        election_id: config.election_id,
+        elections: None,
+        batches: None,
+        invalid_batches: Vec::new(),
+        approvals: None,
+        first_choices: None,
+        for_voters: for_voters.len(),
+        against_voters: against_voters.len(),
+        specifiers_check,
+        pairing_check,
+        evm_check: Some(evm_check),
+    })
+}
+
+/// This function re-checks many batches of the same election against the census, with one randomized pairing check
+/// The batches are in the format of the `--output` file of the batcher, one per line
+/// If the combined check fails, the invalid batches are found by bisection and reported by their line
+/// The proof of a weighted batch does not prove its weights, as anyone can scale a weighted vote proof and its weight together
+/// So the weights are only trusted up to the balances of the voters, a weighted batch can not be checked without them
+fn run_batches(batches_file: &str, election_id: Option<u64>, specifiers: &ElectionSpecifiers, specifiers_check: Option<bool>,
+               census: &Census, balances: Option<&BalanceSnapshot>) -> Result<Report, String> {
+    let content = fs::read_to_string(batches_file)
+        .map_err(|err| format!("Error reading the batches file: {}", err))?;
+
+    let mut lines = Vec::new();
+    let mut keys = Vec::new();
+    let mut proofs = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim() == "" {
+            continue;
+        }
+        let batch = parse_batch(line)
+            .map_err(|err| format!("Error parsing the batch at line {}: {}", i + 1, err))?;
+        if let (Some(election_id), Some(batch_election_id)) = (election_id, batch.election_id) {
+            if election_id != batch_election_id {
+                return Err(format!("The batch at line {} is for the election {}, not for the election {}", i + 1, batch_election_id, election_id));
+            }
+        }
+        lines.push(i + 1);
+        let (mut for_keys, mut against_keys) = (census.public_keys(&batch.for_voters)?, census.public_keys(&batch.against_voters)?);
+        // The proof of a weighted batch is checked against the public keys scaled by the weights
+        if let Some((for_weights, against_weights)) = &batch.weights {
+            let balances = balances.ok_or(format!("The batch at line {} is weighted, `--balances` must be set to check its weights against the balances of the voters", i + 1))?;
+            let voters = batch.for_voters.iter().zip(for_weights).chain(batch.against_voters.iter().zip(against_weights));
+            for (voter, weight) in voters {
+                let balance = balances.balance_of(voter);
+                if U256::from(*weight) > balance {
+                    return Err(format!("The weight {} of the voter {} in the batch at line {} is higher than their balance {}", weight, voter.solidity_repr(), i + 1, balance));
+                }
+            }
+            for_keys = for_keys.iter().zip(for_weights).map(|(key, weight)| verifier::weighted_public_key(key, *weight)).collect();
+            against_keys = against_keys.iter().zip(against_weights).map(|(key, weight)| verifier::weighted_public_key(key, *weight)).collect();
+        }
+        keys.push((for_keys, against_keys));
+        proofs.push(batch.proof);
+    }
+    if lines.is_empty() {
+        return Err("Can not verify a proof for no batches".to_string());
+    }
+
+    let batches: Vec<(&[G1], &[G1], &G1)> = keys.iter().zip(proofs.iter())
+        .map(|((for_keys, against_keys), proof)| (for_keys.as_slice(), against_keys.as_slice(), proof))
+        .collect();
+    let valid = verifier::validate_election_proofs(&batches, specifiers, &mut rand::thread_rng());
+    let invalid_batches: Vec<usize> = lines.iter().zip(valid.iter())
+        .filter(|(_, valid)| !**valid)
+        .map(|(line, _)| *line)
+        .collect();
+
+    Ok(Report {
+        election_id,
+        elections: None,
+        batches: Some(batches.len()),
+        approvals: None,
+        first_choices: None,
+        for_voters: keys.iter().map(|(for_keys, _)| for_keys.len()).sum(),
+        against_voters: keys.iter().map(|(_, against_keys)| against_keys.len()).sum(),
+        specifiers_check,
+        pairing_check: invalid_batches.is_empty(),
+        invalid_batches,
+        evm_check: None,
+    })
+}
+
+/// A batch written by the batcher, the election id is optional
+/// The weights of the `For` and the `Against` voters are set if the batch has weighted ballots
+struct Batch {
+    election_id: Option<u64>,
+    for_voters: Vec<Address>,
+    against_voters: Vec<Address>,
+    weights: Option<(Vec<u64>, Vec<u64>)>,
+    proof: G1,
+}
+
+/// This function parses a batch written by the batcher, `{"electionId": ..., "votersFor": [...], "votersAgainst": [...], "electionProof": ...}`
+/// A weighted batch also has the `weightsFor` and `weightsAgainst` fields, with a weight for each voter
+fn parse_batch(line: &str) -> Result<Batch, String> {
+    let batch: Value = serde_json::from_str(line).map_err(|err| err.to_string())?;
+    let voters = |field: &str| -> Result<Vec<Address>, String> {
+        batch[field].as_array().ok_or(format!("The {} field is missing", field))?.iter()
+            .map(|address| address.as_str().ok_or(format!("The {} field must contain strings", field))
+                .and_then(|address| Address::from_str_c(address).map_err(|err| format!("Error parsing the voter address {}: {}", address, err))))
+            .collect()
+    };
+    let weights = |field: &str, voters: &[Address]| -> Result<Vec<u64>, String> {
+        let weights: Vec<u64> = batch[field].as_array().ok_or(format!("The {} field is missing", field))?.iter()
+            .map(|weight| weight.as_u64().ok_or(format!("The {} field must contain numbers", field)).and_then(check_weight))
+            .collect::<Result<_, String>>()?;
+        if weights.len() != voters.len() {
+            return Err(format!("The {} field has {} weights for {} voters", field, weights.len(), voters.len()));
+        }
+        Ok(weights)
+    };
+    let proof = parse_proof(batch["electionProof"].as_str().ok_or("The electionProof field is missing")?)?;
+    let (for_voters, against_voters) = (voters("votersFor")?, voters("votersAgainst")?);
+    let weights = match batch.get("weightsFor").is_some() || batch.get("weightsAgainst").is_some() {
+        true => Some((weights("weightsFor", &for_voters)?, weights("weightsAgainst", &against_voters)?)),
+        false => None,
+    };
+    Ok(Batch {
+        election_id: batch["electionId"].as_u64(),
+        for_voters,
+        against_voters,
+        weights,
+        proof,
+    })
+}
+
+/// This function re-checks a submission of several elections against the census
+/// The specifiers of each election are generated from its id, and the key sums of all the elections are checked with one pairing product
+/// There is no EVM check, as the contract does not verify several elections at once
+fn run_submission(submission_file: &str, census_file: &str) -> Result<Report, String> {
+    let submission_str = fs::read_to_string(submission_file)
+        .map_err(|err| format!("Error reading the submission file: {}", err))?;
+    let submission_json = serde_json::from_str(&submission_str)
+        .map_err(|err| format!("Error parsing the submission file: {}", err))?;
+    let submission = MultiElectionSubmission::from_json(&submission_json)?;
+    if submission.elections.is_empty() {
+        return Err("Can not verify a proof for no elections".to_string());
+    }
+
+    let census = Census::from_file(census_file)?;
+    let specifiers: Vec<ElectionSpecifiers> = submission.elections.iter()
+        .map(|election| ElectionSpecifiers::from(election.election_id))
+        .collect();
+    let mut key_sums = Vec::with_capacity(submission.elections.len());
+    for (election, specifiers) in submission.elections.iter().zip(specifiers.iter()) {
+        let for_keys = census.public_keys(&election.voters_for)?;
+        let against_keys = census.public_keys(&election.voters_against)?;
+        key_sums.push((for_keys.iter().sum(), against_keys.iter().sum(), specifiers));
+    }
+
+    Ok(Report {
+        election_id: None,
+        elections: Some(submission.elections.len()),
+        batches: None,
+        invalid_batches: Vec::new(),
+        approvals: None,
+        first_choices: None,
+        for_voters: submission.elections.iter().map(|election| election.voters_for.len()).sum(),
+        against_voters: submission.elections.iter().map(|election| election.voters_against.len()).sum(),
+        specifiers_check: None,
+        pairing_check: verifier::validate_multi_election_proof(&key_sums, &submission.election_proof),
+        evm_check: None,
+    })
+}
+
+/// This function re-checks the batch of an approval election against the census
+/// The batch is in the format of the `--output` file of the batcher with `--options`, `{"electionId": ..., "votersByOption": [[...], ...], "electionProof": ...}`
+/// The specifiers of the options are generated from the election id, the proof is checked against the key sum of each option
+/// A voter approves an option at most once, an address that is twice in the voters of an option would count its approval twice
+/// There is no EVM check, as the contract does not verify approval elections
+fn run_approval(approval_file: &str, election_id: Option<u64>, census_file: &str) -> Result<Report, String> {
+    let (election_id, batch, proof) = read_options_batch(approval_file, "approval", election_id)?;
+    let voters_by_option = parse_voters_by_option(&batch["votersByOption"], "votersByOption")?;
+    let specifiers = ApprovalSpecifiers::from(election_id, voters_by_option.len())?;
+    for (option, voters) in voters_by_option.iter().enumerate() {
+        let mut seen = HashSet::new();
+        if let Some(voter) = voters.iter().find(|voter| !seen.insert(*voter)) {
+            return Err(format!("The voter {} approves the option {} more than once", voter.solidity_repr(), option));
+        }
+    }
+
+    let census = Census::from_file(census_file)?;
+    let option_keys = voters_by_option.iter()
+        .map(|voters| census.public_keys(voters))
+        .collect::<Result<Vec<Vec<G1>>, String>>()?;
+    let option_keys: Vec<Vec<&G1>> = option_keys.iter().map(|keys| keys.iter().collect()).collect();
+
+    Ok(Report {
+        election_id: Some(election_id),
+        elections: None,
+        batches: None,
+        invalid_batches: Vec::new(),
+        approvals: Some(voters_by_option.iter().map(|voters| voters.len()).collect()),
+        first_choices: None,
+        for_voters: 0,
+        against_voters: 0,
+        specifiers_check: None,
+        pairing_check: verifier::validate_approval_election_proof(&option_keys, &proof, &specifiers),
+        evm_check: None,
+    })
+}
+
+/// This function re-checks the batch of a ranked election against the census
+/// The batch is in the format of the `--output` file of the batcher with `--ranked`, `{"electionId": ..., "votersByRank": [[[...], ...], ...], "electionProof": ...}`
+/// The specifiers of the options at each rank are generated from the election id, the proof is checked against the key sum of each option at each rank
+/// Like in a ranked ballot, a voter uses each rank at most once and ranks each option at most once
+/// There is no EVM check, as the contract does not verify ranked elections
+fn run_ranked(ranked_file: &str, election_id: Option<u64>, census_file: &str) -> Result<Report, String> {
+    let (election_id, batch, proof) = read_options_batch(ranked_file, "ranked", election_id)?;
+    let voters_by_rank = batch["votersByRank"].as_array().ok_or("The votersByRank field is missing".to_string())?.iter()
+        .map(|voters_by_option| parse_voters_by_option(voters_by_option, "votersByRank"))
+        .collect::<Result<Vec<Vec<Vec<Address>>>, String>>()?;
+    let specifiers = RankedSpecifiers::from(election_id, voters_by_rank.len())?;
+    if voters_by_rank.iter().any(|voters_by_option| voters_by_option.len() != voters_by_rank.len()) {
+        return Err(format!("Each rank of the votersByRank field must have the voters of the {} options", voters_by_rank.len()));
+    }
+    for (rank, voters_by_option) in voters_by_rank.iter().enumerate() {
+        let mut seen = HashSet::new();
+        if let Some(voter) = voters_by_option.iter().flatten().find(|voter| !seen.insert(*voter)) {
+            return Err(format!("The voter {} uses the rank {} more than once", voter.solidity_repr(), rank + 1));
+        }
+    }
+    for option in 0..voters_by_rank.len() {
+        let mut seen = HashSet::new();
+        if let Some(voter) = voters_by_rank.iter().flat_map(|voters_by_option| voters_by_option[option].iter()).find(|voter| !seen.insert(*voter)) {
+            return Err(format!("The voter {} ranks the option {} more than once", voter.solidity_repr(), option));
+        }
+    }
+
+    let census = Census::from_file(census_file)?;
+    let rank_keys = voters_by_rank.iter()
+        .map(|voters_by_option| voters_by_option.iter().map(|voters| census.public_keys(voters)).collect::<Result<Vec<Vec<G1>>, String>>())
+        .collect::<Result<Vec<Vec<Vec<G1>>>, String>>()?;
+    let rank_keys: Vec<Vec<Vec<&G1>>> = rank_keys.iter()
+        .map(|option_keys| option_keys.iter().map(|keys| keys.iter().collect()).collect())
+        .collect();
+
+    Ok(Report {
+        election_id: Some(election_id),
+        elections: None,
+        batches: None,
+        invalid_batches: Vec::new(),
+        approvals: None,
+        first_choices: Some(voters_by_rank[0].iter().map(|voters| voters.len()).collect()),
+        for_voters: 0,
+        against_voters: 0,
+        specifiers_check: None,
+        pairing_check: verifier::validate_ranked_election_proof(&rank_keys, &proof, &specifiers),
+        evm_check: None,
+    })
+}
+
+/// This function reads the batch of an approval or a ranked election, with its election id and its election proof
+/// The election id is the one of the batch, or the provided one, if both are set they must be the same
+/// kind - The kind of the election, for the error messages
+fn read_options_batch(file_path: &str, kind: &str, election_id: Option<u64>) -> Result<(u64, Value, G1), String> {
+    let batch_str = fs::read_to_string(file_path)
+        .map_err(|err| format!("Error reading the {} batch file: {}", kind, err))?;
+    let batch: Value = serde_json::from_str(&batch_str)
+        .map_err(|err| format!("Error parsing the {} batch file: {}", kind, err))?;
+
+    let batch_election_id = batch["electionId"].as_u64();
+    let election_id = match (election_id, batch_election_id) {
+        (Some(election_id), Some(batch_election_id)) if election_id != batch_election_id => {
+            return Err(format!("The {} batch is for the election {}, not for the election {}", kind, batch_election_id, election_id));
+        }
+        (Some(election_id), _) | (None, Some(election_id)) => election_id,
+        (None, None) => return Err(format!("The {} batch has no electionId field, `--election-id` must be provided", kind)),
+    };
+    let proof = parse_proof(batch["electionProof"].as_str().ok_or("The electionProof field is missing")?)?;
+    Ok((election_id, batch, proof))
+}
+
+/// This function parses an election proof, the proof must be a point of the curve in the prime subgroup
+/// The pairing product is only defined for such points, so any other point is rejected before it is checked
+fn parse_proof(raw_proof: &str) -> Result<G1, String> {
+    let proof = G1::from_str_c(raw_proof)
+        .map_err(|err| format!("Error parsing the election proof: {}", err))?;
+    let affine_proof = proof.into_affine();
+    if !affine_proof.is_on_curve() || !affine_proof.is_in_correct_subgroup_assuming_on_curve() {
+        return Err("The election proof is not a point of the prime subgroup of the curve".to_string());
+    }
+    Ok(proof)
+}
+
+/// This function parses the lists of the voters of each option, e.g. the `votersByOption` field of an approval batch
+fn parse_voters_by_option(value: &Value, field: &str) -> Result<Vec<Vec<Address>>, String> {
+    value.as_array().ok_or(format!("The {} field is missing", field))?.iter()
+        .map(|voters| parse_voter_list(&voters.to_string()))
+        .collect()
+}
+
+/// The inputs of the verifier application
+/// election_id - The id of the election, used to generate the specifiers
+/// specifiers_file - The file with the specifiers in their Solidity representation
+/// census_file - The file with the registered voters, one `<address>|<public key>` per line
+/// for_voters - The list of voters who voted `For`, or `@<file>` with the list
+/// against_voters - The list of voters who voted `Against`, or `@<file>` with the list
+/// proof - The aggregated election proof
+/// submission_file - The file with a submission of several elections, as written by the batcher, instead of the voters and the proof
+/// batches_file - The file with many batches of the election, one per line, instead of the voters and the proof
+/// approval_file - The file with the batch of an approval election, instead of the voters and the proof
+/// ranked_file - The file with the batch of a ranked election, instead of the voters and the proof
+/// balances_file - The file with the token balances of the voters, in CSV or JSON, each weight of a weighted batch must not be higher than the balance
+pub struct VerifierConfig {
+    pub election_id: Option<u64>,
+    pub specifiers_file: Option<String>,
+    pub census_file: String,
+    pub for_voters: String,
+    pub against_voters: String,
+    pub proof: String,
+    pub submission_file: Option<String>,
+    pub batches_file: Option<String>,
+    pub approval_file: Option<String>,
+    pub ranked_file: Option<String>,
+    pub balances_file: Option<String>,
+}
+
+/// The outcome of the verification
+/// elections - The number of elections of the submission, if a submission of several elections was checked
+/// batches - The number of batches, if many batches were checked together
+/// invalid_batches - The lines of the batches that failed the check
+/// approvals - The number of voters who approved each option, if the batch of an approval election was checked
+/// first_choices - The number of voters who ranked each option first, if the batch of a ranked election was checked
+/// specifiers_check - Whether the specifiers were generated from the election id, if both were provided
+/// pairing_check - The result of `validate_election_proof`
+/// evm_check - The result of `validate_election_proof_evm`, not available for a submission of several elections
+pub struct Report {
+    pub election_id: Option<u64>,
+    pub elections: Option<usize>,
+    pub batches: Option<usize>,
+    pub invalid_batches: Vec<usize>,
+    pub approvals: Option<Vec<usize>>,
+    pub first_choices: Option<Vec<usize>>,
+    pub for_voters: usize,
+    pub against_voters: usize,
+    pub specifiers_check: Option<bool>,
+    pub pairing_check: bool,
+    pub evm_check: Option<bool>,
+}
+
+impl Report {
+    /// The two proof checks must always agree, if they do not, something is wrong with the verifier itself
+    pub fn exit_code(&self) -> i32 {
+        if self.evm_check.is_some_and(|evm_check| evm_check != self.pairing_check) {
+            EXIT_INCONSISTENT
+        } else if self.pairing_check && self.specifiers_check != Some(false) {
+            EXIT_VALID
+        } else {
+            EXIT_INVALID
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.exit_code() {
+            EXIT_VALID => "valid",
+            EXIT_INCONSISTENT => "inconsistent",
+            _ => "invalid",
+        }
+    }
+
+    /// Prints the report in the human readable format
+    pub fn print(&self) {
+        let passed = |check: bool| if check { "passed".green() } else { "failed".red() };
+
+        println!("----------------------------------------");
+        if let Some(election_id) = self.election_id {
+            println!("Election ID:       {}", election_id);
+        }
+        if let Some(elections) = self.elections {
+            println!("Elections:         {}", elections);
+        }
+        if let Some(batches) = self.batches {
+            println!("Batches:           {}", batches);
+        }
+        match (&self.approvals, &self.first_choices) {
+            (Some(approvals), _) => println!("Approvals:         {:?}", approvals),
+            (_, Some(first_choices)) => println!("First choices:     {:?}", first_choices),
+            (None, None) => {
+                println!("Voters for:        {}", self.for_voters);
+                println!("Voters against:    {}", self.against_voters);
+            }
+        }
+        if let Some(specifiers_check) = self.specifiers_check {
+            println!("Specifiers check:  {}", passed(specifiers_check));
+        }
+        println!("Pairing check:     {}", passed(self.pairing_check));
+        if let Some(evm_check) = self.evm_check {
+            println!("EVM check:         {}", passed(evm_check));
+        }
+        if !self.invalid_batches.is_empty() {
+            println!("Invalid batches:   {}", format!("lines {:?}", self.invalid_batches).red());
+        }
+        println!("Result:            {}", self.status().to_uppercase());
+        println!("----------------------------------------");
+    }
+
+    /// Prints the report as a single line of JSON
+    pub fn print_json(&self) {
+        println!("{}", json!({
+            "status": self.status(),
+            "electionId": self.election_id,
+            "elections": self.elections,
+            "batches": self.batches,
+            "invalidBatches": self.invalid_batches,
+            "approvals": self.approvals,
+            "firstChoices": self.first_choices,
+            "forVoters": self.for_voters,
+            "againstVoters": self.against_voters,
+            "specifiersCheck": self.specifiers_check,
+            "pairingCheck": self.pairing_check,
+            "evmCheck": self.evm_check,
+        }));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use web3::types::Address;
+    use batravot_lib::{batcher, ScalarField, voter};
+    use std::path::Path;
+    use super::*;
+
+    /// Writes a census of three voters in the directory, the first two voting `For` and the last one `Against`
+    /// Returns the config to verify their batch
+    fn setup(dir: &Path) -> VerifierConfig {
+        let specifiers = ElectionSpecifiers::from(7);
+        let mut census = String::new();
+        let mut vote_proofs = Vec::new();
+        for i in 1..=3u64 {
+            let prk = ScalarField::from(i * 1000 + 1);
+            census.push_str(&format!("{}|{}\n", Address::from_low_u64_be(i).solidity_repr(), voter::generate_public_key(&prk).solidity_repr()));
+            let specifier = if i < 3 { &specifiers.forr.0 } else { &specifiers.against.0 };
+            vote_proofs.push(voter::generate_vote_proof(&prk, specifier));
+        }
+        let proof = batcher::generate_batched_election_proof(&vote_proofs.iter().collect());
+
+        let census_file = dir.join("census.txt");
+        fs::write(&census_file, census).unwrap();
+
+        VerifierConfig {
+            election_id: Some(7),
+            specifiers_file: None,
+            census_file: census_file.to_string_lossy().to_string(),
+            for_voters: format!("[{}, {}]", Address::from_low_u64_be(1).solidity_repr(), Address::from_low_u64_be(2).solidity_repr()),
+            against_voters: Address::from_low_u64_be(3).solidity_repr(),
+            proof: proof.solidity_repr(),
+            submission_file: None,
+            batches_file: None,
+            approval_file: None,
+            ranked_file: None,
+            balances_file: None,
+        }
+    }
+
+    #[test]
+    fn accepts_a_valid_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = run(setup(dir.path())).unwrap();
+        assert!(report.pairing_check && report.evm_check == Some(true));
+        assert_eq!(report.exit_code(), EXIT_VALID);
+    }
+
+    #[test]
+    fn rejects_a_batch_with_swapped_votes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        std::mem::swap(&mut config.for_voters, &mut config.against_voters);
+        let report = run(config).unwrap();
+        assert_eq!(report.exit_code(), EXIT_INVALID);
+    }
+
+    #[test]
+    fn rejects_specifiers_of_another_election() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        let specifiers_file = dir.path().join("specifiers.txt");
+        fs::write(&specifiers_file, ElectionSpecifiers::from(8).solidity_repr()).unwrap();
+        config.specifiers_file = Some(specifiers_file.to_string_lossy().to_string());
+
+        let report = run(config).unwrap();
+        assert_eq!(report.specifiers_check, Some(false));
+        assert_eq!(report.exit_code(), EXIT_INVALID);
+    }
+
+    #[test]
+    fn fails_on_unknown_voter() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        config.against_voters = Address::from_low_u64_be(4).solidity_repr();
+        assert!(run(config).is_err());
+    }
+
+    #[test]
+    fn checks_a_submission_of_several_elections() {
+        let dir = tempfile::tempdir().unwrap();
+        use batravot_lib::submission::ElectionVotes;
+
+        // The census of `setup` with the voters of the election 7, and the election 9 in which the first voter votes `Against`
+        let mut config = setup(dir.path());
+        let specifiers = ElectionSpecifiers::from(9);
+        let proof = voter::generate_vote_proof(&ScalarField::from(1001), &specifiers.against.0);
+        let elections = vec![
+            (ElectionVotes { election_id: 7, voters_for: vec![Address::from_low_u64_be(1), Address::from_low_u64_be(2)], voters_against: vec![Address::from_low_u64_be(3)] },
+                G1::from_str_c(&config.proof).unwrap()),
+            (ElectionVotes { election_id: 9, voters_for: Vec::new(), voters_against: vec![Address::from_low_u64_be(1)] }, proof),
+        ];
+        let mut submission = MultiElectionSubmission::aggregate(elections).unwrap();
+        let submission_file = dir.path().join("submission.json");
+        fs::write(&submission_file, submission.to_json().to_string()).unwrap();
+        config.submission_file = Some(submission_file.to_string_lossy().to_string());
+
+        let report = run(config).unwrap();
+        assert_eq!((report.elections, report.for_voters, report.against_voters), (Some(2), 2, 2));
+        assert_eq!(report.exit_code(), EXIT_VALID);
+
+        // The vote of the election 9 is claimed to be `For`
+        let mut config = setup(dir.path());
+        let votes = &mut submission.elections[1];
+        std::mem::swap(&mut votes.voters_for, &mut votes.voters_against);
+        fs::write(&submission_file, submission.to_json().to_string()).unwrap();
+        config.submission_file = Some(submission_file.to_string_lossy().to_string());
+        assert_eq!(run(config).unwrap().exit_code(), EXIT_INVALID);
+    }
+
+    #[test]
+    fn finds_the_invalid_batches_of_a_batches_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        let batch = |for_voters: &str, against_voters: &str| json!({
+            "electionId": 7,
+            "votersFor": parse_voter_list(for_voters).unwrap().iter().map(|address| address.solidity_repr()).collect::<Vec<_>>(),
+            "votersAgainst": parse_voter_list(against_voters).unwrap().iter().map(|address| address.solidity_repr()).collect::<Vec<_>>(),
+            "electionProof": config.proof,
+        }).to_string();
+
+        // The batch at line 3 has the votes swapped
+        let lines = [
+            batch(&config.for_voters, &config.against_voters),
+            batch(&config.for_voters, &config.against_voters),
+            batch(&config.against_voters, &config.for_voters),
+            String::new(),
+            batch(&config.for_voters, &config.against_voters),
+        ];
+        let batches_file = dir.path().join("batches.jsonl");
+        fs::write(&batches_file, lines.join("\n")).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+
+        let report = run(config).unwrap();
+        assert_eq!((report.batches, report.for_voters, report.against_voters), (Some(4), 7, 5));
+        assert_eq!(report.invalid_batches, vec![3]);
+        assert_eq!(report.exit_code(), EXIT_INVALID);
+    }
+
+    #[test]
+    fn rejects_a_proof_off_the_curve() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        let off_curve = "[0x0000000000000000000000000000000000000000000000000000000000000001,0x0000000000000000000000000000000000000000000000000000000000000003]";
+        let batch = json!({
+            "electionId": 7,
+            "votersFor": [Address::from_low_u64_be(1).solidity_repr()],
+            "votersAgainst": [],
+            "electionProof": off_curve,
+        });
+        let batches_file = dir.path().join("batches.jsonl");
+        fs::write(&batches_file, batch.to_string()).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        let err = run(config).err().unwrap();
+        assert!(err.contains("The election proof is not a point of the prime subgroup of the curve"), "{}", err);
+
+        let mut config = setup(dir.path());
+        config.proof = off_curve.to_string();
+        assert_eq!(run(config).err(), Some("The election proof is not a point of the prime subgroup of the curve".to_string()));
+    }
+
+    #[test]
+    fn checks_the_weights_of_a_weighted_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        let specifiers = ElectionSpecifiers::from(7);
+        let proof = voter::generate_weighted_vote_proof(&ScalarField::from(1001), &specifiers.forr.0, 25)
+            + voter::generate_vote_proof(&ScalarField::from(2001), &specifiers.forr.0)
+            + voter::generate_weighted_vote_proof(&ScalarField::from(3001), &specifiers.against.0, 4);
+        let batch = |for_weights: Vec<u64>| json!({
+            "votersFor": [Address::from_low_u64_be(1).solidity_repr(), Address::from_low_u64_be(2).solidity_repr()],
+            "votersAgainst": [Address::from_low_u64_be(3).solidity_repr()],
+            "weightsFor": for_weights,
+            "weightsAgainst": [4],
+            "electionProof": proof.solidity_repr(),
+        }).to_string();
+
+        let balances_file = dir.path().join("balances.csv");
+        let balances = |balance: u64| (1..=3u64).map(|i| format!("{},{}\n", Address::from_low_u64_be(i).solidity_repr(), balance)).collect::<String>();
+        fs::write(&balances_file, balances(30)).unwrap();
+        let balances_file = balances_file.to_string_lossy().to_string();
+
+        // The batch at line 2 claims a higher weight than the one its proof is scaled by
+        let batches_file = dir.path().join("weighted.jsonl");
+        fs::write(&batches_file, [batch(vec![25, 1]), batch(vec![26, 1])].join("\n")).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        config.balances_file = Some(balances_file.clone());
+        let report = run(config).unwrap();
+        assert_eq!(report.invalid_batches, vec![2]);
+
+        // The proof holds with the weights scaled together, so they are only trusted up to the balances
+        let scaled = json!({
+            "votersFor": [Address::from_low_u64_be(1).solidity_repr()],
+            "votersAgainst": [],
+            "weightsFor": [50],
+            "weightsAgainst": [],
+            "electionProof": voter::generate_weighted_vote_proof(&ScalarField::from(1001), &specifiers.forr.0, 25).double().solidity_repr(),
+        }).to_string();
+        let mut config = setup(dir.path());
+        fs::write(&batches_file, &scaled).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        config.balances_file = Some(balances_file.clone());
+        let err = run(config).err().unwrap();
+        assert!(err.contains("is higher than their balance 30"), "{}", err);
+
+        // A weighted batch can not be checked without the balances
+        let mut config = setup(dir.path());
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        assert!(run(config).err().unwrap().contains("`--balances` must be set"));
+
+        // Each voter must have a weight
+        let mut config = setup(dir.path());
+        fs::write(&batches_file, batch(vec![25])).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        assert!(run(config).is_err());
+
+        // A zero weight would make the key of the voter vanish from the pairing product
+        let mut config = setup(dir.path());
+        fs::write(&batches_file, batch(vec![25, 0])).unwrap();
+        config.batches_file = Some(batches_file.to_string_lossy().to_string());
+        let err = run(config).err().unwrap();
+        assert!(err.contains("The weight of a weighted ballot must be positive"), "{}", err);
+    }
+
+    #[test]
+    fn checks_an_approval_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = setup(dir.path());
+        let specifiers = ApprovalSpecifiers::from(7, 3).unwrap();
+        let selections: [&[usize]; 3] = [&[0, 1], &[1], &[2]];
+        let proof: G1 = (1..=3u64).zip(selections.iter())
+            .map(|(i, selection)| voter::generate_approval_vote_proof(&ScalarField::from(i * 1000 + 1), &specifiers, selection).unwrap())
+            .sum();
+        let address = |i: u64| Address::from_low_u64_be(i).solidity_repr();
+
+        let approval_file = dir.path().join("approval.json");
+        config.approval_file = Some(approval_file.to_string_lossy().to_string());
+        let batch = |voters_by_option: Value| json!({ "electionId": 7, "votersByOption": voters_by_option, "electionProof": proof.solidity_repr() });
+        fs::write(&approval_file, batch(json!([[address(1)], [address(1), address(2)], [address(3)]])).to_string()).unwrap();
+        let report = run(config).unwrap();
+        assert_eq!((report.exit_code(), report.approvals), (EXIT_VALID, Some(vec![1, 2, 1])));
+
+        // The approvals of the voter 2 are moved to another option
+        let mut config = setup(dir.path());
+        config.approval_file = Some(approval_file.to_string_lossy().to_string());
+        fs::write(&approval_file, batch(json!([[address(1)], [address(1)], [address(2), address(3)]])).to_string()).unwrap();
+        assert_eq!(run(config).unwrap().exit_code(), EXIT_INVALID);
+
+        // A voter can not approve an option twice
+        let mut config = setup(dir.path());
+        config.approval_file = Some(approval_file.to_string_lossy().to_string());
+        fs::write(&approval_file, batch(json!([[address(1)], [address(1), address(2), address(1)], [address(3)]])).to_string()).unwrap();
+        assert_eq!(run(config).err(), Some(format!("The voter {} approves the option 1 more than once", address(1))));
+    }
+
+    #[test]
+    fn checks_a_ranked_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let specifiers = RankedSpecifiers::from(7, 2).unwrap();
+        let rankings: [&[usize]; 3] = [&[1, 0], &[1], &[0, 1]];
+        let proof: G1 = (1..=3u64).zip(rankings.iter())
+            .map(|(i, ranking)| voter::generate_ranked_vote_proof(&ScalarField::from(i * 1000 + 1), &specifiers, ranking).unwrap())
+            .sum();
+        let address = |i: u64| Address::from_low_u64_be(i).solidity_repr();
+        let ranked_file = dir.path().join("ranked.json");
+        let check = |voters_by_rank: Value| {
+            fs::write(&ranked_file, json!({ "electionId": 7, "votersByRank": voters_by_rank, "electionProof": proof.solidity_repr() }).to_string()).unwrap();
+            let mut config = setup(dir.path());
+            config.ranked_file = Some(ranked_file.to_string_lossy().to_string());
+            run(config)
+        };
+
+        let report = check(json!([[[address(3)], [address(1), address(2)]], [[address(1)], [address(3)]]])).unwrap();
+        assert_eq!((report.exit_code(), report.first_choices), (EXIT_VALID, Some(vec![1, 2])));
+        // The ranks of the voter 1 are swapped
+        assert_eq!(check(json!([[[address(1), address(3)], [address(2)]], [[], [address(1), address(3)]]])).unwrap().exit_code(), EXIT_INVALID);
+        assert!(check(json!([[[address(3)], [address(1), address(2)]]])).is_err());
+    }
+
+    #[test]
+    fn rejects_a_voter_twice_in_a_ranked_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let specifiers = RankedSpecifiers::from(7, 2).unwrap();
+        let proof = voter::generate_ranked_vote_proof(&ScalarField::from(1001), &specifiers, &[1, 0]).unwrap()
+            + voter::generate_ranked_vote_proof(&ScalarField::from(2001), &specifiers, &[0]).unwrap();
+        let address = |i: u64| Address::from_low_u64_be(i).solidity_repr();
+        let ranked_file = dir.path().join("ranked.json");
+        let run_ranked_batch = |voters_by_rank: Value| {
+            fs::write(&ranked_file, json!({ "electionId": 7, "votersByRank": voters_by_rank, "electionProof": proof.solidity_repr() }).to_string()).unwrap();
+            run(VerifierConfig { election_id: None, ranked_file: Some(ranked_file.to_string_lossy().to_string()), ..setup(dir.path()) })
+        };
+
+        let report = run_ranked_batch(json!([[[address(2)], [address(1)]], [[address(1)], []]])).unwrap();
+        assert!(report.pairing_check);
+        assert_eq!(report.first_choices, Some(vec![1, 1]));
+
+        // The voter 1 is twice at the first rank, either for two options or twice for the same option
+        let err = run_ranked_batch(json!([[[address(2), address(1)], [address(1)]], [[], []]])).err().unwrap();
+        assert_eq!(err, format!("The voter {} uses the rank 1 more than once", address(1)));
+        let err = run_ranked_batch(json!([[[address(2)], [address(1), address(1)]], [[], []]])).err().unwrap();
+        assert_eq!(err, format!("The voter {} uses the rank 1 more than once", address(1)));
+
+        // The voter 1 ranks the option 1 first and second
+        let err = run_ranked_batch(json!([[[address(2)], [address(1)]], [[], [address(1)]]])).err().unwrap();
+        assert_eq!(err, format!("The voter {} ranks the option 1 more than once", address(1)));
+
+        // The number of options is checked by the specifiers
+        let err = run_ranked_batch(json!([])).err().unwrap();
+        assert!(err.contains("An election must have between 1 and"), "{}", err);
+    }
+}
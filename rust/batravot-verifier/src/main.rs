@@ -0,0 +1,89 @@
+use std::process;
+use serde_json::json;
+use batravot_verifier::{EXIT_INPUT_ERROR, EXIT_USAGE_ERROR, run, VerifierConfig};
+
+/// This is the main function that initiates the Verifier application
+/// It exits with one of the exit codes defined in the library, so that scripts can rely on them
+fn main() {
+
+    let args: Vec<String> = std::env::args().collect();
+    let json = args.iter().any(|arg| arg == "--json");
+
+    let election_id = flag_value(&args, "--election-id").map(|election_id| {
+        election_id.trim().parse::<u64>().unwrap_or_else(|err| {
+            usage_error(&format!("There was an error parsing the Election Id: {}", err))
+        })
+    });
+    let specifiers_file = flag_value(&args, "--specifiers");
+    let submission_file = flag_value(&args, "--submission");
+    let batches_file = flag_value(&args, "--batches");
+    let approval_file = flag_value(&args, "--approval");
+    let ranked_file = flag_value(&args, "--ranked");
+    if election_id.is_none() && specifiers_file.is_none() && submission_file.is_none() && approval_file.is_none() && ranked_file.is_none() {
+        usage_error("Either `--election-id`, `--specifiers`, `--submission`, `--approval` or `--ranked` must be provided");
+    }
+
+    let config = VerifierConfig {
+        election_id,
+        specifiers_file,
+        census_file: required_flag_value(&args, "--census"),
+        for_voters: flag_value(&args, "--for").unwrap_or_default(),
+        against_voters: flag_value(&args, "--against").unwrap_or_default(),
+        proof: match (&submission_file, &batches_file, &approval_file, &ranked_file) {
+            (None, None, None, None) => required_flag_value(&args, "--proof"),
+            _ => flag_value(&args, "--proof").unwrap_or_default(),
+        },
+        submission_file,
+        batches_file,
+        approval_file,
+        ranked_file,
+        balances_file: flag_value(&args, "--balances"),
+    };
+
+    match run(config) {
+        Ok(report) => {
+            if json {
+                report.print_json();
+            } else {
+                report.print();
+            }
+            process::exit(report.exit_code());
+        }
+        Err(err) => {
+            if json {
+                println!("{}", json!({ "status": "error", "error": err }));
+            } else {
+                eprintln!("An error occurred: {}", err);
+            }
+            process::exit(EXIT_INPUT_ERROR);
+        }
+    }
+}
+
+/// This function returns the value that follows the provided flag
+/// If the flag is set, but there is no value after it, the application exits with an error
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    let mut iter = args.iter().skip_while(|x| *x != flag);
+    iter.next()?;
+    match iter.next() {
+        Some(value) => Some(value.clone()),
+        None => usage_error(&format!("No value provided after `{}` flag", flag)),
+    }
+}
+
+/// Same as `flag_value`, but the application exits with an error if the flag is not set
+fn required_flag_value(args: &[String], flag: &str) -> String {
+    flag_value(args, flag).unwrap_or_else(|| usage_error(&format!("The `{}` flag is required", flag)))
+}
+
+/// This function prints the usage of the application and exits with the usage error code
+fn usage_error(err: &str) -> ! {
+    eprintln!("Error: {}", err);
+    eprintln!("Usage: batravot-verifier (--election-id <id> | --specifiers <file>) --census <file> --for <voters> --against <voters> --proof <proof> [--json]");
+    eprintln!("       batravot-verifier (--election-id <id> | --specifiers <file>) --census <file> --batches <file> [--balances <file>] [--json]");
+    eprintln!("       batravot-verifier --submission <file> --census <file> [--json]");
+    eprintln!("       batravot-verifier [--election-id <id>] --approval <file> --census <file> [--json]");
+    eprintln!("       batravot-verifier [--election-id <id>] --ranked <file> --census <file> [--json]");
+    eprintln!("  <voters> is a list of addresses `[0x..., 0x...]`, or `@<file>` with the list");
+    process::exit(EXIT_USAGE_ERROR);
+}
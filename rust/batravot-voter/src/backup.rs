@@ -0,0 +1,90 @@
+use ark_ff::{BigInteger, PrimeField};
+use colored::Colorize;
+use rand::Rng;
+use zeroize::Zeroizing;
+use batravot_lib::{Share, voter};
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::common::{election_prk, read_line};
+use crate::config::VoterConfig;
+use crate::keystore::save_key;
+use crate::output::Output;
+
+/// This function backs up the election private key with Shamir's Secret Sharing
+/// The action is one of the following:
+/// split - Split the key into `--shares` shares, any `--threshold` of which reconstruct it
+/// combine - Reconstruct the key from the `--share` shares, and store it in the keystore if `--label` is set
+pub(crate) fn backup(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
+    // Describe what the current mode is
+    if config.interactive {
+        println!("{}", "Backing up the election private key with secret shares".green());
+    }
+
+    let action = match &config.action {
+        Some(action) => action.clone(),
+        None if config.interactive => read_line("Please select the action: split or combine")?,
+        None => return Err("The backup action is required in the non-interactive mode: split or combine".to_string()),
+    };
+
+    match action.as_str() {
+        "split" => split_key(config, rng),
+        "combine" => combine_shares(config, rng),
+        _ => Err(format!("The backup action {} is not supported, expected `split` or `combine`", action)),
+    }
+}
+
+/// This function splits the election private key into shares, to give to different trustees or keep in different places
+fn split_key(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
+    let (election_prk, _) = election_prk(config, rng)?;
+    let shares = config.or_prompt(config.shares, "--shares", || read_number("Please provide the number of shares, at most 255:"))?;
+    let threshold = config.or_prompt(config.threshold, "--threshold", || read_number("Please provide the number of shares needed to reconstruct the key:"))?;
+
+    let public_key = voter::generate_public_key(&election_prk).solidity_repr();
+    let outputs = Share::split(&election_prk, threshold, shares, rng)?.iter()
+        .map(|share| Output::new("Please keep the share in a safe place, apart from the other shares:")
+            .field("Share index", "index", share.index)
+            .field("Threshold", "threshold", share.threshold)
+            .field("Public key", "publicKey", public_key.as_str())
+            .field("Share", "share", share.solidity_repr()))
+        .collect::<Vec<_>>();
+    Output::write_list(&outputs, config.format, &config.output)
+}
+
+/// This function reconstructs the election private key from the shares
+/// The shares are asked for one by one until there are enough of them, unless they are set with `--share`
+fn combine_shares(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
+    let mut shares = config.shares_hex.iter()
+        .map(|share| Share::from_str_c(share).map_err(|err| format!("Failed to parse the share {}: {}", share, err)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if config.interactive {
+        while shares.len() < shares.first().map_or(1, |first| first.threshold as usize) {
+            let share = read_line(&format!("Please provide the share #{}:", shares.len() + 1))?;
+            match Share::from_str_c(&share) {
+                Ok(share) => shares.push(share),
+                Err(err) => eprintln!("{}", format!("Failed to parse the share: {}.\nPlease provide a valid share", err).red()),
+            }
+        }
+    }
+
+    let election_prk = Zeroizing::new(Share::combine(&shares)?);
+    if let Some(label) = &config.label {
+        return save_key(config, label, &election_prk, rng);
+    }
+
+    let election_private_key = Zeroizing::new(format!("0x{}", hex::encode(election_prk.into_repr().to_bytes_be())));
+    eprintln!("{}", "Warning: the private key is written in clear, set `--label` to store it in the keystore instead".red());
+    Output::new("The election private key has been reconstructed:")
+        .field("Public key", "publicKey", voter::generate_public_key(&election_prk).solidity_repr())
+        .field("Private key", "privateKey", election_private_key.as_str())
+        .write(config.format, &config.output)
+}
+
+/// This function asks for a number from 1 to 255, until a valid one is provided
+fn read_number(prompt: &str) -> Result<u8, String> {
+    loop {
+        match read_line(prompt)?.parse::<u8>() {
+            Ok(number) if number > 0 => return Ok(number),
+            _ => eprintln!("{}", "Please provide a number from 1 to 255".red()),
+        }
+    }
+}
@@ -1,7 +1,100 @@
-use std::{io, process};
+use std::{fs, io, process};
 use colored::Colorize;
 use rand::{Rng};
+use zeroize::Zeroizing;
+use web3::types::Address;
 use batravot_lib::{ScalarField, voter};
+use batravot_lib::ethereum::{self, BallotDomain, EthSignature, SecretKey};
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::config::{KeySource, VoterConfig};
+use crate::keystore::decrypt_key;
+
+/// This function returns the election private key of the voter based on the configuration
+/// If the key source is not set, the user is asked to select the key on the standard input
+/// If a new key is generated from the configuration, it is returned in hex format as well, so that it can be output
+/// A key derived from the Ethereum account of the voter is not returned, as the voter can derive it again
+/// The key, its hex format and the bytes it is read from are cleared from memory when they are dropped
+pub(crate) fn election_prk(config: &VoterConfig, rng: &mut impl Rng) -> Result<(Zeroizing<ScalarField>, Option<Zeroizing<String>>), String> {
+    match &config.key {
+        Some(KeySource::Hex(raw_election_private_key)) => {
+            let election_private_key = parse_election_prk(raw_election_private_key)?;
+            Ok((Zeroizing::new(voter::convert_private_key(&election_private_key)), None))
+        }
+        Some(KeySource::File(file_path)) => {
+            let raw_election_private_key = Zeroizing::new(fs::read_to_string(file_path)
+                .map_err(|err| format!("Failed to read election private key file: {}", err))?);
+            let election_private_key = parse_election_prk(&raw_election_private_key)?;
+            Ok((Zeroizing::new(voter::convert_private_key(&election_private_key)), None))
+        }
+        Some(KeySource::Generate) => {
+            let mut election_private_key = Zeroizing::new([0; 32]);
+            rng.fill_bytes(election_private_key.as_mut());
+            Ok((Zeroizing::new(voter::convert_private_key(election_private_key.as_ref())),
+                Some(Zeroizing::new(format!("0x{}", hex::encode(election_private_key.as_ref()))))))
+        }
+        Some(KeySource::Signature(raw_signature)) => {
+            let contract = config.or_prompt(config.contract, "--contract", get_contract)?;
+            Ok((wallet_election_prk(raw_signature, &contract, config.address)?, None))
+        }
+        Some(KeySource::Derive) => {
+            let sign_key = sign_key(config)?
+                .ok_or("The `--derive-key` flag requires the Ethereum private key, set with `--sign-key` or `--sign-key-file`")?;
+            let contract = config.or_prompt(config.contract, "--contract", get_contract)?;
+            Ok((Zeroizing::new(ethereum::derive_election_key(&sign_key, &contract)), None))
+        }
+        Some(KeySource::Keystore(label)) => Ok((decrypt_key(config, label)?, None)),
+        None => Ok((config.or_prompt(None, "--key", || get_election_prk(config, rng))?, None)),
+    }
+}
+
+/// This function derives the election private key from the signature of the `election_key_message` by the wallet of the voter
+/// If the address of the voter is known, the signature must be from it, otherwise the signer is only shown
+/// This catches a signature of another message, which would silently give another key
+fn wallet_election_prk(raw_signature: &str, contract: &Address, address: Option<Address>) -> Result<Zeroizing<ScalarField>, String> {
+    let signature = EthSignature::from_str_c(raw_signature)
+        .map_err(|err| format!("Failed to parse the signature: {}", err))?;
+    let signer = ethereum::recover_personal_signer(ethereum::election_key_message(contract).as_bytes(), &signature)?;
+    match address {
+        Some(address) if address != signer => {
+            return Err(format!("The signature is from {}, not from the address {}. Please sign the message for the contract {}",
+                signer.solidity_repr(), address.solidity_repr(), contract.solidity_repr()));
+        }
+        Some(_) => {}
+        None => eprintln!("The election private key is derived from the signature of {}", signer.solidity_repr()),
+    }
+    ethereum::election_key_from_signature(&signature).map(Zeroizing::new)
+}
+
+/// This function returns the private key of the Ethereum account that signs the ballot, if it is set in the configuration
+/// The key is never asked for on the standard input, signing the ballot is optional
+pub(crate) fn sign_key(config: &VoterConfig) -> Result<Option<SecretKey>, String> {
+    match &config.sign_key {
+        Some(KeySource::Hex(raw_sign_key)) => Ok(Some(ethereum::parse_secret_key(raw_sign_key)?)),
+        Some(KeySource::File(file_path)) => {
+            let raw_sign_key = fs::read_to_string(file_path)
+                .map_err(|err| format!("Failed to read the Ethereum private key file: {}", err))?;
+            Ok(Some(ethereum::parse_secret_key(&raw_sign_key)?))
+        }
+        Some(_) => Err("The Ethereum private key can only be provided in hex format or in a file".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// This function parses the election private key from a hex string
+/// The string must encode 32 bytes, the 0x prefix and the surrounding whitespace are optional
+/// The bytes are cleared from memory when they are dropped
+pub(crate) fn parse_election_prk(raw_election_private_key: &str) -> Result<Zeroizing<Vec<u8>>, String> {
+    // First, we remove the 0x prefix if it exists and any whitespace
+    let cleaned_election_private_key = Zeroizing::new(raw_election_private_key.trim().replace("0x", ""));
+    // Then, we convert the hex string to a byte array
+    let election_private_key = Zeroizing::new(hex::decode(cleaned_election_private_key.as_str())
+        .map_err(|err| format!("Failed to decode election private key: {}", err))?);
+
+    if election_private_key.len() != 32 {
+        return Err(format!("The election private key must be 32 bytes long. Provided key is {} bytes long", election_private_key.len()));
+    }
+    Ok(election_private_key)
+}
 
 /// This function asks the user to select the private key to use for the election
 /// In case the user has not yet generated a private key, it will generate one
@@ -9,14 +102,17 @@ use batravot_lib::{ScalarField, voter};
 /// Note that we consider reading errors a fatal error and will exit the program
 /// At the same time, we consider parsing errors as non-fatal and will ask the user to provide a valid data
 /// We use rng to generate the private key if the user does not provide one
-pub(crate) fn get_election_prk(rng: &mut impl Rng) -> Result<ScalarField, String> {
+/// The key can also be derived from a signature of the wallet of the user, for the contract of the configuration, or decrypted from the keystore
+pub(crate) fn get_election_prk(config: &VoterConfig, rng: &mut impl Rng) -> Result<Zeroizing<ScalarField>, String> {
 
 
     loop {
         println!("\nDo you want to use the preexisting or generate a new election private key?");
         println!("1 - Use a preexisting election private key");
         println!("2 - Generate a new election private key");
-        println!("3 - Exit");
+        println!("3 - Derive the election private key from a signature of your Ethereum wallet");
+        println!("4 - Use an election private key from the keystore");
+        println!("5 - Exit");
         let mut choice = String::new();
         io::stdin().read_line(&mut choice)
             .map_err(|err| format!("Failed to read selected option for election private key: {}", err))?;
@@ -25,35 +121,53 @@ pub(crate) fn get_election_prk(rng: &mut impl Rng) -> Result<ScalarField, String
             "1" => {
                 println!("\nPlease provide the preexisting election private key:");
                 println!("And example of a valid election private key is: 0x0000000000000000000000000000000000000000000000000000000000000001");
-                let mut raw_election_private_key = String::new();
+                let mut raw_election_private_key = Zeroizing::new(String::new());
                 io::stdin().read_line(&mut raw_election_private_key)
                     .map_err(|err| format!("Failed to read election private key: {}", err))?;
 
-                // First, we remove the 0x prefix if it exists and any whitespace
-                let cleaned_election_private_key = raw_election_private_key.trim().replace("0x", "");
-                // Then, we convert the hex string to a byte array
-                let election_private_key = match hex::decode(cleaned_election_private_key) {
+                match parse_election_prk(&raw_election_private_key) {
                     Ok(election_private_key) => election_private_key,
                     Err(err) => {
-                        eprintln!("{}", format!("Failed to decode election private key: {}.\nPlease provide a valid election private key", err).red());
+                        eprintln!("{}", format!("{}.\nPlease provide a valid election private key", err).red());
                         continue;
                     }
-                };
-
-                if election_private_key.len() != 32 {
-                    eprintln!("{}", format!("The election private key must be 32 bytes long. Provided key is {} bytes long.\nPlease provide a valid election private key", election_private_key.len()).red());
-                    continue;
                 }
-                election_private_key
             }
             "2" => {
-                let mut election_private_key = [0; 32];
-                rng.fill_bytes(&mut election_private_key);
-                println!("Generated a private key: 0x{}", hex::encode(election_private_key));
+                let mut election_private_key = Zeroizing::new(vec![0; 32]);
+                rng.fill_bytes(election_private_key.as_mut());
+                println!("Generated a private key: {}", Zeroizing::new(format!("0x{}", hex::encode(election_private_key.as_slice()))).as_str());
 
-                election_private_key.to_vec()
+                election_private_key
             }
             "3" => {
+                let contract = match config.contract {
+                    Some(contract) => contract,
+                    None => get_contract()?,
+                };
+                println!("\nPlease sign the following message with `personal_sign` in your wallet, e.g. with the \"Sign message\" of your wallet:");
+                println!("{}", ethereum::election_key_message(&contract));
+                let raw_signature = read_line("Please provide the signature:")?;
+
+                match wallet_election_prk(&raw_signature, &contract, config.address) {
+                    Ok(election_prk) => return Ok(election_prk),
+                    Err(err) => {
+                        eprintln!("{}", format!("{}.\nPlease try again", err).red());
+                        continue;
+                    }
+                }
+            }
+            "4" => {
+                let label = read_line("Please provide the label of the key in the keystore:")?;
+                match decrypt_key(config, &label) {
+                    Ok(election_prk) => return Ok(election_prk),
+                    Err(err) => {
+                        eprintln!("{}", format!("{}.\nPlease try again", err).red());
+                        continue;
+                    }
+                }
+            }
+            "5" => {
                 eprintln!("{}", "Exiting...".red());
                 process::exit(0);
             }
@@ -63,7 +177,7 @@ pub(crate) fn get_election_prk(rng: &mut impl Rng) -> Result<ScalarField, String
             }
         };
 
-        return Ok(voter::convert_private_key(&election_private_key));
+        return Ok(Zeroizing::new(voter::convert_private_key(&election_private_key)));
     }
 }
 
@@ -98,4 +212,53 @@ pub(crate) fn get_election_id() -> Result<u64, String> {
         return Ok(election_id);
     }
 
-}
\ No newline at end of file
+}
+/// This function returns the chain and the contract of the election, the ballots are signed for them
+/// They are asked for if they are not set with `--chain-id` and `--contract`
+pub(crate) fn ballot_domain(config: &VoterConfig) -> Result<BallotDomain, String> {
+    let verifying_contract = config.or_prompt(config.contract, "--contract", get_contract)?;
+    let chain_id = config.or_prompt(config.chain_id, "--chain-id", get_chain_id)?;
+    Ok(BallotDomain { chain_id, verifying_contract })
+}
+
+/// This function asks the user to provide the id of the chain the election contract is deployed to
+/// Parsing errors are non-fatal, the user is asked again
+fn get_chain_id() -> Result<u64, String> {
+    loop {
+        let chain_id = read_line("Please provide the chain id of the election contract, e.g. 1 for the Ethereum mainnet:")?;
+        match chain_id.parse::<u64>() {
+            Ok(chain_id) => return Ok(chain_id),
+            Err(err) => eprintln!("{}", format!("There was an error parsing the chain id: {}.\nPlease provide a valid chain id", err).red()),
+        }
+    }
+}
+
+/// This function asks the user to provide the Ethereum address they vote with
+/// Parsing errors are non-fatal, the user is asked again
+pub(crate) fn get_address() -> Result<Address, String> {
+    read_address("Please provide your Ethereum address:")
+}
+
+/// This function asks the user to provide the address of the election contract
+pub(crate) fn get_contract() -> Result<Address, String> {
+    read_address("Please provide the address of the election contract:")
+}
+
+fn read_address(prompt: &str) -> Result<Address, String> {
+    loop {
+        let address = read_line(prompt)?;
+        match Address::from_str_c(&address) {
+            Ok(address) => return Ok(address),
+            Err(err) => eprintln!("{}", format!("There was an error parsing the address: {}.\nPlease provide a valid address", err).red()),
+        }
+    }
+}
+
+/// This function prints the prompt and reads a single line from the standard input
+pub(crate) fn read_line(prompt: &str) -> Result<String, String> {
+    println!("\n{}", prompt);
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)
+        .map_err(|err| format!("Error reading the input: {}", err))?;
+    Ok(line.trim().to_string())
+}
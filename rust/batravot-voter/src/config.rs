@@ -0,0 +1,305 @@
+use std::str::FromStr;
+use web3::types::Address;
+use batravot_lib::Vote;
+use batravot_lib::ballot::parse_options;
+use batravot_lib::representation::FromStrCustom;
+
+/// Where a private key of the voter comes from
+/// Hex - The key is provided in hex format on the command line
+/// File - The key is read in hex format from a file
+/// Generate - A new key is generated, only for the election private key
+/// Signature - The election private key is derived from a signature of the wallet of the voter, in hex format
+/// Derive - The election private key is derived from the Ethereum private key that signs the ballot
+/// Keystore - The election private key is decrypted from the keystore with the label
+pub enum KeySource {
+    Hex(String),
+    File(String),
+    Generate,
+    Signature(String),
+    Derive,
+    Keystore(String),
+}
+
+/// The format in which the output of the voter application is written
+/// Text - The human readable format
+/// Json - A single JSON object, for scripts
+/// JsonLines - A single JSON object on one line, appended to the output file
+/// Csv - A CSV record with a header, appended to the output file, the header is only written to an empty file
+/// The ballots written in the JSON, JSON Lines and CSV formats can be read by the batcher
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    JsonLines,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            "csv" => Ok(OutputFormat::Csv),
+            _ => Err(format!("Invalid output format: {}, expected `text`, `json`, `jsonl` or `csv`", s)),
+        }
+    }
+}
+
+/// The options of the voter application, set with the command line flags
+/// Each option that is not set is asked for on the standard input, unless `interactive` is false
+/// action - The first argument after the execution mode that is not a flag, e.g. `create` in `keystore create`
+/// key - `--key <hex>`, `--key-file <path>`, `--new-key`, `--key-signature <hex>`, `--derive-key` or `--keystore <label>`
+/// election_id - `--election-id <id>`
+/// vote - `--vote <for|against>`
+/// format - `--format <text|json|jsonl|csv>`, text by default
+/// output - `--output <path>`, the standard output by default
+/// interactive - `--non-interactive` disables the prompts, a missing option is then an error
+/// address - `--address <address>`, the Ethereum address of the voter
+/// batcher - `--batcher <url>`, the URL of the batcher server
+/// batcher_address - `--batcher-address <address>`, the Ethereum account of the batcher server that signs the receipts
+/// receipt - `--receipt <path>`, the file with the receipt of a submitted ballot
+/// sign_key - `--sign-key <hex>` or `--sign-key-file <path>`, the private key of the Ethereum account that signs the ballot
+/// sequence - `--sequence <n>`, the sequence of the signed ballot, the current Unix time in milliseconds by default
+/// weight - `--weight <n>`, the declared weight of a weighted ballot, the vote proof is scaled by it
+/// approve - `--approve <options>`, the options approved by an approval ballot, e.g. `0,2`, instead of the vote
+/// rank - `--rank <options>`, the options ranked by a ranked ballot, the most preferred first, e.g. `2,0,1`, instead of the vote
+/// contract - `--contract <address>`, the address of the election contract, the election private key is derived for it and the ballots are signed for it
+/// chain_id - `--chain-id <id>`, the chain of the election contract, the ballots are signed for it
+/// label - `--label <label>`, the label of the key in the keystore
+/// keystore_dir - `--keystore-dir <path>`, the directory of the keystore, `$HOME/.batravot/keystore` by default
+/// password_file - `--password-file <path>`, the file with the password of the keystore, instead of the prompt
+/// shares - `--shares <n>`, the number of shares the key is split into
+/// threshold - `--threshold <t>`, the number of shares needed to reconstruct the key
+/// shares_hex - `--share <hex>`, repeated for each share the key is reconstructed from
+/// ballot - `--ballot <line>`, the line of a ballot given to the batcher, to verify
+/// public_key - `--public-key <point>`, the public key of the voter, to verify
+/// vote_proof - `--vote-proof <point>`, the vote proof of a ballot, to verify
+/// key_proof - `--key-proof <proof>`, the Schnorr key proof of the public key, to verify
+pub struct VoterConfig {
+    pub action: Option<String>,
+    pub key: Option<KeySource>,
+    pub election_id: Option<u64>,
+    pub vote: Option<Vote>,
+    pub format: OutputFormat,
+    pub output: Option<String>,
+    pub interactive: bool,
+    pub address: Option<Address>,
+    pub batcher: Option<String>,
+    pub batcher_address: Option<Address>,
+    pub receipt: Option<String>,
+    pub sign_key: Option<KeySource>,
+    pub sequence: Option<u64>,
+    pub weight: Option<u64>,
+    pub approve: Option<Vec<usize>>,
+    pub rank: Option<Vec<usize>>,
+    pub contract: Option<Address>,
+    pub chain_id: Option<u64>,
+    pub label: Option<String>,
+    pub keystore_dir: Option<String>,
+    pub password_file: Option<String>,
+    pub shares: Option<u8>,
+    pub threshold: Option<u8>,
+    pub shares_hex: Vec<String>,
+    pub ballot: Option<String>,
+    pub public_key: Option<String>,
+    pub vote_proof: Option<String>,
+    pub key_proof: Option<String>,
+}
+
+impl Default for VoterConfig {
+    fn default() -> Self {
+        VoterConfig {
+            action: None,
+            key: None,
+            election_id: None,
+            vote: None,
+            format: OutputFormat::Text,
+            output: None,
+            interactive: true,
+            address: None,
+            batcher: None,
+            batcher_address: None,
+            receipt: None,
+            sign_key: None,
+            sequence: None,
+            weight: None,
+            approve: None,
+            rank: None,
+            contract: None,
+            chain_id: None,
+            label: None,
+            keystore_dir: None,
+            password_file: None,
+            shares: None,
+            threshold: None,
+            shares_hex: Vec::new(),
+            ballot: None,
+            public_key: None,
+            vote_proof: None,
+            key_proof: None,
+        }
+    }
+}
+
+impl VoterConfig {
+    /// This function parses the command line flags that follow the execution mode
+    /// The first argument that is not a flag is the action of the execution mode
+    /// Flags with a value can be provided either as `--flag value` or as `--flag=value`
+    pub fn from_args(args: &[String]) -> Result<VoterConfig, String> {
+        let mut config = VoterConfig::default();
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            // Returns the value of the current flag
+            let mut value = || inline_value.clone().or_else(|| iter.next().cloned())
+                .ok_or(format!("No value provided after `{}` flag", flag));
+
+            match flag {
+                "--key" => config.key = Some(KeySource::Hex(value()?)),
+                "--key-file" => config.key = Some(KeySource::File(value()?)),
+                "--new-key" => config.key = Some(KeySource::Generate),
+                "--key-signature" => config.key = Some(KeySource::Signature(value()?)),
+                "--derive-key" => config.key = Some(KeySource::Derive),
+                "--keystore" => config.key = Some(KeySource::Keystore(value()?)),
+                "--label" => config.label = Some(value()?),
+                "--keystore-dir" => config.keystore_dir = Some(value()?),
+                "--password-file" => config.password_file = Some(value()?),
+                "--shares" => {
+                    let shares = value()?;
+                    config.shares = Some(shares.trim().parse::<u8>()
+                        .map_err(|err| format!("There was an error parsing the number of shares: {}", err))?);
+                }
+                "--threshold" => {
+                    let threshold = value()?;
+                    config.threshold = Some(threshold.trim().parse::<u8>()
+                        .map_err(|err| format!("There was an error parsing the threshold: {}", err))?);
+                }
+                "--share" => config.shares_hex.push(value()?),
+                "--ballot" => config.ballot = Some(value()?),
+                "--public-key" => config.public_key = Some(value()?),
+                "--vote-proof" => config.vote_proof = Some(value()?),
+                "--key-proof" => config.key_proof = Some(value()?),
+                "--election-id" => {
+                    let election_id = value()?;
+                    config.election_id = Some(election_id.trim().parse::<u64>()
+                        .map_err(|err| format!("There was an error parsing the Election Id: {}", err))?);
+                }
+                "--vote" => config.vote = Some(Vote::from_str(value()?.trim())?),
+                "--format" => config.format = OutputFormat::from_str(&value()?)?,
+                "--output" => config.output = Some(value()?),
+                "--non-interactive" => config.interactive = false,
+                "--address" => {
+                    let address = value()?;
+                    config.address = Some(Address::from_str_c(address.trim())
+                        .map_err(|err| format!("There was an error parsing the address: {}", err))?);
+                }
+                "--contract" => {
+                    let contract = value()?;
+                    config.contract = Some(Address::from_str_c(contract.trim())
+                        .map_err(|err| format!("There was an error parsing the contract address: {}", err))?);
+                }
+                "--chain-id" => {
+                    let chain_id = value()?;
+                    config.chain_id = Some(chain_id.trim().parse::<u64>()
+                        .map_err(|err| format!("There was an error parsing the chain id: {}", err))?);
+                }
+                "--batcher" => config.batcher = Some(value()?),
+                "--batcher-address" => {
+                    let batcher_address = value()?;
+                    config.batcher_address = Some(Address::from_str_c(batcher_address.trim())
+                        .map_err(|err| format!("There was an error parsing the batcher address: {}", err))?);
+                }
+                "--receipt" => config.receipt = Some(value()?),
+                "--sign-key" => config.sign_key = Some(KeySource::Hex(value()?)),
+                "--sign-key-file" => config.sign_key = Some(KeySource::File(value()?)),
+                "--sequence" => {
+                    let sequence = value()?;
+                    config.sequence = Some(sequence.trim().parse::<u64>()
+                        .map_err(|err| format!("There was an error parsing the sequence: {}", err))?);
+                }
+                "--weight" => {
+                    let weight = value()?.trim().parse::<u64>()
+                        .map_err(|err| format!("There was an error parsing the weight: {}", err))?;
+                    if weight == 0 {
+                        return Err("The weight must be positive".to_string());
+                    }
+                    config.weight = Some(weight);
+                }
+                "--approve" => config.approve = Some(parse_options(&value()?)
+                    .map_err(|err| format!("There was an error parsing the approved options: {}", err))?),
+                "--rank" => config.rank = Some(parse_options(&value()?)
+                    .map_err(|err| format!("There was an error parsing the ranked options: {}", err))?),
+                _ if !arg.starts_with("--") && config.action.is_none() => config.action = Some(arg.clone()),
+                _ => return Err(format!("Unknown flag `{}`", arg)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the value of an option, or asks for it with the prompt function if it is not set
+    /// If the prompts are disabled, a missing option is an error
+    pub(crate) fn or_prompt<T>(&self, value: Option<T>, flag: &str, prompt: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+        match value {
+            Some(value) => Ok(value),
+            None if self.interactive => prompt(),
+            None => Err(format!("The `{}` flag is required in the non-interactive mode", flag)),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_the_flags() {
+        let config = VoterConfig::from_args(&args(&["--election-id", "7", "--vote=against", "--format", "JSONL", "--non-interactive",
+            "--address", "0x0000000000000000000000000000000000000001", "--weight", "40", "--rank", "2, 0,1", "--share", "0x01", "--share=0x02"])).unwrap();
+        assert_eq!(config.election_id, Some(7));
+        assert_eq!(config.vote, Some(Vote::Against));
+        assert!(config.format == OutputFormat::JsonLines);
+        assert!(!config.interactive);
+        assert_eq!(config.address, Some(Address::from_low_u64_be(1)));
+        assert_eq!(config.weight, Some(40));
+        assert_eq!(config.rank, Some(vec![2, 0, 1]));
+        assert_eq!(config.shares_hex, vec!["0x01".to_string(), "0x02".to_string()]);
+        assert!(config.action.is_none() && config.key.is_none());
+
+        let config = VoterConfig::from_args(&args(&["--keystore", "main", "--key-file=key.txt"])).unwrap();
+        assert!(matches!(config.key, Some(KeySource::File(path)) if path == "key.txt"));
+    }
+
+    #[test]
+    fn parses_the_action() {
+        let config = VoterConfig::from_args(&args(&["--label", "main", "create"])).unwrap();
+        assert_eq!(config.action, Some("create".to_string()));
+        assert_eq!(config.label, Some("main".to_string()));
+
+        // Only one action can be given
+        assert_eq!(VoterConfig::from_args(&args(&["create", "export"])).err(), Some("Unknown flag `export`".to_string()));
+    }
+
+    #[test]
+    fn rejects_invalid_flags() {
+        assert_eq!(VoterConfig::from_args(&args(&["--vote-for"])).err(), Some("Unknown flag `--vote-for`".to_string()));
+        assert_eq!(VoterConfig::from_args(&args(&["--election-id"])).err(), Some("No value provided after `--election-id` flag".to_string()));
+        assert_eq!(VoterConfig::from_args(&args(&["--weight", "0"])).err(), Some("The weight must be positive".to_string()));
+        assert!(VoterConfig::from_args(&args(&["--election-id", "-1"])).is_err());
+        assert!(VoterConfig::from_args(&args(&["--format", "xml"])).is_err());
+        assert!(VoterConfig::from_args(&args(&["--approve", "0,a"])).is_err());
+        assert!(VoterConfig::from_args(&args(&["--address", "0x01"])).is_err());
+    }
+}
@@ -1,52 +1,234 @@
 use colored::Colorize;
 use rand::Rng;
-use batravot_lib::{ElectionSpecifiers, Vote, voter};
+use std::time::{SystemTime, UNIX_EPOCH};
+use web3::types::Address;
+use zeroize::Zeroizing;
+use batravot_lib::{ApprovalSpecifiers, DleqProof, ElectionSpecifiers, G1, RankedSpecifiers, ScalarField, Vote, voter};
+use batravot_lib::ballot::BALLOT_FORMAT_VERSION;
+use batravot_lib::ethereum::{self, EthSignature, TypedBallot};
 use batravot_lib::representation::SolidityRepresentable;
-use crate::common::{get_election_id, get_election_prk};
+use crate::common::{ballot_domain, election_prk, get_address, get_election_id, sign_key};
+use crate::config::{OutputFormat, VoterConfig};
+use crate::output::Output;
 
 /// This function generates a ballot for the voter
-/// It will request the user to provide the election ID, the election private key, and the vote
-/// It will then generate the ballot and print it to the screen
-pub(crate) fn generate_ballot(rng: &mut impl Rng) -> Result<(), String> {
+/// It will request the user to provide the election ID, the election private key, the vote and the Ethereum address, unless they are set in the configuration
+/// It will then generate the ballot and write it to the output, in the text format together with the line to give to the batcher
+pub(crate) fn generate_ballot(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
     // Describe what the current mode is
-    println!("{}", "Generating a ballot for the voter".green());
+    if config.interactive {
+        println!("{}", "Generating a ballot for the voter".green());
+    }
+
+    // The ballot of an approval election approves a subset of the options, the one of a ranked election ranks them, instead of a vote
+    match (&config.approve, &config.rank) {
+        (Some(_), Some(_)) => return Err("A ballot can not both approve and rank the options".to_string()),
+        (Some(selection), None) => return generate_options_ballot(config, selection, false, rng),
+        (None, Some(ranking)) => return generate_options_ballot(config, ranking, true, rng),
+        (None, None) => {}
+    }
+
+    let ballot = new_ballot(config, rng)?;
+
+    // Write out the information of the ballot to the user
+    // If the users does not trust the batcher, they can submit the proof directly to the verifier
+    // This is possible because the proof of election with one vote is the the same as the proof of the vote
+    // However, this is not recommended as this makes it a lot more expensive to vote
+    // The idea of the protocol is still rather that the batcher can reduce the gas cost of a single vote
+    // In the other formats than text, the output is a ballot in the format of the batcher, so the generated key is not part of it
+    let mut output = Output::new("Please submit the following data to the election batcher/verifier:")
+        .version(BALLOT_FORMAT_VERSION);
+    if let Some(generated_prk) = &ballot.generated_prk {
+        if config.format == OutputFormat::Text {
+            output = output.field("Generated private key", "privateKey", generated_prk.as_str());
+        } else {
+            eprintln!("{}", format!("Generated private key: {}\nPlease keep it safe, it is not written to the ballot", generated_prk.as_str()).yellow());
+        }
+    }
+    output = output
+        .field("Election ID", "electionId", ballot.election_id)
+        .field("Vote", "vote", format!("{:?}", ballot.vote))
+        .field("Public key", "publicKey", ballot.public_key.solidity_repr())
+        .field("Vote Proof", "voteProof", ballot.vote_proof.solidity_repr())
+        .field("DLEQ Proof", "dleqProof", ballot.dleq_proof.solidity_repr())
+        .field("Address", "address", ballot.address.solidity_repr());
+    if let Some(weight) = ballot.weight {
+        output = output.field("Weight", "weight", weight);
+    }
+    if let (Some(sequence), Some(signature)) = (ballot.sequence, &ballot.signature) {
+        output = output
+            .field("Sequence", "sequence", sequence)
+            .field("Signature", "signature", signature.solidity_repr());
+    }
+    if config.format == OutputFormat::Text {
+        output = output.field("Batcher line", "line", ballot.batcher_line());
+    }
+    output.write(config.format, &config.output)
+}
+
+/// This function generates an approval ballot, which approves the selected options of the election, or a ranked ballot, which ranks them
+/// The vote proof is made with the sum of the specifiers of the approved options, or of the ranked options at their rank, there is no DLEQ proof, signature or weight
+/// The ballot is written in the format of the approval and ranked ballots of the batcher, `<public key>|<options>|<vote proof>|<address>` in the text format
+/// options - The approved options, or the ranked options with the most preferred first
+/// ranked - Whether the ballot is a ranked ballot
+fn generate_options_ballot(config: &VoterConfig, options: &[usize], ranked: bool, rng: &mut impl Rng) -> Result<(), String> {
+    if config.vote.is_some() || config.weight.is_some() || config.sign_key.is_some() {
+        return Err("An approval or ranked ballot can not be combined with `--vote`, `--weight` or `--sign-key`".to_string());
+    }
+    let (election_prk, generated_prk) = election_prk(config, rng)?;
+    let election_id = config.or_prompt(config.election_id, "--election-id", get_election_id)?;
+
+    // The specifiers of an option only depend on the election id, the option and its rank, so only the specifiers of the options of the ballot are created
+    // An approval ballot lists its options in increasing order, a ranked ballot in the order of preference
+    let (vote_proof, options, label, key) = if ranked {
+        let specifier = RankedSpecifiers::ranking_specifier_of(election_id, options)?;
+        (voter::generate_vote_proof(&election_prk, &specifier), options.to_vec(), "Ranking", "ranking")
+    } else {
+        let specifier = ApprovalSpecifiers::selection_specifier_of(election_id, options)?;
+        let mut selection = options.to_vec();
+        selection.sort_unstable();
+        (voter::generate_vote_proof(&election_prk, &specifier), selection, "Selection", "selection")
+    };
+    let address = config.or_prompt(config.address, "--address", get_address)?;
+
+    let options = options.iter().map(|option| option.to_string()).collect::<Vec<String>>().join(",");
+    let public_key = voter::generate_public_key(&election_prk);
 
+    let mut output = Output::new("Please submit the following data to the election batcher:")
+        .version(BALLOT_FORMAT_VERSION);
+    if let Some(generated_prk) = &generated_prk {
+        if config.format == OutputFormat::Text {
+            output = output.field("Generated private key", "privateKey", generated_prk.as_str());
+        } else {
+            eprintln!("{}", format!("Generated private key: {}\nPlease keep it safe, it is not written to the ballot", generated_prk.as_str()).yellow());
+        }
+    }
+    output = output
+        .field("Election ID", "electionId", election_id)
+        .field(label, key, options.as_str())
+        .field("Public key", "publicKey", public_key.solidity_repr())
+        .field("Vote Proof", "voteProof", vote_proof.solidity_repr())
+        .field("Address", "address", address.solidity_repr());
+    if config.format == OutputFormat::Text {
+        let line = format!("{}|{}|{}|{}", public_key.solidity_repr(), options, vote_proof.solidity_repr(), address.solidity_repr());
+        output = output.field("Batcher line", "line", line);
+    }
+    output.write(config.format, &config.output)
+}
+
+/// This struct represents a ballot generated by the voter
+/// dleq_proof - The proof that the vote proof is made with the private key of the public key, the batcher can check it without a pairing
+/// address - The Ethereum address the voter registered with, the batcher includes the ballot for this address
+/// sequence - The sequence of the signed ballot, a later ballot of the voter must have a higher sequence
+/// signature - The EIP-712 signature of the ballot by the Ethereum account of the address, if the voter provided its key
+/// weight - The declared weight of the vote, if the ballot is weighted, the vote proof and the DLEQ proof are then scaled by it
+/// generated_prk - The new election private key in hex format, if it was generated for this ballot, it is cleared from memory when it is dropped
+pub(crate) struct VoterBallot {
+    pub election_id: u64,
+    pub vote: Vote,
+    pub public_key: G1,
+    pub vote_proof: G1,
+    pub dleq_proof: DleqProof,
+    pub address: Address,
+    pub sequence: Option<u64>,
+    pub signature: Option<EthSignature>,
+    pub weight: Option<u64>,
+    pub generated_prk: Option<Zeroizing<String>>,
+}
+
+impl VoterBallot {
+    /// This function returns the ballot in the format of the ballot files of the batcher
+    /// `<public key>|<vote>|<vote proof>|<address>`, followed by `|<sequence>|<signature>|<dleq proof>` if the ballot is signed
+    /// A weighted ballot ends with `|<weight>`, the sequence and the signature are left empty if it is not signed
+    pub(crate) fn batcher_line(&self) -> String {
+        let vote = match self.vote {
+            Vote::For => "for",
+            Vote::Against => "against",
+        };
+        let mut line = format!("{}|{}|{}|{}",
+            self.public_key.solidity_repr(), vote, self.vote_proof.solidity_repr(), self.address.solidity_repr());
+        if let (Some(sequence), Some(signature)) = (self.sequence, &self.signature) {
+            line.push_str(&format!("|{}|{}|{}", sequence, signature.solidity_repr(), self.dleq_proof.solidity_repr()));
+        } else if self.weight.is_some() {
+            line.push_str(&format!("|||{}", self.dleq_proof.solidity_repr()));
+        }
+        if let Some(weight) = self.weight {
+            line.push_str(&format!("|{}", weight));
+        }
+        line
+    }
+}
+
+/// This function creates a new ballot from the configuration, asking for the missing values
+pub(crate) fn new_ballot(config: &VoterConfig, rng: &mut impl Rng) -> Result<VoterBallot, String> {
     // Get the election private key of the voter
-    let election_prk = get_election_prk(rng)?;
+    let (election_prk, generated_prk) = election_prk(config, rng)?;
     let election_pbk = voter::generate_public_key(&election_prk);
 
     // Get the election id in which the voter wants to vote
     // This is used to generate the election specifiers to then generate the vote proof
     // The election id can be any 256 bit number, however, we limit it to a 64 bit number here
     // This should be sufficient for most use cases as it allows for 2^64 elections
-    let election_id = get_election_id()?;
+    let election_id = config.or_prompt(config.election_id, "--election-id", get_election_id)?;
     let specifiers = ElectionSpecifiers::from(election_id);
 
     // Ask the user how they want to vote
-    let vote = get_vote()?;
+    let vote = config.or_prompt(config.vote, "--vote", get_vote)?;
 
     // Generate a proof of the vote
+    // A weighted vote proof is made with the private key scaled by the weight, so the DLEQ proof is made with the same key
     let vote_specifier = match vote {
         Vote::For => &specifiers.forr.0,
         Vote::Against => &specifiers.against.0,
     };
-    let vote_proof = voter::generate_vote_proof(&election_prk, &vote_specifier);
+    let mut proof_prk = election_prk;
+    if let Some(weight) = config.weight {
+        *proof_prk *= ScalarField::from(weight);
+    }
+    let vote_proof = voter::generate_vote_proof(&proof_prk, vote_specifier);
+    let dleq_proof = DleqProof::generate_proof(&proof_prk, vote_specifier, rng);
 
-    // Print out the information of the ballot to the user
-    // If the users does not trust the batcher, they can submit the proof directly to the verifier
-    // This is possible because the proof of election with one vote is the the same as the proof of the vote
-    // However, this is not recommended as this makes it a lot more expensive to vote
-    // The idea of the protocol is still rather that the batcher can reduce the gas cost of a single vote
-    println!("\n");
-    println!("----------------------------------------");
-    println!("Please submit the following data to the election batcher/verifier:");
-    println!("Election ID: {}", election_id);
-    println!("Vote:        {:?}", vote);
-    println!("Public key:  {}", election_pbk.solidity_repr());
-    println!("Vote Proof:  {}", vote_proof.solidity_repr());
-    println!("----------------------------------------");
-
-    Ok(())
+    // Get the Ethereum address of the voter, the address of the signing key if the ballot is signed
+    // The signature proves to the batcher that the voter controls the address, so the ballot can not be attributed to someone else
+    let sign_key = sign_key(config)?;
+    let address = match (&sign_key, config.address) {
+        (Some(sign_key), Some(address)) if ethereum::address(sign_key) != address => {
+            return Err(format!("The address {} is not the address of the signing key, {}", address.solidity_repr(), ethereum::address(sign_key).solidity_repr()));
+        }
+        (Some(sign_key), _) => ethereum::address(sign_key),
+        (None, address) => config.or_prompt(address, "--address", get_address)?,
+    };
+
+    // Sign the ballot, the sequence is the current time in milliseconds by default, so that a later ballot has a higher sequence
+    // even if it is made within the same second
+    let (sequence, signature) = match sign_key {
+        Some(sign_key) => {
+            let sequence = match config.sequence {
+                Some(sequence) => sequence,
+                None => {
+                    let now = SystemTime::now().duration_since(UNIX_EPOCH)
+                        .map_err(|err| format!("Error reading the current time: {}", err))?;
+                    u64::try_from(now.as_millis()).map_err(|err| format!("Error reading the current time: {}", err))?
+                }
+            };
+            let typed_ballot = TypedBallot { election_id, vote, vote_proof, sequence };
+            (Some(sequence), Some(typed_ballot.sign(&ballot_domain(config)?, &sign_key)))
+        }
+        None => (None, None),
+    };
+
+    Ok(VoterBallot {
+        election_id,
+        vote,
+        public_key: election_pbk,
+        vote_proof,
+        dleq_proof,
+        address,
+        sequence,
+        signature,
+        weight: config.weight,
+        generated_prk,
+    })
 }
 
 
@@ -54,7 +236,7 @@ pub(crate) fn generate_ballot(rng: &mut impl Rng) -> Result<(), String> {
 /// The user can choose between `for` or `against`, case insensitive.
 /// Function can also accept `+` as `for` and `-` as `against`
 /// It will return the vote as a `Vote` enum
-fn get_vote() -> Result<Vote, String> {
+pub(crate) fn get_vote() -> Result<Vote, String> {
 
     // Ask the user to select how they want to vote
     println!("\nHow do you want to vote?");
@@ -2,29 +2,33 @@ use colored::Colorize;
 use rand::Rng;
 use batravot_lib::representation::SolidityRepresentable;
 use batravot_lib::{SchnorrKnowledgeProof, voter};
-use crate::common::get_election_prk;
+use crate::common::election_prk;
+use crate::config::VoterConfig;
+use crate::output::Output;
 
 /// This function generates a Schnorr key proof for the voter to prove that they own the private key
-/// It will request the user to provide the election private key
-/// It will then generate the Schnorr key proof and print it to the screen
-pub(crate) fn generate_schnorr_key_proof(rng: &mut impl Rng) -> Result<(), String> {
+/// It will request the user to provide the election private key, unless it is set in the configuration
+/// It will then generate the Schnorr key proof and write it to the output
+pub(crate) fn generate_schnorr_key_proof(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
     // Describe what the current mode is
-    println!("{}", "Generating a Schnorr key proof for the voter".green());
+    if config.interactive {
+        println!("{}", "Generating a Schnorr key proof for the voter".green());
+    }
 
     // Get the election private key of the voter
-    let election_prk = get_election_prk(rng)?;
+    let (election_prk, generated_prk) = election_prk(config, rng)?;
     let election_pbk = voter::generate_public_key(&election_prk);
 
     // Generate a Schnorr key proof
     let schnorr_key_proof = SchnorrKnowledgeProof::generate_key_proof(&election_prk, rng);
 
-    // Print out the information of the Schnorr key proof to the user
-    println!("\n");
-    println!("----------------------------------------");
-    println!("Please submit the following data to register your key:");
-    println!("Public key:         {}", election_pbk.solidity_repr());
-    println!("Schnorr key proof:  {}", schnorr_key_proof.solidity_repr());
-    println!("----------------------------------------");
-
-    Ok(())
-}
\ No newline at end of file
+    // Write out the information of the Schnorr key proof to the user
+    let mut output = Output::new("Please submit the following data to register your key:");
+    if let Some(generated_prk) = generated_prk {
+        output = output.field("Generated private key", "privateKey", generated_prk.as_str());
+    }
+    output
+        .field("Public key", "publicKey", election_pbk.solidity_repr())
+        .field("Schnorr key proof", "keyProof", schnorr_key_proof.solidity_repr())
+        .write(config.format, &config.output)
+}
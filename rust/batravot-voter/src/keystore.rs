@@ -0,0 +1,274 @@
+use std::{env, fs, io};
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use ark_ff::{BigInteger, PrimeField};
+use colored::Colorize;
+use rand::Rng;
+use zeroize::Zeroizing;
+use batravot_lib::{ScalarField, voter};
+use batravot_lib::keystore::{KdfParams, Keystore};
+use batravot_lib::representation::SolidityRepresentable;
+use crate::common::{election_prk, read_line};
+use crate::config::VoterConfig;
+use crate::output::Output;
+
+/// This function manages the encrypted keystore of the election private keys
+/// The action is one of the following:
+/// create - Generate a new election private key and store it under the label
+/// import - Store an existing election private key under the label, the key can come from any key source
+/// export - Decrypt the key with the label and write it in hex format
+/// list - Write the labels and the public keys of the stored keys, no password is needed
+pub(crate) fn keystore(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
+    // Describe what the current mode is
+    if config.interactive {
+        println!("{}", "Managing the keystore of the election private keys".green());
+    }
+
+    let action = match &config.action {
+        Some(action) => action.clone(),
+        None if config.interactive => read_line("Please select the action: create, import, export or list")?,
+        None => return Err("The keystore action is required in the non-interactive mode: create, import, export or list".to_string()),
+    };
+
+    match action.as_str() {
+        "create" => store_key(config, rng, false),
+        "import" => store_key(config, rng, true),
+        "export" => export_key(config),
+        "list" => list_keys(config),
+        _ => Err(format!("The keystore action {} is not supported, expected `create`, `import`, `export` or `list`", action)),
+    }
+}
+
+/// This function stores an election private key under the label
+/// A new key is generated, unless the key is imported from the key source of the configuration
+fn store_key(config: &VoterConfig, rng: &mut impl Rng, import: bool) -> Result<(), String> {
+    let label = config.or_prompt(config.label.clone(), "--label", || read_line("Please provide the label of the key:"))?;
+    // Check the label before the key is asked for
+    new_keystore_path(config, &label)?;
+
+    let election_prk = if import {
+        election_prk(config, rng)?.0
+    } else {
+        let mut election_private_key = Zeroizing::new([0u8; 32]);
+        rng.fill_bytes(election_private_key.as_mut());
+        Zeroizing::new(voter::convert_private_key(election_private_key.as_ref()))
+    };
+    save_key(config, &label, &election_prk, rng)
+}
+
+/// This function encrypts the election private key with a new password and writes it to the keystore under the label
+/// An existing key with the same label is never overwritten
+pub(crate) fn save_key(config: &VoterConfig, label: &str, election_prk: &ScalarField, rng: &mut impl Rng) -> Result<(), String> {
+    let path = new_keystore_path(config, label)?;
+    let password = new_password(config)?;
+    let keystore = Keystore::encrypt(label, election_prk, password.as_bytes(), kdf_params(), rng)?;
+
+    let directory = keystore_dir(config)?;
+    fs::create_dir_all(&directory)
+        .map_err(|err| format!("Error creating the keystore directory {}: {}", directory.display(), err))?;
+    write_keystore_file(&path, label, &keystore)?;
+
+    Output::new("The key has been stored in the keystore, use it with `--keystore <label>`:")
+        .field("Label", "label", label)
+        .field("Public key", "publicKey", keystore.public_key.solidity_repr())
+        .field("Keystore file", "keystoreFile", path.display().to_string())
+        .write(config.format, &config.output)
+}
+
+/// Returns the scrypt parameters of the new keys, the tests use light ones so that they are fast
+fn kdf_params() -> KdfParams {
+    if cfg!(test) {
+        KdfParams { log_n: 10, r: 8, p: 1 }
+    } else {
+        KdfParams::default()
+    }
+}
+
+/// Returns the file for a new key with the label, it is an error if there is already a key with the label
+/// This only reports a taken label early, `write_keystore_file` refuses to replace a file created since
+fn new_keystore_path(config: &VoterConfig, label: &str) -> Result<PathBuf, String> {
+    let path = keystore_path(config, label)?;
+    if path.exists() {
+        return Err(format!("A key with the label {} already exists in {}", label, path.display()));
+    }
+    Ok(path)
+}
+
+/// This function writes the keystore to a new file, only readable by the user
+/// The file is created in the same step as it is checked not to exist, so that a key written in between is never overwritten
+fn write_keystore_file(path: &Path, label: &str, keystore: &Keystore) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).map_err(|err| match err.kind() {
+        io::ErrorKind::AlreadyExists => format!("A key with the label {} already exists in {}", label, path.display()),
+        _ => format!("Error writing the keystore file {}: {}", path.display(), err),
+    })?;
+    file.write_all(format!("{}\n", keystore.to_json_string()).as_bytes())
+        .map_err(|err| format!("Error writing the keystore file {}: {}", path.display(), err))
+}
+
+/// This function decrypts the key with the label and writes it in hex format
+/// Anyone who sees the output can vote in the name of the voter, so it is only meant for backups
+fn export_key(config: &VoterConfig) -> Result<(), String> {
+    let label = config.or_prompt(config.label.clone(), "--label", || read_line("Please provide the label of the key:"))?;
+    let election_prk = decrypt_key(config, &label)?;
+    let election_private_key = Zeroizing::new(format!("0x{}", hex::encode(election_prk.into_repr().to_bytes_be())));
+
+    eprintln!("{}", "Warning: the private key is written in clear, anyone who sees it can vote with it".red());
+    Output::new("Please keep the exported private key secret:")
+        .field("Label", "label", label)
+        .field("Public key", "publicKey", voter::generate_public_key(&election_prk).solidity_repr())
+        .field("Private key", "privateKey", election_private_key.as_str())
+        .write(config.format, &config.output)
+}
+
+/// This function writes the label, the public key and the file of each key in the keystore
+/// The files that are not keystores are skipped with a warning
+fn list_keys(config: &VoterConfig) -> Result<(), String> {
+    let directory = keystore_dir(config)?;
+    let mut paths = match fs::read_dir(&directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+            .collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+
+    let mut outputs = Vec::new();
+    for path in paths {
+        match read_keystore_file(&path) {
+            Ok(keystore) => outputs.push(Output::new("Key:")
+                .field("Label", "label", keystore.label)
+                .field("Public key", "publicKey", keystore.public_key.solidity_repr())
+                .field("Keystore file", "keystoreFile", path.display().to_string())),
+            Err(err) => eprintln!("{}", format!("Skipping {}: {}", path.display(), err).red()),
+        }
+    }
+
+    if outputs.is_empty() {
+        eprintln!("There are no keys in the keystore {}", directory.display());
+    }
+    Output::write_list(&outputs, config.format, &config.output)
+}
+
+/// This function reads the keystore with the label and decrypts the election private key with the password
+pub(crate) fn decrypt_key(config: &VoterConfig, label: &str) -> Result<Zeroizing<ScalarField>, String> {
+    let keystore = read_keystore_file(&keystore_path(config, label)?)
+        .map_err(|err| format!("Error reading the key {} from the keystore: {}", label, err))?;
+    let password = read_password(config, &format!("Please provide the password of the key {}: ", label))?;
+    keystore.decrypt(password.as_bytes())
+}
+
+fn read_keystore_file(path: &Path) -> Result<Keystore, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Error reading {}: {}", path.display(), err))?;
+    let json = serde_json::from_str(&content)
+        .map_err(|err| format!("Error parsing {}: {}", path.display(), err))?;
+    Keystore::from_json(&json)
+}
+
+/// Returns the directory of the keystore, `$HOME/.batravot/keystore` unless it is set with `--keystore-dir`
+fn keystore_dir(config: &VoterConfig) -> Result<PathBuf, String> {
+    match &config.keystore_dir {
+        Some(directory) => Ok(PathBuf::from(directory)),
+        None => env::var("HOME").map(|home| PathBuf::from(home).join(".batravot").join("keystore"))
+            .map_err(|_| "The home directory is not known, please set the keystore directory with `--keystore-dir`".to_string()),
+    }
+}
+
+/// Returns the file of the key with the label, the label is the name of the file
+fn keystore_path(config: &VoterConfig, label: &str) -> Result<PathBuf, String> {
+    let valid = !label.is_empty() && !label.starts_with('.')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid {
+        return Err(format!("Invalid label `{}`, a label can only contain letters, digits, `-`, `_` and `.`", label));
+    }
+    Ok(keystore_dir(config)?.join(format!("{}.json", label)))
+}
+
+/// This function reads the password of the keystore, from the `--password-file` file or without echo from the terminal
+/// Only the line break at the end of the file is removed, so that the password can contain spaces
+fn read_password(config: &VoterConfig, prompt: &str) -> Result<Zeroizing<String>, String> {
+    match &config.password_file {
+        Some(path) => {
+            let content = Zeroizing::new(fs::read_to_string(path)
+                .map_err(|err| format!("Error reading the password file: {}", err))?);
+            Ok(Zeroizing::new(content.trim_end_matches(['\r', '\n']).to_string()))
+        }
+        None => config.or_prompt(None, "--password-file", || rpassword::prompt_password(prompt)
+            .map(Zeroizing::new)
+            .map_err(|err| format!("Error reading the password: {}", err))),
+    }
+}
+
+/// This function reads the password of a new key, the password is asked twice when it is typed
+fn new_password(config: &VoterConfig) -> Result<Zeroizing<String>, String> {
+    let password = read_password(config, "Please choose the password of the key: ")?;
+    if password.is_empty() {
+        return Err("The password of the key must not be empty".to_string());
+    }
+    if config.password_file.is_none() && *read_password(config, "Please repeat the password: ")? != *password {
+        return Err("The passwords do not match".to_string());
+    }
+    Ok(password)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use ark_std::UniformRand;
+    use crate::config::OutputFormat;
+    use super::*;
+
+    /// Returns the config of a keystore in the directory, with the password in a file
+    fn config(directory: &Path) -> VoterConfig {
+        let password_file = directory.join("password.txt");
+        fs::write(&password_file, "correct horse\n").unwrap();
+        VoterConfig {
+            interactive: false,
+            format: OutputFormat::Json,
+            output: Some(directory.join("output.json").to_string_lossy().to_string()),
+            keystore_dir: Some(directory.join("keystore").to_string_lossy().to_string()),
+            password_file: Some(password_file.to_string_lossy().to_string()),
+            ..VoterConfig::default()
+        }
+    }
+
+    #[test]
+    fn stores_and_decrypts_a_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        let rng = &mut ark_std::test_rng();
+        let election_prk = ScalarField::rand(rng);
+
+        save_key(&config, "main", &election_prk, rng).unwrap();
+        assert_eq!(*decrypt_key(&config, "main").unwrap(), election_prk);
+
+        // The key is listed with its public key, and is never overwritten
+        list_keys(&config).unwrap();
+        let listed: serde_json::Value = serde_json::from_str(&fs::read_to_string(dir.path().join("output.json")).unwrap()).unwrap();
+        assert_eq!(listed[0]["publicKey"], voter::generate_public_key(&election_prk).solidity_repr());
+        assert!(save_key(&config, "main", &ScalarField::rand(rng), rng).is_err());
+        assert_eq!(*decrypt_key(&config, "main").unwrap(), election_prk);
+
+        // The password of the key is checked
+        fs::write(dir.path().join("password.txt"), "wrong horse").unwrap();
+        assert!(decrypt_key(&config, "main").is_err());
+        assert!(decrypt_key(&config, "other").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_labels() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        assert!(keystore_path(&config, "main-2_backup.v1").is_ok());
+        for label in ["", ".hidden", "../main", "main key"] {
+            assert!(keystore_path(&config, label).is_err());
+        }
+    }
+}
@@ -5,28 +5,62 @@ mod generate_ballot;
 mod generate_schnorr;
 mod generate_specifiers;
 mod common;
+mod config;
+mod output;
+mod submit_ballot;
+mod keystore;
+mod backup;
+mod verify;
 
 use generate_schnorr::generate_schnorr_key_proof;
 use generate_ballot::generate_ballot;
 use generate_specifiers::generate_specifiers;
+use submit_ballot::{check_receipt, submit_ballot};
+use keystore::keystore;
+use backup::backup;
+use verify::verify;
+
+pub use config::{KeySource, OutputFormat, VoterConfig};
 
 /// The main logic function, it selects the correct function based on the command line arguments
-pub fn run(mode: &String, rng: &mut impl Rng) -> Result<(), String> {
+pub fn run(mode: &str, config: VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
 
     let mode = ExecutionMode::from_str(mode)?;
 
-    // Clear the screen
-    eprint!("{}[2J", 27 as char);
+    // Only the modes with actions accept an argument that is not a flag
+    if let (false, Some(action)) = (mode.takes_action(), &config.action) {
+        return Err(format!("The {} mode does not take an action, unexpected argument `{}`", mode, action));
+    }
+
+    // Clear the screen, unless the application is used from a script
+    if config.interactive && config.format == OutputFormat::Text && config.output.is_none() {
+        eprint!("{}[2J", 27 as char);
+    }
 
     match mode {
         ExecutionMode::GenerateBallot => {
-            generate_ballot(rng)
+            generate_ballot(&config, rng)
         },
         ExecutionMode::GenerateKeyProof => {
-            generate_schnorr_key_proof(rng)
+            generate_schnorr_key_proof(&config, rng)
         },
         ExecutionMode::GenerateSpecifiers => {
-            generate_specifiers()
+            generate_specifiers(&config)
+        },
+        ExecutionMode::SubmitBallot => {
+            submit_ballot(&config, rng)
+        },
+        ExecutionMode::CheckReceipt => {
+            check_receipt(&config)
+        },
+        ExecutionMode::Keystore => {
+            keystore(&config, rng)
+        },
+        ExecutionMode::Backup => {
+            backup(&config, rng)
+        },
+        ExecutionMode::Verify => {
+            verify(&config)
         },
     }
 
@@ -39,10 +73,20 @@ pub fn run(mode: &String, rng: &mut impl Rng) -> Result<(), String> {
 /// The default is to generate a vote ballot
 /// Another generates a Schnorr key proof to verify that they own the private key
 /// Another generates the specifiers for the election based on the election id
+/// Another generates a ballot and submits it to the batcher server
+/// Another checks that the ballot of a receipt is included in the batch of the batcher server
+/// Another manages the encrypted keystore of the election private keys
+/// Another splits the election private key into secret shares and reconstructs it from them
+/// Another verifies a ballot and a Schnorr key proof before they are sent
 pub enum ExecutionMode {
     GenerateBallot,
     GenerateKeyProof,
     GenerateSpecifiers,
+    SubmitBallot,
+    CheckReceipt,
+    Keystore,
+    Backup,
+    Verify,
 }
 
 impl fmt::Display for ExecutionMode {
@@ -51,6 +95,11 @@ impl fmt::Display for ExecutionMode {
             ExecutionMode::GenerateBallot => "vote",
             ExecutionMode::GenerateKeyProof => "keyproof",
             ExecutionMode::GenerateSpecifiers => "spec",
+            ExecutionMode::SubmitBallot => "submit",
+            ExecutionMode::CheckReceipt => "check",
+            ExecutionMode::Keystore => "keystore",
+            ExecutionMode::Backup => "backup",
+            ExecutionMode::Verify => "verify",
         };
         write!(f, "{}", str)
     }
@@ -66,8 +115,39 @@ impl ExecutionMode {
             Ok(ExecutionMode::GenerateKeyProof)
         } else if s == ExecutionMode::GenerateSpecifiers.to_string() {
             Ok(ExecutionMode::GenerateSpecifiers)
+        } else if s == ExecutionMode::SubmitBallot.to_string() {
+            Ok(ExecutionMode::SubmitBallot)
+        } else if s == ExecutionMode::CheckReceipt.to_string() {
+            Ok(ExecutionMode::CheckReceipt)
+        } else if s == ExecutionMode::Keystore.to_string() {
+            Ok(ExecutionMode::Keystore)
+        } else if s == ExecutionMode::Backup.to_string() {
+            Ok(ExecutionMode::Backup)
+        } else if s == ExecutionMode::Verify.to_string() {
+            Ok(ExecutionMode::Verify)
         } else {
             Err(format!("The execution mode {} is not supported", s))
         }
     }
+
+    /// Returns whether the execution mode takes an action, e.g. `create` in `keystore create`
+    pub fn takes_action(&self) -> bool {
+        matches!(self, ExecutionMode::Keystore | ExecutionMode::Backup)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_an_action_in_the_modes_without_actions() {
+        let config = VoterConfig::from_args(&["create".to_string(), "--non-interactive".to_string()]).unwrap();
+        let err = run("vote", config, &mut rand::thread_rng()).unwrap_err();
+        assert_eq!(err, "The vote mode does not take an action, unexpected argument `create`");
+
+        assert!(ExecutionMode::Keystore.takes_action() && ExecutionMode::Backup.takes_action());
+        assert!(!ExecutionMode::GenerateBallot.takes_action() && !ExecutionMode::Verify.takes_action());
+    }
 }
@@ -1,5 +1,5 @@
 use std::{env, process};
-use batravot_voter::{ExecutionMode, run};
+use batravot_voter::{ExecutionMode, run, VoterConfig};
 
 /// This is the main function that initiates the Voter application
 /// If there are any errors in the execution and prints them in error stream
@@ -12,17 +12,69 @@ fn main() {
     // This is used to determine the execution mode of the application
     let args: Vec<String> = env::args().collect();
     let mode = if args.len() < 2 {
-        eprintln!("Please provide an execution mode. The available modes are:");
-        eprintln!("  - {}:    Generate a Vote Ballot", ExecutionMode::GenerateBallot);
-        eprintln!("  - {}:    Generate a Schnorr Key Proof", ExecutionMode::GenerateKeyProof);
-        eprintln!("  - {}:    Generate specifiers for the Election Id", ExecutionMode::GenerateSpecifiers);
+        print_usage();
         process::exit(1);
     } else {
         &args[1]
     };
 
-    run(mode, &mut rng).unwrap_or_else(|err| {
+    // The rest of the arguments are the flags of the execution mode
+    let config = VoterConfig::from_args(&args[2..]).unwrap_or_else(|err| {
+        eprintln!("Error: {}", err);
+        print_usage();
+        process::exit(1);
+    });
+
+    run(mode, config, &mut rng).unwrap_or_else(|err| {
         eprintln!("An error occurred: {}", err);
         process::exit(1);
     });
-}
\ No newline at end of file
+}
+
+/// This function prints the available execution modes and flags
+fn print_usage() {
+    eprintln!("Usage: batravot-voter <mode> [flags]");
+    eprintln!("Please provide an execution mode. The available modes are:");
+    eprintln!("  - {}:    Generate a Vote Ballot", ExecutionMode::GenerateBallot);
+    eprintln!("  - {}:    Generate a Schnorr Key Proof", ExecutionMode::GenerateKeyProof);
+    eprintln!("  - {}:    Generate specifiers for the Election Id", ExecutionMode::GenerateSpecifiers);
+    eprintln!("  - {}:  Generate a Vote Ballot and submit it to the batcher", ExecutionMode::SubmitBallot);
+    eprintln!("  - {}:   Check that a submitted ballot is included in the batch", ExecutionMode::CheckReceipt);
+    eprintln!("  - {}: Manage the encrypted keystore: `keystore create|import|export|list`", ExecutionMode::Keystore);
+    eprintln!("  - {}:  Back up the election private key in secret shares: `backup split|combine`", ExecutionMode::Backup);
+    eprintln!("  - {}:  Verify a ballot or a Schnorr key proof before sending it", ExecutionMode::Verify);
+    eprintln!("The available flags are, any missing value is asked for interactively:");
+    eprintln!("  --key <hex>             The election private key");
+    eprintln!("  --key-file <path>       The file with the election private key");
+    eprintln!("  --new-key               Generate a new election private key");
+    eprintln!("  --key-signature <hex>   Derive the election private key from the wallet signature of the key message");
+    eprintln!("  --derive-key            Derive the election private key from the Ethereum private key of `--sign-key`");
+    eprintln!("  --keystore <label>      Decrypt the election private key with the label from the keystore");
+    eprintln!("  --contract <address>    The election contract, the election private key is derived for it and the ballots are signed for it");
+    eprintln!("  --chain-id <id>         The chain of the election contract, the ballots are signed for it");
+    eprintln!("  --election-id <id>      The election id");
+    eprintln!("  --vote <for|against>    The vote");
+    eprintln!("  --format <format>       The output format: text, json, jsonl or csv, text by default");
+    eprintln!("  --output <path>         Write the output to the file instead of the standard output");
+    eprintln!("  --non-interactive       Never prompt, fail if a value is missing instead");
+    eprintln!("  --address <address>     The Ethereum address of the voter, for `vote` and `submit`");
+    eprintln!("  --sign-key <hex>        The Ethereum private key that signs the ballot, its address is the address of the ballot");
+    eprintln!("  --sign-key-file <path>  The file with the Ethereum private key that signs the ballot");
+    eprintln!("  --sequence <n>          The sequence of the signed ballot, higher than the previous ones, the current time in milliseconds by default");
+    eprintln!("  --weight <n>            The declared weight of a weighted ballot, the vote proof is scaled by it, for `vote`, `submit` and `verify`");
+    eprintln!("  --approve <options>     The options approved by an approval ballot, e.g. `0,2`, instead of the vote, for `vote`");
+    eprintln!("  --rank <options>        The options ranked by a ranked ballot, the most preferred first, e.g. `2,0,1`, instead of the vote, for `vote`");
+    eprintln!("  --batcher <url>         The URL of the batcher server, for `submit` and `check`");
+    eprintln!("  --batcher-address <address>  The Ethereum account that must sign the receipts of the batcher, for `submit` and `check`");
+    eprintln!("  --receipt <path>        The receipt file of the submitted ballot, for `submit` and `check`");
+    eprintln!("  --label <label>         The label of the key in the keystore, for `keystore`");
+    eprintln!("  --keystore-dir <path>   The directory of the keystore, `$HOME/.batravot/keystore` by default");
+    eprintln!("  --password-file <path>  The file with the password of the keystore, instead of the prompt");
+    eprintln!("  --shares <n>            The number of shares the key is split into, for `backup split`");
+    eprintln!("  --threshold <t>         The number of shares needed to reconstruct the key, for `backup split`");
+    eprintln!("  --share <hex>           A share of the key, repeated for each share, for `backup combine`");
+    eprintln!("  --ballot <line>         The line of the ballot given to the batcher, for `verify`");
+    eprintln!("  --public-key <point>    The public key of the voter, for `verify`");
+    eprintln!("  --vote-proof <point>    The vote proof of the ballot, for `verify`");
+    eprintln!("  --key-proof <proof>     The Schnorr key proof of the public key, for `verify`");
+}
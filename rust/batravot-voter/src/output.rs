@@ -0,0 +1,183 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use serde_json::{Map, Value};
+use zeroize::{Zeroize, Zeroizing};
+use crate::config::OutputFormat;
+
+/// The result of an execution of the voter application
+/// It is a list of fields, which are written either as labelled lines, as a JSON object or as a CSV record
+/// title - The line describing what the user should do with the data, only used in the text format
+/// version - The version of the format of the data, written as the first field in the other formats
+/// The fields may hold secrets, e.g. a generated private key, so their strings and the written content are cleared from memory when they are dropped
+pub(crate) struct Output {
+    title: &'static str,
+    version: Option<u64>,
+    fields: Vec<Field>,
+}
+
+/// A single field of the output
+/// label - The label in the text format
+/// key - The key in the JSON format and the column in the CSV format
+struct Field {
+    label: &'static str,
+    key: &'static str,
+    value: Value,
+}
+
+impl Output {
+    pub(crate) fn new(title: &'static str) -> Output {
+        Output {
+            title,
+            version: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub(crate) fn version(mut self, version: u64) -> Output {
+        self.version = Some(version);
+        self
+    }
+
+    pub(crate) fn field(mut self, label: &'static str, key: &'static str, value: impl Into<Value>) -> Output {
+        self.fields.push(Field { label, key, value: value.into() });
+        self
+    }
+
+    /// This function writes the output in the provided format
+    /// If the path is provided, the output is written to the file, otherwise it is printed to the standard output
+    /// In the JSON Lines and CSV formats, the output is appended to the file, so that a file can collect several outputs
+    pub(crate) fn write(&self, format: OutputFormat, path: &Option<String>) -> Result<(), String> {
+        let header = csv_header(format, path);
+        let content = match format {
+            OutputFormat::Text => self.text(),
+            OutputFormat::Json | OutputFormat::JsonLines => json_line(self.object()),
+            OutputFormat::Csv => self.csv(header)?,
+        };
+        write_content(&content, format, path)
+    }
+
+    /// This function writes several outputs of the same fields, e.g. the keys of the keystore
+    /// In the JSON format, the outputs are written as an array, in the CSV format, the header is written once
+    pub(crate) fn write_list(outputs: &[Output], format: OutputFormat, path: &Option<String>) -> Result<(), String> {
+        let header = csv_header(format, path);
+        let mut content = Zeroizing::new(String::new());
+        match format {
+            OutputFormat::Text => outputs.iter().for_each(|output| content.push_str(&output.text())),
+            OutputFormat::Json => content = json_line(Value::Array(outputs.iter().map(|output| output.object()).collect())),
+            OutputFormat::JsonLines => outputs.iter().for_each(|output| content.push_str(&json_line(output.object()))),
+            OutputFormat::Csv => {
+                for (index, output) in outputs.iter().enumerate() {
+                    content.push_str(&output.csv(header && index == 0)?);
+                }
+            }
+        }
+        write_content(&content, format, path)
+    }
+
+    fn text(&self) -> Zeroizing<String> {
+        let width = self.fields.iter().map(|field| field.label.len()).max().unwrap_or(0) + 2;
+
+        let mut text = Zeroizing::new(String::from("\n\n----------------------------------------\n"));
+        text.push_str(&format!("{}\n", self.title));
+        for field in self.fields.iter() {
+            let line = Zeroizing::new(format!("{:<width$}{}\n", format!("{}:", field.label), text_value(&field.value).as_str(), width = width));
+            text.push_str(&line);
+        }
+        text.push_str("----------------------------------------\n");
+        text
+    }
+
+    fn object(&self) -> Value {
+        let mut object = Map::new();
+        if let Some(version) = self.version {
+            object.insert("version".to_string(), Value::from(version));
+        }
+        for field in self.fields.iter() {
+            object.insert(field.key.to_string(), field.value.clone());
+        }
+        Value::Object(object)
+    }
+
+    fn csv(&self, header: bool) -> Result<Zeroizing<String>, String> {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        if let Some(version) = self.version {
+            keys.push("version".to_string());
+            values.push(Zeroizing::new(version.to_string()));
+        }
+        for field in self.fields.iter() {
+            keys.push(field.key.to_string());
+            values.push(text_value(&field.value));
+        }
+        let values: Vec<&str> = values.iter().map(|value| value.as_str()).collect();
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        if header {
+            writer.write_record(&keys).map_err(|err| format!("Error writing the CSV header: {}", err))?;
+        }
+        writer.write_record(&values).map_err(|err| format!("Error writing the CSV record: {}", err))?;
+        let content = writer.into_inner().map_err(|err| format!("Error writing the CSV record: {}", err))?;
+        String::from_utf8(content).map(Zeroizing::new).map_err(|err| format!("Error writing the CSV record: {}", err))
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        self.fields.iter_mut().for_each(|field| zeroize_value(&mut field.value));
+    }
+}
+
+/// This function clears the strings of the value from memory, the other values are not secret
+fn zeroize_value(value: &mut Value) {
+    match value {
+        Value::String(value) => value.zeroize(),
+        Value::Array(values) => values.iter_mut().for_each(zeroize_value),
+        Value::Object(object) => object.values_mut().for_each(zeroize_value),
+        _ => {}
+    }
+}
+
+/// Returns the JSON of the value followed by a new line, the value is cleared from memory afterwards
+fn json_line(mut value: Value) -> Zeroizing<String> {
+    let line = Zeroizing::new(format!("{}\n", value));
+    zeroize_value(&mut value);
+    line
+}
+
+/// Returns whether the CSV header is written, it is only written at the start of the file
+fn csv_header(format: OutputFormat, path: &Option<String>) -> bool {
+    match path {
+        Some(path) if appends(format) => fs::metadata(path).map(|metadata| metadata.len() == 0).unwrap_or(true),
+        _ => true,
+    }
+}
+
+fn appends(format: OutputFormat) -> bool {
+    matches!(format, OutputFormat::JsonLines | OutputFormat::Csv)
+}
+
+/// This function writes the content to the file, or prints it to the standard output if there is no file
+fn write_content(content: &str, format: OutputFormat, path: &Option<String>) -> Result<(), String> {
+    match path {
+        Some(path) => {
+            let written = if appends(format) {
+                OpenOptions::new().create(true).append(true).open(path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()))
+            } else {
+                fs::write(path, content)
+            };
+            written.map_err(|err| format!("Error writing the output to {}: {}", path, err))?;
+            eprintln!("The output has been written to {}", path);
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Returns the value as it is shown in the text and CSV formats, the strings are not quoted
+fn text_value(value: &Value) -> Zeroizing<String> {
+    Zeroizing::new(match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    })
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::str::FromStr;
+use colored::Colorize;
+use rand::Rng;
+use serde_json::{json, Value};
+use web3::types::{Address, H256};
+use batravot_lib::ballot::{ballot_hash, BallotReceipt};
+use batravot_lib::ethereum::EthSignature;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::common::read_line;
+use crate::config::VoterConfig;
+use crate::generate_ballot::new_ballot;
+use crate::output::Output;
+
+/// This function generates a ballot for the voter and submits it to the batcher server
+/// It will request the user to provide the batcher URL and the ballot data, unless they are set in the configuration
+/// If the batcher accepts the ballot, the receipt is stored in the `--receipt` file, so that the voter can check later that their ballot is included
+/// The receipt must be for the ballot that was sent and be signed by the batcher, so that the voter can prove that the batcher admitted it
+pub(crate) fn submit_ballot(config: &VoterConfig, rng: &mut impl Rng) -> Result<(), String> {
+    // Describe what the current mode is
+    if config.interactive {
+        println!("{}", "Submitting a ballot to the batcher".green());
+    }
+
+    // The batcher server only accepts `For` and `Against` ballots, approval and ranked ballots are batched from a file
+    if config.approve.is_some() || config.rank.is_some() {
+        return Err("Approval and ranked ballots can not be submitted to the batcher server, generate them with `vote` instead".to_string());
+    }
+
+    let batcher = config.or_prompt(config.batcher.clone(), "--batcher", || read_line("Please provide the URL of the batcher:"))?;
+    let ballot = new_ballot(config, rng)?;
+    let address = ballot.address;
+
+    let mut body = json!({
+        "publicKey": ballot.public_key.solidity_repr(),
+        "vote": format!("{:?}", ballot.vote),
+        "voteProof": ballot.vote_proof.solidity_repr(),
+        "dleqProof": ballot.dleq_proof.solidity_repr(),
+        "address": address.solidity_repr(),
+    });
+    if let Some(weight) = ballot.weight {
+        body["weight"] = json!(weight);
+    }
+    if let (Some(sequence), Some(signature)) = (ballot.sequence, &ballot.signature) {
+        body["sequence"] = json!(sequence);
+        body["signature"] = json!(signature.solidity_repr());
+    }
+    let url = format!("{}/elections/{}/ballots", batcher.trim_end_matches('/'), ballot.election_id);
+    let (status, response) = send(ureq::post(&url).set("Content-Type", "application/json"), Some(body.to_string()))?;
+    if status != 200 {
+        return Err(format!("The batcher rejected the ballot: {}", reason(&response)));
+    }
+
+    // The receipt must be for the ballot that was sent, and be signed by the batcher
+    let mut receipt = response["receipt"].clone();
+    if !receipt.is_object() {
+        return Err(format!("The batcher did not return a receipt: {}", response));
+    }
+    let signed_receipt = check_receipt_signature(&receipt, config)?;
+    let sent_hash = ballot_hash(&ballot.public_key, ballot.vote, &ballot.vote_proof, &address);
+    if signed_receipt.ballot_hash != sent_hash || signed_receipt.election_id != ballot.election_id || signed_receipt.address != address {
+        return Err(format!("The batcher returned the receipt of another ballot: {}", receipt));
+    }
+
+    // Store the receipt together with the batcher, so that the check only needs the receipt file
+    receipt["batcher"] = json!(batcher);
+    receipt["vote"] = json!(format!("{:?}", ballot.vote));
+    let receipt_file = config.receipt.clone()
+        .unwrap_or(format!("receipt-{}-{}.json", ballot.election_id, address.solidity_repr()));
+    fs::write(&receipt_file, format!("{:#}\n", receipt))
+        .map_err(|err| format!("Error writing the receipt to {}: {}", receipt_file, err))?;
+
+    let mut output = Output::new("The batcher accepted the ballot, keep the receipt to check that it is included:");
+    if let Some(generated_prk) = &ballot.generated_prk {
+        output = output.field("Generated private key", "privateKey", generated_prk.as_str());
+    }
+    output
+        .field("Election ID", "electionId", ballot.election_id)
+        .field("Address", "address", address.solidity_repr())
+        .field("Vote", "vote", format!("{:?}", ballot.vote))
+        .field("Sequence", "sequence", receipt["sequence"].clone())
+        .field("Ballot hash", "ballotHash", receipt["ballotHash"].as_str().unwrap_or_default())
+        .field("Batcher address", "batcherAddress", receipt["batcherAddress"].as_str().unwrap_or_default())
+        .field("Receipt file", "receiptFile", receipt_file.as_str())
+        .write(config.format, &config.output)
+}
+
+/// This function checks that the ballot of a receipt is still in the batch of the batcher
+/// The ballot is no longer in the batch if the batcher lost it, or if the voter has submitted another ballot since
+/// The batcher of the receipt is used, unless another one is set with `--batcher`, and the signature of the receipt is checked again
+pub(crate) fn check_receipt(config: &VoterConfig) -> Result<(), String> {
+    // Describe what the current mode is
+    if config.interactive {
+        println!("{}", "Checking the receipt of a ballot".green());
+    }
+
+    let receipt_file = config.or_prompt(config.receipt.clone(), "--receipt", || read_line("Please provide the path to the receipt file:"))?;
+    let receipt: Value = fs::read_to_string(&receipt_file)
+        .map_err(|err| format!("Error reading the receipt file: {}", err))
+        .and_then(|content| serde_json::from_str(&content).map_err(|err| format!("Error parsing the receipt: {}", err)))?;
+    let field = |name: &str| receipt.get(name).filter(|value| !value.is_null())
+        .ok_or(format!("The receipt has no `{}` field", name));
+    check_receipt_signature(&receipt, config)?;
+
+    let batcher = match &config.batcher {
+        Some(batcher) => batcher.clone(),
+        None => field("batcher")?.as_str().unwrap_or_default().to_string(),
+    };
+    let url = format!("{}/elections/{}/ballots/{}",
+        batcher.trim_end_matches('/'), field("electionId")?, field("address")?.as_str().unwrap_or_default());
+    let (status, response) = send(ureq::get(&url), None)?;
+    if status != 200 {
+        return Err(format!("The ballot is not in the batch: {}", reason(&response)));
+    }
+
+    let included = &response["receipt"];
+    if included["ballotHash"] != *field("ballotHash")? {
+        return Err(format!("The ballot has been replaced in the batch by the ballot #{} of the same voter", included["sequence"]));
+    }
+
+    Output::new("The ballot is included in the batch:")
+        .field("Status", "status", "included")
+        .field("Election ID", "electionId", field("electionId")?.clone())
+        .field("Address", "address", field("address")?.as_str().unwrap_or_default())
+        .field("Sequence", "sequence", field("sequence")?.clone())
+        .field("Ballot hash", "ballotHash", field("ballotHash")?.as_str().unwrap_or_default())
+        .write(config.format, &config.output)
+}
+
+/// This function checks that the receipt is signed by the account of the batcher in its `batcherAddress` field
+/// If `--batcher-address` is set, the receipt must be signed by that account, so that another server can not issue it
+/// Returns the signed receipt
+fn check_receipt_signature(receipt: &Value, config: &VoterConfig) -> Result<BallotReceipt, String> {
+    let field = |name: &str| receipt.get(name).and_then(|value| value.as_str())
+        .ok_or(format!("The receipt has no `{}` field", name));
+    let signed_receipt = BallotReceipt {
+        election_id: receipt.get("electionId").and_then(|value| value.as_u64())
+            .ok_or("The receipt has no `electionId` field")?,
+        address: Address::from_str_c(field("address")?)
+            .map_err(|err| format!("Error parsing the address of the receipt: {}", err))?,
+        sequence: receipt.get("sequence").and_then(|value| value.as_u64())
+            .ok_or("The receipt has no `sequence` field")?,
+        ballot_hash: H256::from_str(field("ballotHash")?)
+            .map_err(|err| format!("Error parsing the ballot hash of the receipt: {}", err))?,
+    };
+    let batcher_address = Address::from_str_c(field("batcherAddress")?)
+        .map_err(|err| format!("Error parsing the batcher address of the receipt: {}", err))?;
+    let signature = EthSignature::from_str_c(field("signature")?)
+        .map_err(|err| format!("Error parsing the signature of the receipt: {}", err))?;
+
+    if signed_receipt.recover_signer(&signature)? != batcher_address {
+        return Err(format!("The receipt is not signed by the batcher account {}", batcher_address.solidity_repr()));
+    }
+    if let Some(expected) = config.batcher_address {
+        if batcher_address != expected {
+            return Err(format!("The receipt is signed by the account {}, not by the batcher account {}", batcher_address.solidity_repr(), expected.solidity_repr()));
+        }
+    }
+    Ok(signed_receipt)
+}
+
+/// This function sends the request to the batcher and returns the status code with the JSON response
+/// The error responses of the batcher are returned as well, only the connection errors are errors
+fn send(request: ureq::Request, body: Option<String>) -> Result<(u16, Value), String> {
+    let url = request.url().to_string();
+    let result = match body {
+        Some(body) => request.send_string(&body),
+        None => request.call(),
+    };
+    let response = match result {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(err) => return Err(format!("Error sending the request to {}: {}", url, err)),
+    };
+
+    let status = response.status();
+    let content = response.into_string()
+        .map_err(|err| format!("Error reading the response of the batcher: {}", err))?;
+    let json = serde_json::from_str(&content)
+        .map_err(|err| format!("The batcher returned an invalid response ({}): {}", status, err))?;
+    Ok((status, json))
+}
+
+/// Returns the reason the batcher gave in an error response
+fn reason(response: &Value) -> String {
+    response["reason"].as_str().map(|reason| reason.to_string()).unwrap_or(response.to_string())
+}
@@ -0,0 +1,329 @@
+use ark_ec::ProjectiveCurve;
+use ark_ff::Zero;
+use colored::Colorize;
+use batravot_lib::{ElectionSpecifiers, G1, SchnorrKnowledgeProof, Vote, verifier};
+use batravot_lib::ballot::BallotLine;
+use batravot_lib::ethereum::TypedBallot;
+use batravot_lib::representation::{FromStrCustom, SolidityRepresentable};
+use crate::common::{ballot_domain, get_election_id, read_line};
+use crate::config::VoterConfig;
+use crate::generate_ballot::get_vote;
+use crate::output::Output;
+
+/// This function checks a ballot and a Schnorr key proof, so that the voter can confirm them before sending them
+/// The ballot is either the line given to the batcher, `--ballot`, or its parts, `--public-key`, `--vote` and `--vote-proof`
+/// The vote proof is checked for the election id with the pairing check of the verifier, the key proof is checked for the public key
+/// The result of each check is written as `pass` or `fail`, and a failed check is an error, so that scripts can rely on the exit code
+/// A ballot line without a signature fails as well, its signature is written as `missing`
+pub(crate) fn verify(config: &VoterConfig) -> Result<(), String> {
+    // Describe what the current mode is
+    if config.interactive {
+        println!("{}", "Verifying a ballot or a key proof".green());
+    }
+
+    let (check_ballot, check_key_proof) = match (config.ballot.is_some() || config.vote_proof.is_some(), config.key_proof.is_some()) {
+        (false, false) if config.interactive => get_verify_choice()?,
+        (false, false) => return Err("Nothing to verify, please provide `--ballot`, `--vote-proof` or `--key-proof`".to_string()),
+        checks => checks,
+    };
+
+    let ballot = match &config.ballot {
+        Some(line) => Some(parse_ballot_line(line)?),
+        None => None,
+    };
+    let public_key = match (&ballot, &config.public_key) {
+        (Some(ballot), _) => ballot.public_key,
+        (None, Some(public_key)) => parse_point(public_key, "public key")?,
+        (None, None) => config.or_prompt(None, "--public-key", || read_point("Please provide the public key:", "public key"))?,
+    };
+
+    let mut output = Output::new("The result of the verification:")
+        .field("Public key", "publicKey", public_key.solidity_repr());
+    let mut passed = true;
+
+    if check_ballot {
+        let vote = match &ballot {
+            Some(ballot) => ballot.vote,
+            None => config.or_prompt(config.vote, "--vote", get_vote)?,
+        };
+        let vote_proof = match (&ballot, &config.vote_proof) {
+            (Some(ballot), _) => ballot.vote_proof,
+            (None, Some(vote_proof)) => parse_point(vote_proof, "vote proof")?,
+            (None, None) => config.or_prompt(None, "--vote-proof", || read_point("Please provide the vote proof:", "vote proof"))?,
+        };
+        let election_id = config.or_prompt(config.election_id, "--election-id", get_election_id)?;
+        let weight = match &ballot {
+            Some(ballot) => ballot.weight,
+            None => config.weight,
+        };
+
+        // The proof of a single vote is the proof of an election with one voter
+        // A weighted vote proof is checked against the public key scaled by the weight
+        let specifiers = ElectionSpecifiers::from(election_id);
+        let proof_key = match weight {
+            Some(weight) => verifier::weighted_public_key(&public_key, weight),
+            None => public_key,
+        };
+        let vote_proof_valid = match vote {
+            Vote::For => verifier::validate_election_proof(&vec![&proof_key], &Vec::new(), &vote_proof, &specifiers),
+            Vote::Against => verifier::validate_election_proof(&Vec::new(), &vec![&proof_key], &vote_proof, &specifiers),
+        };
+        passed &= vote_proof_valid;
+        output = output
+            .field("Election ID", "electionId", election_id)
+            .field("Vote", "vote", format!("{:?}", vote));
+        if let Some(weight) = weight {
+            output = output.field("Weight", "weight", weight);
+        }
+        output = output.field("Vote proof", "voteProof", result(vote_proof_valid));
+
+        // The signature binds the ballot to the address, it is only in the line of a signed ballot
+        if let Some(ballot) = &ballot {
+            output = output.field("Address", "address", ballot.address.solidity_repr());
+            match ballot.signature {
+                Some((sequence, signature)) => {
+                    let typed_ballot = TypedBallot { election_id, vote, vote_proof, sequence };
+                    let signature_valid = typed_ballot.recover_signer(&ballot_domain(config)?, &signature).is_ok_and(|signer| signer == ballot.address);
+                    passed &= signature_valid;
+                    output = output
+                        .field("Sequence", "sequence", sequence)
+                        .field("Signature", "signature", result(signature_valid));
+                }
+                None => {
+                    // The batcher rejects the unsigned ballots unless it allows them, so the ballot may not be admitted
+                    passed = false;
+                    output = output.field("Signature", "signature", "missing");
+                }
+            }
+
+            // The DLEQ proof is what the batcher checks instead of the pairing, so it must hold as well
+            if let Some(dleq_proof) = &ballot.dleq_proof {
+                let vote_specifier = match vote {
+                    Vote::For => &specifiers.forr.0,
+                    Vote::Against => &specifiers.against.0,
+                };
+                let dleq_proof_valid = dleq_proof.verify(&proof_key, vote_specifier, &vote_proof);
+                passed &= dleq_proof_valid;
+                output = output.field("DLEQ proof", "dleqProof", result(dleq_proof_valid));
+            }
+        }
+    }
+
+    if check_key_proof {
+        let key_proof = match &config.key_proof {
+            Some(key_proof) => parse_key_proof(key_proof)?,
+            None => read_key_proof()?,
+        };
+        let key_proof_valid = key_proof.verify(&public_key);
+        passed &= key_proof_valid;
+        output = output.field("Key proof", "keyProof", result(key_proof_valid));
+    }
+
+    output
+        .field("Result", "result", result(passed))
+        .write(config.format, &config.output)?;
+    if !passed {
+        return Err("The verification failed".to_string());
+    }
+    Ok(())
+}
+
+fn result(valid: bool) -> &'static str {
+    if valid { "pass" } else { "fail" }
+}
+
+/// This function parses the line of a ballot given to the batcher, as written by the `vote` mode
+/// The line is parsed as the batcher parses it, the points must also be valid points of the curve
+fn parse_ballot_line(line: &str) -> Result<BallotLine, String> {
+    let ballot = BallotLine::parse(line)
+        .map_err(|err| format!("Invalid ballot, expected `<public key>|<vote>|<vote proof>|<address>[|<sequence>|<signature>[|<dleq proof>[|<weight>]]]`: {}", err))?;
+    check_point(&ballot.public_key, "public key")?;
+    check_point(&ballot.vote_proof, "vote proof")?;
+    Ok(ballot)
+}
+
+/// This function parses a point of G1, the point must be on the curve and must not be the identity
+fn parse_point(raw_point: &str, name: &str) -> Result<G1, String> {
+    let point = G1::from_str_c(raw_point)
+        .map_err(|err| format!("There was an error parsing the {}: {}", name, err))?;
+    check_point(&point, name)?;
+    Ok(point)
+}
+
+fn check_point(point: &G1, name: &str) -> Result<(), String> {
+    if point.is_zero() || !point.into_affine().is_on_curve() {
+        return Err(format!("The {} is not a valid point of the curve", name));
+    }
+    Ok(())
+}
+
+fn parse_key_proof(raw_key_proof: &str) -> Result<SchnorrKnowledgeProof, String> {
+    let key_proof = SchnorrKnowledgeProof::from_str_c(raw_key_proof)
+        .map_err(|err| format!("There was an error parsing the key proof: {}", err))?;
+    if key_proof.t.is_zero() || !key_proof.t.into_affine().is_on_curve() {
+        return Err("The key proof is not valid, its point is not on the curve".to_string());
+    }
+    Ok(key_proof)
+}
+
+/// This function asks for a point until a valid one is provided
+fn read_point(prompt: &str, name: &str) -> Result<G1, String> {
+    loop {
+        match parse_point(&read_line(prompt)?, name) {
+            Ok(point) => return Ok(point),
+            Err(err) => eprintln!("{}", format!("{}.\nPlease provide a valid {}", err, name).red()),
+        }
+    }
+}
+
+/// This function asks for a Schnorr key proof until a valid one is provided
+fn read_key_proof() -> Result<SchnorrKnowledgeProof, String> {
+    loop {
+        match parse_key_proof(&read_line("Please provide the Schnorr key proof:")?) {
+            Ok(key_proof) => return Ok(key_proof),
+            Err(err) => eprintln!("{}", format!("{}.\nPlease provide a valid key proof", err).red()),
+        }
+    }
+}
+
+/// This function asks the user what to verify, returns whether to verify a ballot and whether to verify a key proof
+fn get_verify_choice() -> Result<(bool, bool), String> {
+    loop {
+        println!("\nWhat do you want to verify?");
+        println!("1 - A ballot");
+        println!("2 - A Schnorr key proof");
+        println!("3 - Both");
+        match read_line("Please select an option:")?.as_str() {
+            "1" => return Ok((true, false)),
+            "2" => return Ok((false, true)),
+            "3" => return Ok((true, true)),
+            _ => eprintln!("{}", "Invalid choice. Please try again.".red()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use batravot_lib::{ScalarField, voter};
+    use batravot_lib::ethereum;
+    use web3::types::Address;
+    use super::*;
+
+    /// Returns the public key, the vote proof of a `For` vote in the election 1 and the address of a ballot
+    fn ballot_fields() -> (String, String, String) {
+        let prk = ScalarField::from(9u64);
+        (voter::generate_public_key(&prk).solidity_repr(),
+         voter::generate_vote_proof(&prk, &ElectionSpecifiers::from(1).forr.0).solidity_repr(),
+         Address::from_low_u64_be(1).solidity_repr())
+    }
+
+    #[test]
+    fn parses_the_ballot_lines() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        let ballot = parse_ballot_line(&format!(" {} | for | {} | {} ", public_key, vote_proof, address)).unwrap();
+        assert_eq!(ballot.public_key.solidity_repr(), public_key);
+        assert_eq!((ballot.vote, ballot.address), (Vote::For, Address::from_low_u64_be(1)));
+        assert!(ballot.signature.is_none() && ballot.dleq_proof.is_none() && ballot.weight.is_none());
+
+        let sign_key = ethereum::parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let typed_ballot = TypedBallot { election_id: 1, vote: Vote::For, vote_proof: ballot.vote_proof, sequence: 7 };
+        let domain = ethereum::BallotDomain { chain_id: 1, verifying_contract: Address::from_low_u64_be(0xc0) };
+        let signature = typed_ballot.sign(&domain, &sign_key);
+        let ballot = parse_ballot_line(&format!("{}|for|{}|{}|7|{}", public_key, vote_proof, address, signature.solidity_repr())).unwrap();
+        assert_eq!(ballot.signature.map(|(sequence, _)| sequence), Some(7));
+
+        // The sequence and the signature of a weighted ballot that is not signed are empty
+        let ballot = parse_ballot_line(&format!("{}|against|{}|{}||||40", public_key, vote_proof, address)).unwrap();
+        assert_eq!((ballot.vote, ballot.weight, ballot.signature.is_none()), (Vote::Against, Some(40), true));
+    }
+
+    /// Returns the configuration of a non-interactive verification in the election 1, written to the output file
+    fn verify_config(output: &std::path::Path) -> VoterConfig {
+        VoterConfig {
+            election_id: Some(1),
+            interactive: false,
+            format: crate::config::OutputFormat::Json,
+            output: Some(output.display().to_string()),
+            contract: Some(Address::from_low_u64_be(0xc0)),
+            chain_id: Some(1),
+            ..VoterConfig::default()
+        }
+    }
+
+    /// Runs the verification and returns its result and the checks in the output
+    fn run_verify(config: VoterConfig) -> (Result<(), String>, serde_json::Value) {
+        let result = verify(&config);
+        let output = std::fs::read_to_string(config.output.as_ref().unwrap()).unwrap();
+        (result, serde_json::from_str(&output).unwrap())
+    }
+
+    #[test]
+    fn verifies_a_signed_ballot_and_a_key_proof() {
+        let dir = tempfile::tempdir().unwrap();
+        let prk = ScalarField::from(9u64);
+        let (public_key, vote_proof, _) = ballot_fields();
+        let sign_key = ethereum::parse_secret_key("0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318").unwrap();
+        let domain = ethereum::BallotDomain { chain_id: 1, verifying_contract: Address::from_low_u64_be(0xc0) };
+        let typed_ballot = TypedBallot { election_id: 1, vote: Vote::For, vote_proof: G1::from_str_c(&vote_proof).unwrap(), sequence: 7 };
+        let signature = typed_ballot.sign(&domain, &sign_key);
+        let address = ethereum::address(&sign_key).solidity_repr();
+        let key_proof = SchnorrKnowledgeProof::generate_key_proof(&prk, &mut ark_std::test_rng()).solidity_repr();
+
+        let (result, output) = run_verify(VoterConfig {
+            ballot: Some(format!("{}|for|{}|{}|7|{}", public_key, vote_proof, address, signature.solidity_repr())),
+            key_proof: Some(key_proof.clone()),
+            ..verify_config(&dir.path().join("signed.json"))
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!((&output["voteProof"], &output["signature"], &output["keyProof"], &output["result"]),
+            (&"pass".into(), &"pass".into(), &"pass".into(), &"pass".into()));
+
+        // The signature is for the election 1 only
+        let (result, output) = run_verify(VoterConfig {
+            ballot: Some(format!("{}|for|{}|{}|7|{}", public_key, vote_proof, address, signature.solidity_repr())),
+            election_id: Some(2),
+            ..verify_config(&dir.path().join("other_election.json"))
+        });
+        assert!(result.is_err());
+        assert_eq!((&output["voteProof"], &output["signature"], &output["result"]), (&"fail".into(), &"fail".into(), &"fail".into()));
+
+        // The key proof is for the public key only
+        let (result, output) = run_verify(VoterConfig {
+            public_key: Some(voter::generate_public_key(&ScalarField::from(10u64)).solidity_repr()),
+            key_proof: Some(key_proof),
+            ..verify_config(&dir.path().join("other_key.json"))
+        });
+        assert!(result.is_err());
+        assert_eq!((&output["keyProof"], &output["result"]), (&"fail".into(), &"fail".into()));
+    }
+
+    #[test]
+    fn fails_a_ballot_without_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let (public_key, vote_proof, address) = ballot_fields();
+        let (result, output) = run_verify(VoterConfig {
+            ballot: Some(format!("{}|for|{}|{}", public_key, vote_proof, address)),
+            ..verify_config(&dir.path().join("unsigned.json"))
+        });
+        assert_eq!(result, Err("The verification failed".to_string()));
+        assert_eq!((&output["voteProof"], &output["signature"], &output["result"]), (&"pass".into(), &"missing".into(), &"fail".into()));
+    }
+
+    #[test]
+    fn rejects_invalid_ballot_lines() {
+        let (public_key, vote_proof, address) = ballot_fields();
+        for line in [
+            format!("{}|for|{}", public_key, vote_proof),
+            format!("{}|for|{}|{}|7", public_key, vote_proof, address),
+            format!("{}|maybe|{}|{}", public_key, vote_proof, address),
+            format!("{}|for|{}|{}", public_key, public_key, "0x01"),
+            format!("{}|for|{}|{}|seven|0x01", public_key, vote_proof, address),
+            format!("{}|for|{}|{}||||many", public_key, vote_proof, address),
+            format!("{}|for|{}|{}||||0", public_key, vote_proof, address),
+            format!("{}|for|{}|{}", G1::zero().solidity_repr(), vote_proof, address),
+        ] {
+            assert!(parse_ballot_line(&line).is_err(), "{}", line);
+        }
+    }
+}